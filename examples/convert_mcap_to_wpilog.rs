@@ -0,0 +1,28 @@
+use std::{
+    env,
+    fs::File,
+    io::{BufWriter, Read},
+    path::Path,
+};
+
+use wpilog_reader::mcap::convert::convert_to_wpilog;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let in_path = Path::new(&args[1]);
+    let mut infile = File::open(&args[1]).unwrap();
+
+    let mut content = Vec::new();
+    infile.read_to_end(&mut content).unwrap();
+
+    let wpilog_fname = format!(
+        "{}/{}.wpilog",
+        in_path.parent().unwrap().to_str().unwrap(),
+        in_path.file_stem().unwrap().to_str().unwrap()
+    );
+    let wpilog_file = BufWriter::new(File::create(&wpilog_fname).unwrap());
+
+    convert_to_wpilog(&content, wpilog_file).unwrap();
+
+    println!("Converted {} to {}", args[1], wpilog_fname);
+}
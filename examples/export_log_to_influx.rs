@@ -0,0 +1,70 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Read},
+};
+
+use wpilog_reader::{
+    export::influx::{export_influx, InfluxOptions},
+    filter::EntryFilter,
+    wpilog::parser::parse_wpilog,
+};
+
+fn parse_args(args: &[String]) -> (EntryFilter, InfluxOptions, Option<String>) {
+    let mut options = InfluxOptions::default();
+    let mut tags = Vec::new();
+    let mut output = None;
+    let (filter, rest) = EntryFilter::from_args(args).unwrap();
+    let mut args = rest.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--separator" => {
+                options.separator = args.next().expect("--separator requires a string").clone();
+            }
+            "--measurement" => {
+                options.measurement = args.next().expect("--measurement requires a name").clone();
+            }
+            "--tag" => {
+                let tag = args.next().expect("--tag requires <key>=<entry>");
+                let (key, entry) = tag.split_once('=').expect("--tag requires <key>=<entry>");
+                tags.push((key.to_string(), entry.to_string()));
+            }
+            "--strings" => options.strings = true,
+            "--output" => {
+                output = Some(args.next().expect("--output requires a path").clone());
+            }
+            _ => panic!("unknown argument: {}", arg),
+        }
+    }
+
+    if !tags.is_empty() {
+        options.tags = tags;
+    }
+
+    (filter, options, output)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (filter, options, output) = parse_args(&args[2..]);
+    let mut infile = File::open(&args[1]).unwrap();
+
+    let mut content = Vec::new();
+    infile.read_to_end(&mut content).unwrap();
+
+    let parsed_log = parse_wpilog(&content).unwrap().1;
+
+    let metadata = filter.filter_entries(&parsed_log.get_entry_metadata());
+
+    match output {
+        Some(path) => export_influx(
+            BufWriter::new(File::create(path).unwrap()),
+            &parsed_log,
+            &metadata,
+            &options,
+        ),
+        None => export_influx(io::stdout().lock(), &parsed_log, &metadata, &options),
+    }
+    .unwrap();
+}
@@ -0,0 +1,64 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+};
+
+use wpilog_reader::{
+    export::jsonl::{export_jsonl, export_source_jsonl},
+    filter::EntryFilter,
+    mcap::{MCap, Parse, MAGIC},
+    source::LogSource,
+    wpilog::parser::parse_wpilog,
+};
+
+fn parse_args(args: &[String]) -> (EntryFilter, Option<String>) {
+    let mut control = None;
+    let (filter, rest) = EntryFilter::from_args(args).unwrap();
+    let mut args = rest.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--control" => {
+                control = Some(args.next().expect("--control requires a path").clone());
+            }
+            _ => panic!("unknown argument: {}", arg),
+        }
+    }
+
+    (filter, control)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (filter, control_path) = parse_args(&args[2..]);
+    let mut infile = File::open(&args[1]).unwrap();
+
+    let mut content = Vec::new();
+    infile.read_to_end(&mut content).unwrap();
+
+    if content.starts_with(MAGIC) {
+        // MCAP files have no control records, every line is a message
+        assert!(control_path.is_none(), "--control requires a wpilog file");
+
+        let (_, mcap) = MCap::parse(&content).unwrap();
+        let entries = mcap.filter_entries(&filter).unwrap();
+        export_source_jsonl(io::stdout().lock(), &mcap, &entries).unwrap();
+
+        return;
+    }
+
+    let parsed_log = parse_wpilog(&content).unwrap().1;
+
+    let metadata = filter.filter_entries(&parsed_log.get_entry_metadata());
+
+    let mut control_file = control_path.map(|path| BufWriter::new(File::create(path).unwrap()));
+
+    export_jsonl(
+        io::stdout().lock(),
+        control_file.as_mut().map(|file| file as &mut dyn Write),
+        &parsed_log,
+        &metadata,
+    )
+    .unwrap();
+}
@@ -0,0 +1,36 @@
+use std::{
+    env,
+    fs::File,
+    io::{BufWriter, Read},
+    path::Path,
+};
+
+use wpilog_reader::{export::mcap::export_mcap, filter::EntryFilter, wpilog::parser::parse_wpilog};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let in_path = Path::new(&args[1]);
+    let (filter, rest) = EntryFilter::from_args(&args[2..]).unwrap();
+    if let Some(arg) = rest.first() {
+        panic!("unknown argument: {}", arg);
+    }
+    let mut infile = File::open(&args[1]).unwrap();
+
+    let mut content = Vec::new();
+    infile.read_to_end(&mut content).unwrap();
+
+    let parsed_log = parse_wpilog(&content).unwrap().1;
+
+    let metadata = filter.filter_entries(&parsed_log.get_entry_metadata());
+
+    let mcap_fname = format!(
+        "{}/{}.mcap",
+        in_path.parent().unwrap().to_str().unwrap(),
+        in_path.file_stem().unwrap().to_str().unwrap()
+    );
+    let mcap_file = BufWriter::new(File::create(&mcap_fname).unwrap());
+
+    export_mcap(mcap_file, &parsed_log, &metadata).unwrap();
+
+    println!("Exported {} entries to {}", metadata.len(), mcap_fname);
+}
@@ -0,0 +1,69 @@
+use std::{
+    env,
+    fs::File,
+    io::{BufWriter, Read},
+    path::Path,
+};
+
+use wpilog_reader::{
+    export::columnar::{export_ipc, export_parquet, Layout},
+    filter::EntryFilter,
+    wpilog::parser::parse_wpilog,
+};
+
+struct Options {
+    filter: EntryFilter,
+    layout: Layout,
+    ipc: bool,
+}
+
+fn parse_args(args: &[String]) -> Options {
+    let (filter, rest) = EntryFilter::from_args(args).unwrap();
+    let mut options = Options {
+        filter,
+        layout: Layout::Wide,
+        ipc: false,
+    };
+
+    for arg in rest {
+        match arg.as_str() {
+            "--tidy" => options.layout = Layout::Tidy,
+            "--ipc" => options.ipc = true,
+            _ => panic!("unknown argument: {}", arg),
+        }
+    }
+
+    options
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let in_path = Path::new(&args[1]);
+    let options = parse_args(&args[2..]);
+    let mut infile = File::open(&args[1]).unwrap();
+
+    let mut content = Vec::new();
+    infile.read_to_end(&mut content).unwrap();
+
+    let parsed_log = parse_wpilog(&content).unwrap().1;
+
+    let metadata = options
+        .filter
+        .filter_entries(&parsed_log.get_entry_metadata());
+
+    let out_fname = format!(
+        "{}/{}.{}",
+        in_path.parent().unwrap().to_str().unwrap(),
+        in_path.file_stem().unwrap().to_str().unwrap(),
+        if options.ipc { "arrow" } else { "parquet" }
+    );
+    let out_file = BufWriter::new(File::create(&out_fname).unwrap());
+
+    if options.ipc {
+        export_ipc(out_file, &parsed_log, &metadata, options.layout).unwrap();
+    } else {
+        export_parquet(out_file, &parsed_log, &metadata, options.layout).unwrap();
+    }
+
+    println!("Exported {} entries to {}", metadata.len(), out_fname);
+}
@@ -0,0 +1,45 @@
+use std::{env, fs::File, io::Read, path::Path};
+
+use rusqlite::Connection;
+use wpilog_reader::{
+    export::sqlite::export_sqlite, filter::EntryFilter, wpilog::parser::parse_wpilog,
+};
+
+fn process_log_file(conn: &mut Connection, in_path: &Path, filter: &EntryFilter) {
+    let mut infile = File::open(in_path).unwrap();
+
+    let mut content = Vec::new();
+    infile.read_to_end(&mut content).unwrap();
+
+    let parsed_log = parse_wpilog(&content).unwrap().1;
+
+    let metadata = filter.filter_entries(&parsed_log.get_entry_metadata());
+
+    let log_id = export_sqlite(
+        conn,
+        in_path.file_name().unwrap().to_str().unwrap(),
+        &parsed_log,
+        &metadata,
+    )
+    .unwrap();
+
+    println!("Exported {} entries as log {}", metadata.len(), log_id);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (filter, rest) = EntryFilter::from_args(&args[3..]).unwrap();
+    if let Some(arg) = rest.first() {
+        panic!("unknown argument: {}", arg);
+    }
+
+    let mut conn = Connection::open(&args[1]).unwrap();
+
+    for path in glob::glob(&args[2])
+        .unwrap_or_else(|_| panic!("{} is not globbable", args[2]))
+        .flatten()
+    {
+        println!("processing {}", path.to_str().unwrap());
+        process_log_file(&mut conn, path.as_path(), &filter)
+    }
+}
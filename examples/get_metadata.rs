@@ -1,16 +1,20 @@
-use std::{env, fs::File, io::Read};
-
-use wpilog_reader::wpilog::parser::parse_wpilog;
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut file = File::open(&args[1]).unwrap();
-
-    let mut content = Vec::new();
-    file.read_to_end(&mut content).unwrap();
-
-    let parsed_log = parse_wpilog(&content).unwrap().1;
-    let metadata = parsed_log.get_entry_metadata();
-
-    println!("{:#?}", metadata);
-}
+use std::{env, fs::File, io::Read};
+
+use wpilog_reader::{filter::EntryFilter, wpilog::parser::parse_wpilog};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (filter, rest) = EntryFilter::from_args(&args[2..]).unwrap();
+    if let Some(arg) = rest.first() {
+        panic!("unknown argument: {}", arg);
+    }
+    let mut file = File::open(&args[1]).unwrap();
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).unwrap();
+
+    let parsed_log = parse_wpilog(&content).unwrap().1;
+    let metadata = filter.filter_entries(&parsed_log.get_entry_metadata());
+
+    println!("{:#?}", metadata);
+}
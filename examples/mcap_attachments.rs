@@ -0,0 +1,72 @@
+use std::{env, fs::File, io::BufReader, path::Path, process};
+
+use wpilog_reader::mcap::attachments::{
+    extract_attachments, list_attachments, metadata_json, read_attachments, read_metadata,
+};
+
+fn open(path: &str) -> BufReader<File> {
+    BufReader::new(File::open(path).unwrap())
+}
+
+fn list(path: &str) {
+    println!("name\tmedia_type\tlog_time\tcreate_time\tsize");
+    for attachment in list_attachments(open(path)).unwrap() {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            attachment.name,
+            attachment.media_type,
+            attachment.log_time,
+            attachment.create_time,
+            attachment.size
+        );
+    }
+}
+
+/// Writes every attachment with a valid CRC to `out_dir`, returning whether all were extracted.
+fn extract(path: &str, out_dir: &Path) -> bool {
+    let attachments = read_attachments(open(path)).unwrap();
+
+    let mut valid = true;
+    for (attachment, result) in attachments
+        .iter()
+        .zip(extract_attachments(&attachments, out_dir))
+    {
+        match result {
+            Ok(out_path) => println!("Extracted {} to {}", attachment.name(), out_path.display()),
+            Err(err) => {
+                println!("{}: {}", attachment.name(), err);
+                valid = false;
+            }
+        }
+    }
+
+    valid
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: mcap_attachments <list | extract <output dir> | metadata> <path to mcap file>"
+    );
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("list") if args.len() == 3 => list(&args[2]),
+        Some("extract") if args.len() == 4 => {
+            if !extract(&args[3], Path::new(&args[2])) {
+                process::exit(1);
+            }
+        }
+        Some("metadata") if args.len() == 3 => {
+            let metadata = read_metadata(open(&args[2])).unwrap();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&metadata_json(&metadata)).unwrap()
+            );
+        }
+        _ => usage(),
+    }
+}
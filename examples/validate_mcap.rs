@@ -1,6 +1,6 @@
-use std::{env, fs::File, io::Read};
+use std::{env, fs::File, io::Read, process};
 
-use wpilog_reader::mcap::{MCap, Parse, Record};
+use wpilog_reader::mcap::{lint::lint, validate::check_crcs};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -9,32 +9,30 @@ fn main() {
     let mut content = Vec::new();
     file.read_to_end(&mut content).unwrap();
 
-    let parsed_log = MCap::parse(&content);
-
-    match parsed_log {
-        Ok((_, log)) => {
-            println!("Parse successful - entries: {}", log.records.len());
-            for record in log.records {
-                let parsed = Record::try_from(record);
+    let findings = lint(&content);
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    let mut valid = findings.is_empty();
 
-                if let Ok(Record::Chunk(chunk)) = parsed {
-                    println!("Entering Chunk");
-                    match chunk.parse_inner() {
-                        Ok((_, records)) => {
-                            for record in records {
-                                let parsed = Record::try_from(record);
-        
-                                println!("{:?}", parsed);
-                            }
-                        },
-                        _ => {}
-                    }
-                    println!("Exiting Chunk");
-                } else {
-                    println!("{:?}", parsed);
-                }
+    match check_crcs(&content) {
+        Ok(mismatches) => {
+            for mismatch in &mismatches {
+                println!("{}", mismatch);
             }
+            valid &= mismatches.is_empty();
+        }
+        // the linter already reported where the file stops parsing
+        Err(_) if !valid => {}
+        Err(e) => {
+            println!("{}", e);
+            valid = false;
         }
-        Err(e) => println!("{}", e),
     }
+
+    if !valid {
+        process::exit(1);
+    }
+
+    println!("Valid MCAP file");
 }
@@ -0,0 +1,128 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use wpilog_reader::mcap::{
+    attachments::{
+        extract_attachments, list_attachments, metadata_json, read_attachments, read_metadata,
+    },
+    MAGIC,
+};
+
+use crate::{for_each_input, print_json, Result};
+
+#[derive(Serialize)]
+struct Extracted {
+    name: String,
+    path: Option<PathBuf>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct FileMetadata {
+    path: PathBuf,
+    metadata: Value,
+}
+
+fn read_mcap(path: &Path) -> Result<Cursor<Vec<u8>>> {
+    let content = fs::read(path)?;
+    if !content.starts_with(MAGIC) {
+        return Err("not an MCAP file".into());
+    }
+
+    Ok(Cursor::new(content))
+}
+
+/// Lists the attachments of an MCAP file, or writes them below `out_dir`. Returns whether every
+/// attachment could be extracted.
+pub fn attachments(file: &Path, out_dir: Option<&Path>, json: bool) -> Result<bool> {
+    let mcap = read_mcap(file)?;
+
+    let Some(out_dir) = out_dir else {
+        let attachments = list_attachments(mcap)?;
+        if json {
+            print_json(&attachments)?;
+            return Ok(true);
+        }
+
+        for attachment in &attachments {
+            println!(
+                "{}: {}, {} bytes, log time {}, create time {}",
+                attachment.name,
+                attachment.media_type,
+                attachment.size,
+                attachment.log_time,
+                attachment.create_time
+            );
+        }
+        return Ok(true);
+    };
+
+    let attachments = read_attachments(mcap)?;
+    let extracted = attachments
+        .iter()
+        .zip(extract_attachments(&attachments, out_dir))
+        .map(|(attachment, result)| {
+            let (path, error) = match result {
+                Ok(path) => (Some(path), None),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            Extracted {
+                name: attachment.name().to_string(),
+                path,
+                error,
+            }
+        })
+        .collect::<Vec<_>>();
+    let ok = extracted.iter().all(|extracted| extracted.error.is_none());
+
+    if json {
+        print_json(&extracted)?;
+        return Ok(ok);
+    }
+
+    for extracted in &extracted {
+        match (&extracted.path, &extracted.error) {
+            (Some(path), _) => println!("Extracted {} to {}", extracted.name, path.display()),
+            (_, error) => eprintln!(
+                "{}: {}: {}",
+                file.display(),
+                extracted.name,
+                error.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+
+    Ok(ok)
+}
+
+pub fn metadata(inputs: &[PathBuf], json: bool) -> Result<bool> {
+    let (files, ok) = for_each_input(inputs, |path| {
+        Ok(FileMetadata {
+            path: path.to_path_buf(),
+            metadata: metadata_json(&read_metadata(read_mcap(path)?)?),
+        })
+    });
+
+    if json {
+        print_json(&files)?;
+        return Ok(ok);
+    }
+
+    for file in &files {
+        println!("{}:", file.path.display());
+        for record in file.metadata.as_array().into_iter().flatten() {
+            println!("  {}:", record["name"].as_str().unwrap_or_default());
+            for (key, value) in record["metadata"].as_object().into_iter().flatten() {
+                println!("    {}: {}", key, value.as_str().unwrap_or_default());
+            }
+        }
+    }
+
+    Ok(ok)
+}
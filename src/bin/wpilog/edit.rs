@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use wpilog_reader::{
+    export::mcap::export_mcap,
+    filter::EntryFilter,
+    mcap::{convert::convert_to_wpilog, MAGIC},
+    wpilog::edit,
+};
+
+use crate::{expand_inputs, parse_wpilog_content, print_json, read_wpilog, usage, Log, Result};
+
+#[derive(Serialize)]
+struct Written<'a> {
+    inputs: &'a [PathBuf],
+    output: &'a Path,
+}
+
+fn report(inputs: &[PathBuf], output: &Path, json: bool) -> Result<bool> {
+    if json {
+        print_json(&Written { inputs, output })?;
+    } else {
+        println!("Wrote {}", output.display());
+    }
+
+    Ok(true)
+}
+
+fn single_input(file: &str) -> Result<PathBuf> {
+    let mut inputs = expand_inputs(&[file.to_string()])?;
+    if inputs.len() != 1 {
+        return Err(usage(format!("{} matches more than one file", file)).into());
+    }
+
+    Ok(inputs.remove(0))
+}
+
+fn seconds_to_us(seconds: f64) -> u64 {
+    (seconds * 1e6).round() as u64
+}
+
+pub fn slice(
+    file: &str,
+    start: f64,
+    end: Option<f64>,
+    output: &Path,
+    filter: &EntryFilter,
+    json: bool,
+) -> Result<bool> {
+    if start < 0.0 || end.is_some_and(|end| end < start) {
+        return Err(usage("the slice has to start at 0 or later and end after its start").into());
+    }
+    let input = single_input(file)?;
+
+    let content = fs::read(&input)?;
+    let log = Log::parse(&content)?;
+    let source = log.source();
+    let entries = source.filter_entries(filter)?;
+    let end_us = end.map(seconds_to_us).unwrap_or(u64::MAX);
+
+    let sliced = edit::slice_source(Vec::new(), source, &entries, seconds_to_us(start), end_us)?;
+    fs::write(output, sliced)?;
+
+    report(&[input], output, json)
+}
+
+pub fn merge(inputs: &[PathBuf], output: &Path, filter: &EntryFilter, json: bool) -> Result<bool> {
+    let contents = inputs
+        .iter()
+        .map(|input| read_wpilog(input).map_err(|err| format!("{}: {}", input.display(), err)))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let logs = contents
+        .iter()
+        .zip(inputs)
+        .map(|(content, input)| {
+            parse_wpilog_content(content).map_err(|err| format!("{}: {}", input.display(), err))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let merged = edit::merge(Vec::new(), &logs.iter().collect::<Vec<_>>(), filter)?;
+    fs::write(output, merged)?;
+
+    report(inputs, output, json)
+}
+
+/// Converts between the two formats, picking the direction from the input.
+pub fn convert(
+    file: &str,
+    output: Option<PathBuf>,
+    filter: &EntryFilter,
+    json: bool,
+) -> Result<bool> {
+    let input = single_input(file)?;
+    let content = fs::read(&input)?;
+    let is_mcap = content.starts_with(MAGIC);
+
+    let output = output.unwrap_or_else(|| match is_mcap {
+        true => input.with_extension("wpilog"),
+        false => input.with_extension("mcap"),
+    });
+    if output == input {
+        return Err(usage("the output would overwrite the input").into());
+    }
+
+    let converted = if is_mcap {
+        let converted = convert_to_wpilog(&content, Vec::new())?;
+        if filter.is_empty() {
+            converted
+        } else {
+            // filter the converted log by slicing all of it
+            let log = parse_wpilog_content(&converted)?;
+            let metadata = filter.filter_entries(&log.get_entry_metadata());
+            edit::slice(Vec::new(), &log, &metadata, 0, u64::MAX)?
+        }
+    } else {
+        let log = parse_wpilog_content(&content)?;
+        let metadata = filter.filter_entries(&log.get_entry_metadata());
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &metadata)?;
+        converted
+    };
+    fs::write(&output, converted)?;
+
+    report(&[input], &output, json)
+}
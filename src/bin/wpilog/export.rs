@@ -0,0 +1,264 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Subcommand};
+use serde::Serialize;
+
+use wpilog_reader::{
+    export::{
+        jsonl::{export_jsonl, export_source_jsonl},
+        mcap_csv::export_mcap_channels,
+        tidy::{export_source_tidy, ArrayMode},
+        wide::{export_metadata, export_source_csv, export_types},
+    },
+    filter::EntryFilter,
+    source::LogSource,
+};
+
+use crate::{for_each_input, print_json, FilterArgs, Log, Result};
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Export to CSV, one column per entry or one row per sample
+    ///
+    /// Writes `<name>-data.csv`, `<name>-types.csv` and `<name>-metadata.csv`, or a single
+    /// `<name>-tidy.csv` with --tidy. MCAP files also get `<name>-channels.csv`.
+    Csv {
+        #[command(flatten)]
+        args: ExportArgs,
+        /// One row per sample instead of one column per entry, with arrays as JSON
+        #[arg(long)]
+        tidy: bool,
+        /// Like --tidy, with one row per array element
+        #[arg(long)]
+        explode_arrays: bool,
+    },
+    /// Export to `<name>.jsonl`, one sample per line
+    Jsonl {
+        #[command(flatten)]
+        args: ExportArgs,
+        /// Also write the control records of wpilogs to `<name>-control.jsonl`
+        #[arg(long)]
+        control: bool,
+    },
+    /// Export to `<name>.parquet`, or `<name>.arrow` with --ipc
+    #[cfg(feature = "parquet")]
+    Parquet {
+        #[command(flatten)]
+        args: ExportArgs,
+        /// One row per sample instead of one column per entry
+        #[arg(long)]
+        tidy: bool,
+        /// Write an Arrow IPC file instead
+        #[arg(long)]
+        ipc: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[arg(required = true)]
+    files: Vec<String>,
+    /// Directory to write to, instead of the directory of each input
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+#[derive(Serialize)]
+struct Exported {
+    input: PathBuf,
+    outputs: Vec<PathBuf>,
+}
+
+/// Names outputs after their input, `<dir>/<stem><suffix>`.
+struct Outputs<'a> {
+    input: &'a Path,
+    dir: Option<&'a Path>,
+    paths: Vec<PathBuf>,
+}
+
+impl<'a> Outputs<'a> {
+    fn new(input: &'a Path, dir: Option<&'a Path>) -> Self {
+        Outputs {
+            input,
+            dir,
+            paths: Vec::new(),
+        }
+    }
+
+    fn create(&mut self, suffix: &str) -> Result<BufWriter<File>> {
+        let dir = self.dir.or(self.input.parent()).unwrap_or(Path::new(""));
+        let stem = self.input.file_stem().unwrap_or(self.input.as_os_str());
+        let mut name = stem.to_os_string();
+        name.push(suffix);
+
+        let path = dir.join(name);
+        let file = File::create(&path)?;
+        self.paths.push(path);
+
+        Ok(BufWriter::new(file))
+    }
+
+    fn finish(self) -> Exported {
+        Exported {
+            input: self.input.to_path_buf(),
+            outputs: self.paths,
+        }
+    }
+}
+
+fn export_csv_file(
+    outputs: &mut Outputs,
+    filter: &EntryFilter,
+    tidy: Option<ArrayMode>,
+) -> Result<()> {
+    let content = fs::read(outputs.input)?;
+    let log = Log::parse(&content)?;
+    let source = log.source();
+    let entries = source.filter_entries(filter)?;
+
+    if let Log::Mcap(mcap) = &log {
+        export_mcap_channels(outputs.create("-channels.csv")?, mcap)?;
+    }
+
+    if let Some(arrays) = tidy {
+        export_source_tidy(outputs.create("-tidy.csv")?, source, &entries, arrays)?;
+    } else {
+        export_types(outputs.create("-types.csv")?, &entries)?;
+        export_metadata(outputs.create("-metadata.csv")?, &entries)?;
+        export_source_csv(outputs.create("-data.csv")?, source, &entries)?;
+    }
+
+    Ok(())
+}
+
+fn export_jsonl_file(outputs: &mut Outputs, filter: &EntryFilter, control: bool) -> Result<()> {
+    let content = fs::read(outputs.input)?;
+
+    match Log::parse(&content)? {
+        Log::Mcap(mcap) => {
+            // MCAP files have no control records, every line is a message
+            if control {
+                return Err("--control requires a wpilog file".into());
+            }
+
+            let entries = mcap.filter_entries(filter)?;
+            let mut out = outputs.create(".jsonl")?;
+            export_source_jsonl(&mut out, &mcap, &entries)?;
+            out.flush()?;
+        }
+        Log::Wpilog(log) => {
+            let metadata = filter.filter_entries(&log.get_entry_metadata());
+            let mut control = match control {
+                true => Some(outputs.create("-control.jsonl")?),
+                false => None,
+            };
+
+            let mut out = outputs.create(".jsonl")?;
+            export_jsonl(
+                &mut out,
+                control.as_mut().map(|file| file as &mut dyn Write),
+                &log,
+                &metadata,
+            )?;
+            out.flush()?;
+            if let Some(mut control) = control {
+                control.flush()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Exports wpilogs, and MCAP files converted to wpilogs, to Parquet or Arrow IPC.
+#[cfg(feature = "parquet")]
+fn export_parquet_file(
+    outputs: &mut Outputs,
+    filter: &EntryFilter,
+    tidy: bool,
+    ipc: bool,
+) -> Result<()> {
+    use wpilog_reader::export::columnar::{export_ipc, export_parquet, Layout};
+
+    let content = crate::read_wpilog(outputs.input)?;
+    let log = crate::parse_wpilog_content(&content)?;
+    let metadata = filter.filter_entries(&log.get_entry_metadata());
+    let layout = match tidy {
+        true => Layout::Tidy,
+        false => Layout::Wide,
+    };
+
+    if ipc {
+        export_ipc(outputs.create(".arrow")?, &log, &metadata, layout)?;
+    } else {
+        export_parquet(outputs.create(".parquet")?, &log, &metadata, layout)?;
+    }
+
+    Ok(())
+}
+
+pub fn export(command: ExportCommand, json: bool) -> Result<bool> {
+    let args = match &command {
+        ExportCommand::Csv { args, .. } | ExportCommand::Jsonl { args, .. } => args,
+        #[cfg(feature = "parquet")]
+        ExportCommand::Parquet { args, .. } => args,
+    };
+    let inputs = crate::expand_inputs(&args.files)?;
+    let filter = args.filter.filter()?;
+    if let Some(dir) = &args.out_dir {
+        fs::create_dir_all(dir)?;
+    }
+
+    let (exported, ok) = for_each_input(&inputs, |input| {
+        let mut outputs = Outputs::new(input, args.out_dir.as_deref());
+        match &command {
+            ExportCommand::Csv {
+                tidy,
+                explode_arrays,
+                ..
+            } => {
+                let arrays = match (tidy, explode_arrays) {
+                    (_, true) => Some(ArrayMode::Explode),
+                    (true, false) => Some(ArrayMode::Json),
+                    (false, false) => None,
+                };
+                export_csv_file(&mut outputs, &filter, arrays)?;
+            }
+            ExportCommand::Jsonl { control, .. } => {
+                export_jsonl_file(&mut outputs, &filter, *control)?
+            }
+            #[cfg(feature = "parquet")]
+            ExportCommand::Parquet { tidy, ipc, .. } => {
+                export_parquet_file(&mut outputs, &filter, *tidy, *ipc)?
+            }
+        }
+
+        Ok(outputs.finish())
+    });
+
+    if json {
+        print_json(&exported)?;
+        return Ok(ok);
+    }
+
+    for exported in &exported {
+        let outputs = exported
+            .outputs
+            .iter()
+            .map(|output| output.display().to_string())
+            .collect::<Vec<_>>();
+        println!(
+            "Exported {} to {}",
+            exported.input.display(),
+            outputs.join(", ")
+        );
+    }
+
+    Ok(ok)
+}
@@ -0,0 +1,291 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use wpilog_reader::{
+    filter::EntryFilter,
+    mcap::{MCap, Parse, Record, MAGIC},
+    statistics::statistics,
+};
+
+use crate::{for_each_input, print_json, Log, Result};
+
+#[derive(Serialize)]
+struct FileInfo {
+    path: PathBuf,
+    format: &'static str,
+    entries: usize,
+    samples: u64,
+    start_us: Option<u64>,
+    end_us: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct EntryInfo {
+    id: u32,
+    name: String,
+    #[serde(rename = "type")]
+    typ: String,
+    metadata: String,
+    samples: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct FileEntries {
+    path: PathBuf,
+    entries: Vec<EntryInfo>,
+}
+
+#[derive(Serialize)]
+struct ChannelInfo {
+    id: u16,
+    topic: String,
+    message_encoding: String,
+    schema: Option<String>,
+    schema_encoding: Option<String>,
+    messages: u64,
+}
+
+#[derive(Serialize)]
+struct McapInfo {
+    path: PathBuf,
+    profile: String,
+    library: String,
+    messages: u64,
+    start_ns: Option<u64>,
+    end_ns: Option<u64>,
+    chunks: u64,
+    compression: BTreeSet<String>,
+    attachments: u64,
+    metadata: u64,
+    channels: Vec<ChannelInfo>,
+}
+
+fn seconds(timestamp_us: Option<u64>) -> String {
+    match timestamp_us {
+        Some(timestamp_us) => format!("{:.6}", timestamp_us as f64 / 1e6),
+        None => "-".to_string(),
+    }
+}
+
+fn file_info(path: &Path) -> Result<FileInfo> {
+    let content = fs::read(path)?;
+    let log = Log::parse(&content)?;
+    let source = log.source();
+
+    let entries = source.entries()?;
+    let statistics = statistics(source, &entries)?;
+
+    Ok(FileInfo {
+        path: path.to_path_buf(),
+        format: log.format(),
+        entries: entries.len(),
+        samples: statistics.iter().map(|entry| entry.samples).sum(),
+        start_us: statistics
+            .iter()
+            .filter_map(|entry| entry.start_ns)
+            .min()
+            .map(|start_ns| start_ns / 1000),
+        end_us: statistics
+            .iter()
+            .filter_map(|entry| entry.end_ns)
+            .max()
+            .map(|end_ns| end_ns / 1000),
+    })
+}
+
+pub fn info(inputs: &[PathBuf], json: bool) -> Result<bool> {
+    let (infos, ok) = for_each_input(inputs, file_info);
+
+    if json {
+        print_json(&infos)?;
+        return Ok(ok);
+    }
+
+    for info in &infos {
+        println!(
+            "{}: {}, {} entries, {} samples, {} s to {} s",
+            info.path.display(),
+            info.format,
+            info.entries,
+            info.samples,
+            seconds(info.start_us),
+            seconds(info.end_us)
+        );
+    }
+
+    Ok(ok)
+}
+
+fn file_entries(path: &Path, filter: &EntryFilter) -> Result<FileEntries> {
+    let content = fs::read(path)?;
+    let log = Log::parse(&content)?;
+    let source = log.source();
+
+    let entries = source.filter_entries(filter)?;
+    let statistics = statistics(source, &entries)?;
+
+    Ok(FileEntries {
+        path: path.to_path_buf(),
+        entries: entries
+            .into_iter()
+            .zip(statistics)
+            .map(|(entry, statistics)| EntryInfo {
+                id: entry.id,
+                name: entry.name,
+                typ: entry.typ,
+                metadata: entry.metadata,
+                samples: statistics.samples,
+                min: statistics.min,
+                max: statistics.max,
+                mean: statistics.mean,
+            })
+            .collect(),
+    })
+}
+
+pub fn entries(inputs: &[PathBuf], filter: &EntryFilter, json: bool) -> Result<bool> {
+    let (files, ok) = for_each_input(inputs, |path| file_entries(path, filter));
+
+    if json {
+        print_json(&files)?;
+        return Ok(ok);
+    }
+
+    for file in &files {
+        if inputs.len() > 1 {
+            println!("{}:", file.path.display());
+        }
+        for entry in &file.entries {
+            let range = match (entry.min, entry.max, entry.mean) {
+                (Some(min), Some(max), Some(mean)) => {
+                    format!("  {} to {}, mean {}", min, max, mean)
+                }
+                _ => String::new(),
+            };
+            println!(
+                "{:>5}  {}  {}  {} samples{}  {}",
+                entry.id, entry.name, entry.typ, entry.samples, range, entry.metadata
+            );
+        }
+    }
+
+    Ok(ok)
+}
+
+fn mcap_file_info(path: &Path) -> Result<McapInfo> {
+    let content = fs::read(path)?;
+    if !content.starts_with(MAGIC) {
+        return Err("not an MCAP file".into());
+    }
+    let (_, mcap) = MCap::parse(&content).map_err(|err| format!("invalid MCAP file: {}", err))?;
+
+    let mut info = McapInfo {
+        path: path.to_path_buf(),
+        profile: String::new(),
+        library: String::new(),
+        messages: 0,
+        start_ns: None,
+        end_ns: None,
+        chunks: 0,
+        compression: BTreeSet::new(),
+        attachments: 0,
+        metadata: 0,
+        channels: Vec::new(),
+    };
+    for raw in &mcap.records {
+        match Record::try_from(*raw) {
+            Ok(Record::Header(header)) => {
+                info.profile = header.profile().to_string();
+                info.library = header.library().to_string();
+            }
+            Ok(Record::Chunk(chunk)) => {
+                info.chunks += 1;
+                info.compression.insert(chunk.compression().to_string());
+            }
+            Ok(Record::Attachment(_)) => info.attachments += 1,
+            Ok(Record::Metadata(_)) => info.metadata += 1,
+            _ => {}
+        }
+    }
+
+    let mut counts = HashMap::new();
+    for message in mcap.messages() {
+        let message = message?.message;
+        let log_time = message.log_time();
+        info.messages += 1;
+        info.start_ns = Some(info.start_ns.map_or(log_time, |start| start.min(log_time)));
+        info.end_ns = Some(info.end_ns.map_or(log_time, |end| end.max(log_time)));
+        *counts.entry(message.channel_id()).or_insert(0) += 1;
+    }
+
+    info.channels = mcap
+        .channels()?
+        .into_iter()
+        .map(|(channel, schema)| ChannelInfo {
+            id: channel.id(),
+            topic: channel.topic().to_string(),
+            message_encoding: channel.message_encoding().to_string(),
+            schema: schema.as_ref().map(|schema| schema.name().to_string()),
+            schema_encoding: schema.as_ref().map(|schema| schema.encoding().to_string()),
+            messages: counts.get(&channel.id()).copied().unwrap_or(0),
+        })
+        .collect();
+
+    Ok(info)
+}
+
+pub fn mcap_info(inputs: &[PathBuf], json: bool) -> Result<bool> {
+    let (infos, ok) = for_each_input(inputs, mcap_file_info);
+
+    if json {
+        print_json(&infos)?;
+        return Ok(ok);
+    }
+
+    for info in &infos {
+        println!("{}:", info.path.display());
+        println!("  profile: {}", info.profile);
+        println!("  library: {}", info.library);
+        println!(
+            "  messages: {}, {} s to {} s",
+            info.messages,
+            seconds(info.start_ns.map(|ns| ns / 1000)),
+            seconds(info.end_ns.map(|ns| ns / 1000))
+        );
+        println!(
+            "  chunks: {} ({})",
+            info.chunks,
+            info.compression
+                .iter()
+                .map(|compression| match compression.as_str() {
+                    "" => "none",
+                    compression => compression,
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!("  attachments: {}", info.attachments);
+        println!("  metadata: {}", info.metadata);
+        println!("  channels:");
+        for channel in &info.channels {
+            println!(
+                "  {:>5}  {}  {} {}  {} messages",
+                channel.id,
+                channel.topic,
+                channel.message_encoding,
+                channel.schema.as_deref().unwrap_or("(no schema)"),
+                channel.messages
+            );
+        }
+    }
+
+    Ok(ok)
+}
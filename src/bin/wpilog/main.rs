@@ -0,0 +1,325 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use clap::{Args, Parser, Subcommand};
+
+use wpilog_reader::{
+    filter::EntryFilter,
+    mcap::{convert::convert_to_wpilog, MCap, Parse, MAGIC},
+    source::LogSource,
+    wpilog::{parser::parse_wpilog, types::WpiLog},
+};
+
+mod attachments;
+mod edit;
+mod export;
+mod info;
+mod validate;
+
+/// A file that couldn't be processed.
+const EXIT_FAILURE: i32 = 1;
+/// Invalid arguments, like a pattern that matches no files.
+const EXIT_USAGE: i32 = 2;
+
+/// Inspect, validate, export and edit wpilog and MCAP files.
+///
+/// Every command reading files accepts both formats, told apart by their contents. Input paths
+/// may be glob patterns, quoted to keep the shell from expanding them.
+#[derive(Parser)]
+#[command(name = "wpilog", version)]
+struct Cli {
+    /// Print machine readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Summarize the entries, samples and time range of each file
+    Info {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Check that each file parses, and lint MCAP files; exits with 1 if any is invalid
+    Validate {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// List the entries of each file, with their types, metadata and sample counts
+    Entries {
+        #[arg(required = true)]
+        files: Vec<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Export each file to another format, next to it unless --out-dir is given
+    #[command(subcommand)]
+    Export(export::ExportCommand),
+    /// Write the samples between two timestamps to a new wpilog
+    Slice {
+        file: String,
+        /// Start of the slice in seconds
+        #[arg(long, default_value_t = 0.0)]
+        start: f64,
+        /// End of the slice in seconds, inclusive
+        #[arg(long)]
+        end: Option<f64>,
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Merge files into one wpilog, joining entries with the same name
+    Merge {
+        #[arg(required = true)]
+        files: Vec<String>,
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// MCAP specific commands
+    #[command(subcommand)]
+    Mcap(McapCommand),
+    /// Convert a wpilog to MCAP, or an MCAP file to a wpilog
+    Convert {
+        file: String,
+        /// Defaults to the input path with the other extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum McapCommand {
+    /// Show the header, statistics, channels and chunks of each file
+    Info {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Lint each file against the MCAP specification and check its CRCs
+    Validate {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// List the attachments of a file, or extract those with a valid CRC; exits with 1 if any
+    /// couldn't be extracted
+    Attachments {
+        file: PathBuf,
+        /// Write each attachment below this directory, at the path its name gives
+        #[arg(long, value_name = "DIR")]
+        extract: Option<PathBuf>,
+    },
+    /// Print the metadata records of each file
+    Metadata {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+}
+
+/// Entry selection, see [`EntryFilter`] for the pattern syntax.
+#[derive(Args)]
+struct FilterArgs {
+    /// Only keep entries matching this glob, or regex when prefixed with `re:`
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<String>,
+    /// Drop entries matching this glob, or regex when prefixed with `re:`
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+}
+
+impl FilterArgs {
+    fn filter(&self) -> std::result::Result<EntryFilter, UsageError> {
+        let mut filter = EntryFilter::new();
+        for pattern in &self.include {
+            filter = filter.include(pattern).map_err(usage)?;
+        }
+        for pattern in &self.exclude {
+            filter = filter.exclude(pattern).map_err(usage)?;
+        }
+
+        Ok(filter)
+    }
+}
+
+/// An error in the arguments, exiting with [`EXIT_USAGE`].
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+fn usage(err: impl fmt::Display) -> UsageError {
+    UsageError(err.to_string())
+}
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Expands glob patterns in `args`. Paths without glob characters are kept as they are, so
+/// missing files are reported when they are read.
+fn expand_inputs(args: &[String]) -> std::result::Result<Vec<PathBuf>, UsageError> {
+    let mut paths = Vec::new();
+    for arg in args {
+        if !arg.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+
+        let matches = glob::glob(arg)
+            .map_err(|err| usage(format!("{} is not a valid pattern: {}", arg, err)))?
+            .flatten()
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            return Err(usage(format!("no files match {}", arg)));
+        }
+        paths.extend(matches);
+    }
+
+    Ok(paths)
+}
+
+/// A parsed input file of either format.
+enum Log<'a> {
+    Wpilog(WpiLog<'a>),
+    Mcap(MCap<'a>),
+}
+
+impl<'a> Log<'a> {
+    fn parse(content: &'a [u8]) -> Result<Self> {
+        if content.starts_with(MAGIC) {
+            let (_, mcap) =
+                MCap::parse(content).map_err(|err| format!("invalid MCAP file: {}", err))?;
+            Ok(Log::Mcap(mcap))
+        } else {
+            let (_, log) =
+                parse_wpilog(content).map_err(|err| format!("invalid wpilog file: {}", err))?;
+            Ok(Log::Wpilog(log))
+        }
+    }
+
+    fn format(&self) -> &'static str {
+        match self {
+            Log::Wpilog(_) => "wpilog",
+            Log::Mcap(_) => "mcap",
+        }
+    }
+
+    fn source(&self) -> &dyn LogSource {
+        match self {
+            Log::Wpilog(log) => log,
+            Log::Mcap(mcap) => mcap,
+        }
+    }
+}
+
+/// Reads a file as a wpilog, converting MCAP files first, for the commands that write wpilogs
+/// or only support them.
+fn read_wpilog(path: &Path) -> Result<Vec<u8>> {
+    let content = fs::read(path)?;
+    if content.starts_with(MAGIC) {
+        return Ok(convert_to_wpilog(&content, Vec::new())?);
+    }
+
+    Ok(content)
+}
+
+fn parse_wpilog_content(content: &[u8]) -> Result<WpiLog<'_>> {
+    let (_, log) = parse_wpilog(content).map_err(|err| format!("invalid wpilog file: {}", err))?;
+    Ok(log)
+}
+
+/// Runs `f` on every input, reporting failures on stderr and carrying on with the next input.
+/// Returns the results of the inputs that succeeded, and whether all did.
+fn for_each_input<T>(inputs: &[PathBuf], mut f: impl FnMut(&Path) -> Result<T>) -> (Vec<T>, bool) {
+    let mut results = Vec::new();
+    let mut ok = true;
+    for input in inputs {
+        match f(input) {
+            Ok(result) => results.push(result),
+            Err(err) => {
+                eprintln!("{}: {}", input.display(), err);
+                ok = false;
+            }
+        }
+    }
+
+    (results, ok)
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Runs the command, returning whether everything it checked or processed was fine.
+fn run(cli: Cli) -> Result<bool> {
+    let json = cli.json;
+
+    match cli.command {
+        Command::Info { files } => info::info(&expand_inputs(&files)?, json),
+        Command::Validate { files } => validate::validate(&expand_inputs(&files)?, false, json),
+        Command::Entries { files, filter } => {
+            info::entries(&expand_inputs(&files)?, &filter.filter()?, json)
+        }
+        Command::Export(command) => export::export(command, json),
+        Command::Slice {
+            file,
+            start,
+            end,
+            output,
+            filter,
+        } => edit::slice(&file, start, end, &output, &filter.filter()?, json),
+        Command::Merge {
+            files,
+            output,
+            filter,
+        } => edit::merge(&expand_inputs(&files)?, &output, &filter.filter()?, json),
+        Command::Mcap(McapCommand::Info { files }) => {
+            info::mcap_info(&expand_inputs(&files)?, json)
+        }
+        Command::Mcap(McapCommand::Validate { files }) => {
+            validate::validate(&expand_inputs(&files)?, true, json)
+        }
+        Command::Mcap(McapCommand::Attachments { file, extract }) => {
+            attachments::attachments(&file, extract.as_deref(), json)
+        }
+        Command::Mcap(McapCommand::Metadata { files }) => {
+            attachments::metadata(&expand_inputs(&files)?, json)
+        }
+        Command::Convert {
+            file,
+            output,
+            filter,
+        } => edit::convert(&file, output, &filter.filter()?, json),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(true) => {}
+        Ok(false) => process::exit(EXIT_FAILURE),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            if err.is::<UsageError>() {
+                process::exit(EXIT_USAGE);
+            }
+            process::exit(EXIT_FAILURE);
+        }
+    }
+}
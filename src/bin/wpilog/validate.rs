@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use wpilog_reader::{
+    mcap::{lint::lint, validate::check_crcs, MAGIC},
+    wpilog::{
+        parser::parse_wpilog,
+        types::{ControlRecord, Record},
+    },
+};
+
+use crate::{for_each_input, print_json, Result};
+
+#[derive(Serialize)]
+struct Report {
+    path: PathBuf,
+    format: &'static str,
+    valid: bool,
+    problems: Vec<String>,
+    /// Entries and records of a valid wpilog.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    records: Option<usize>,
+}
+
+fn validate_mcap(path: &Path, content: &[u8]) -> Report {
+    let mut problems = lint(content)
+        .iter()
+        .map(|finding| finding.to_string())
+        .collect::<Vec<_>>();
+
+    match check_crcs(content) {
+        Ok(mismatches) => problems.extend(mismatches.iter().map(|mismatch| mismatch.to_string())),
+        // the linter already reported where the file stops parsing
+        Err(_) if !problems.is_empty() => {}
+        Err(err) => problems.push(err.to_string()),
+    }
+
+    Report {
+        path: path.to_path_buf(),
+        format: "mcap",
+        valid: problems.is_empty(),
+        problems,
+        entries: None,
+        records: None,
+    }
+}
+
+fn validate_wpilog(path: &Path, content: &[u8]) -> Report {
+    let mut report = Report {
+        path: path.to_path_buf(),
+        format: "wpilog",
+        valid: false,
+        problems: Vec::new(),
+        entries: None,
+        records: None,
+    };
+
+    match parse_wpilog(content) {
+        Ok((_, log)) => {
+            report.valid = true;
+            report.entries = Some(
+                log.records
+                    .iter()
+                    .filter(|rec| matches!(rec.data, Record::Control(ControlRecord::Start(_))))
+                    .count(),
+            );
+            report.records = Some(log.records.len());
+        }
+        Err(err) => report.problems.push(err.to_string()),
+    }
+
+    report
+}
+
+/// Validates every input, with `mcap_only` reporting wpilogs as invalid.
+pub fn validate(inputs: &[PathBuf], mcap_only: bool, json: bool) -> Result<bool> {
+    let (reports, ok) = for_each_input(inputs, |path| {
+        let content = fs::read(path)?;
+        if content.starts_with(MAGIC) {
+            return Ok(validate_mcap(path, &content));
+        }
+        if mcap_only {
+            return Ok(Report {
+                path: path.to_path_buf(),
+                format: "unknown",
+                valid: false,
+                problems: vec!["not an MCAP file".to_string()],
+                entries: None,
+                records: None,
+            });
+        }
+
+        Ok(validate_wpilog(path, &content))
+    });
+    let valid = ok && reports.iter().all(|report| report.valid);
+
+    if json {
+        print_json(&reports)?;
+        return Ok(valid);
+    }
+
+    for report in &reports {
+        match (report.valid, report.entries, report.records) {
+            (true, Some(entries), Some(records)) => println!(
+                "{}: valid, {} entries with {} records",
+                report.path.display(),
+                entries,
+                records
+            ),
+            (true, _, _) => println!("{}: valid", report.path.display()),
+            (false, _, _) => {
+                println!("{}: invalid", report.path.display());
+                for problem in &report.problems {
+                    println!("  {}", problem);
+                }
+            }
+        }
+    }
+
+    Ok(valid)
+}
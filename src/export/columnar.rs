@@ -0,0 +1,692 @@
+use std::{collections::HashMap, io, sync::Arc};
+
+use arrow::{
+    array::{
+        ArrayRef, BinaryArray, BooleanArray, FixedSizeListArray, Float32Array, Float64Array,
+        Int16Array, Int32Array, Int64Array, Int8Array, ListArray, RecordBatch, StringArray,
+        StructArray, TimestampMicrosecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+    },
+    buffer::{NullBuffer, OffsetBuffer},
+    compute::concat_batches,
+    datatypes::{DataType as ArrowType, Field, Fields, Schema, SchemaRef, TimeUnit},
+    error::ArrowError,
+    ipc::writer::FileWriter,
+};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+
+use super::unique_names;
+use crate::wpilog::{
+    parser::parse_data,
+    structs::{StructFieldType, StructRegistry, StructSchema, StructValue},
+    types::{DataType, MetadataEntry, Record, WpiLog},
+};
+
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One column per entry, one row per timestamp
+    #[default]
+    Wide,
+    /// `timestamp`, `entry` and `type` columns, plus one `value_<type>` column per entry type
+    Tidy,
+}
+
+/// Rows per record batch, each batch is written out as soon as it is full.
+const BATCH_ROWS: usize = 8192;
+
+/// Writes the entries in `metadata` to a zstd compressed Parquet file.
+pub fn export_parquet<W: io::Write + Send>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+    layout: Layout,
+) -> parquet::errors::Result<()> {
+    let columns = Columns::new(log, metadata, layout);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+
+    let mut parquet_writer = ArrowWriter::try_new(writer, columns.schema(), Some(properties))?;
+    columns.for_each_batch(log, BATCH_ROWS, |batch| parquet_writer.write(&batch))?;
+    parquet_writer.close()?;
+
+    Ok(())
+}
+
+/// Writes the entries in `metadata` to an Arrow IPC (Feather v2) file.
+pub fn export_ipc<W: io::Write>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+    layout: Layout,
+) -> Result<(), ArrowError> {
+    let columns = Columns::new(log, metadata, layout);
+
+    let mut ipc_writer = FileWriter::try_new(writer, &columns.schema())?;
+    columns.for_each_batch(log, BATCH_ROWS, |batch| ipc_writer.write(&batch))?;
+    ipc_writer.finish()?;
+
+    Ok(())
+}
+
+/// Builds a typed record batch from every data record belonging to an entry in `metadata`.
+///
+/// Timestamps are microseconds in a `timestamp` column. Entries with `struct:` types are decoded
+/// using the schemas published in the log, falling back to binary if the schema is missing.
+/// Wide columns are named after their entry, entries sharing a name are told apart by their id.
+pub fn to_record_batch(
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+    layout: Layout,
+) -> Result<RecordBatch, ArrowError> {
+    let columns = Columns::new(log, metadata, layout);
+
+    let mut batches = Vec::new();
+    columns.for_each_batch(log, BATCH_ROWS, |batch| {
+        batches.push(batch);
+        Ok::<_, ArrowError>(())
+    })?;
+
+    concat_batches(&columns.schema(), &batches)
+}
+
+/// The schema of an export, and the types of its value columns.
+struct Columns<'a> {
+    layout: Layout,
+    structs: StructRegistry,
+    schema: SchemaRef,
+    /// Arrow type of each value column, one per entry or one per entry type
+    types: Vec<ArrowType>,
+    /// Index of the entry's value column in `types`, by entry id
+    entries: HashMap<u32, (usize, &'a MetadataEntry<'a>)>,
+}
+
+impl<'a> Columns<'a> {
+    fn new(log: &WpiLog, metadata: &'a [MetadataEntry<'a>], layout: Layout) -> Self {
+        let structs = StructRegistry::from_log(log);
+        let mut fields = vec![Field::new(
+            "timestamp",
+            ArrowType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        )];
+        let mut types = Vec::new();
+        let mut entries = HashMap::new();
+
+        match layout {
+            Layout::Wide => {
+                for (entry, name) in metadata.iter().zip(unique_names(
+                    metadata.iter().map(|entry| (entry.name, entry.entry_id)),
+                )) {
+                    let typ = entry_type(entry.typ, &structs);
+                    entries.insert(entry.entry_id, (types.len(), entry));
+                    fields.push(Field::new(name, typ.clone(), true));
+                    types.push(typ);
+                }
+            }
+            Layout::Tidy => {
+                fields.push(Field::new("entry", ArrowType::Utf8, false));
+                fields.push(Field::new("type", ArrowType::Utf8, false));
+
+                let mut value_types = Vec::<&str>::new();
+                for entry in metadata {
+                    let column = match value_types.iter().position(|typ| *typ == entry.typ) {
+                        Some(column) => column,
+                        None => {
+                            let typ = entry_type(entry.typ, &structs);
+                            fields.push(Field::new(
+                                format!("value_{}", entry.typ),
+                                typ.clone(),
+                                true,
+                            ));
+                            value_types.push(entry.typ);
+                            types.push(typ);
+                            types.len() - 1
+                        }
+                    };
+                    entries.insert(entry.entry_id, (column, entry));
+                }
+            }
+        }
+
+        Columns {
+            layout,
+            structs,
+            schema: Arc::new(Schema::new(fields)),
+            types,
+            entries,
+        }
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Calls `f` with batches of at most `batch_rows` rows, only holding one batch of decoded
+    /// values at a time.
+    fn for_each_batch<E: From<ArrowError>>(
+        &self,
+        log: &WpiLog,
+        batch_rows: usize,
+        mut f: impl FnMut(RecordBatch) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut records = log
+            .records
+            .iter()
+            .filter_map(|record| match record.data {
+                Record::Data(data) => self
+                    .entries
+                    .get(&record.entry_id)
+                    .map(|(column, entry)| (record.timestamp_us, *column, *entry, data)),
+                Record::Control(_) => None,
+            })
+            .collect::<Vec<_>>();
+        records.sort_by_key(|(timestamp, ..)| *timestamp);
+
+        let mut rows = Rows::new(self.types.len());
+        for (timestamp, column, entry, data) in records {
+            let new_row = match self.layout {
+                // a second value for an entry at the same timestamp starts a new row
+                Layout::Wide => {
+                    rows.timestamps.last() != Some(&(timestamp as i64))
+                        || matches!(rows.cells[column].last(), Some(Some(_)))
+                }
+                Layout::Tidy => true,
+            };
+
+            if new_row {
+                if rows.timestamps.len() == batch_rows {
+                    f(self.batch(std::mem::replace(&mut rows, Rows::new(self.types.len())))?)?;
+                }
+
+                rows.timestamps.push(timestamp as i64);
+                if self.layout == Layout::Tidy {
+                    rows.names.push(entry.name);
+                    rows.types.push(entry.typ);
+                }
+                rows.cells.iter_mut().for_each(|cells| cells.push(None));
+            }
+
+            *rows.cells[column].last_mut().unwrap() = Some(decode(entry.typ, data, &self.structs));
+        }
+
+        if !rows.timestamps.is_empty() {
+            f(self.batch(rows)?)?;
+        }
+
+        Ok(())
+    }
+
+    fn batch(&self, rows: Rows<'a>) -> Result<RecordBatch, ArrowError> {
+        let mut columns: Vec<ArrayRef> =
+            vec![Arc::new(TimestampMicrosecondArray::from(rows.timestamps))];
+        if self.layout == Layout::Tidy {
+            columns.push(Arc::new(StringArray::from(rows.names)));
+            columns.push(Arc::new(StringArray::from(rows.types)));
+        }
+
+        for (typ, cells) in self.types.iter().zip(&rows.cells) {
+            columns.push(build_array(
+                typ,
+                &cells.iter().map(Option::as_ref).collect::<Vec<_>>(),
+            )?);
+        }
+
+        RecordBatch::try_new(self.schema(), columns)
+    }
+}
+
+/// The rows of the batch being built, with one cell per value column and row.
+struct Rows<'a> {
+    timestamps: Vec<i64>,
+    names: Vec<&'a str>,
+    types: Vec<&'a str>,
+    cells: Vec<Vec<Option<Value>>>,
+}
+
+impl<'a> Rows<'a> {
+    fn new(columns: usize) -> Self {
+        Rows {
+            timestamps: Vec::new(),
+            names: Vec::new(),
+            types: Vec::new(),
+            cells: vec![Vec::new(); columns],
+        }
+    }
+}
+
+/// Owned, decoded cell value, shaped to match the column's arrow type.
+#[derive(Debug, Clone)]
+enum Value {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Struct(Vec<Value>),
+}
+
+impl From<StructValue> for Value {
+    fn from(value: StructValue) -> Self {
+        match value {
+            StructValue::Bool(val) => Value::Bool(val),
+            StructValue::Int(val) => Value::Int(val),
+            StructValue::UInt(val) => Value::UInt(val),
+            StructValue::Float(val) => Value::Float(val),
+            StructValue::Double(val) => Value::Double(val),
+            StructValue::String(val) => Value::String(val),
+            StructValue::Array(val) => Value::List(val.into_iter().map(Value::from).collect()),
+            StructValue::Struct(val) => {
+                Value::Struct(val.into_iter().map(|(_, val)| Value::from(val)).collect())
+            }
+        }
+    }
+}
+
+impl<'a> From<DataType<'a>> for Value {
+    fn from(value: DataType<'a>) -> Self {
+        match value {
+            DataType::Raw(val) => Value::Bytes(val.to_vec()),
+            DataType::Boolean(val) => Value::Bool(val),
+            DataType::Int64(val) => Value::Int(val),
+            DataType::Float(val) => Value::Float(val),
+            DataType::Double(val) => Value::Double(val),
+            DataType::String(val) => Value::String(val.to_string()),
+            DataType::BooleanArray(val) => Value::List(val.into_iter().map(Value::Bool).collect()),
+            DataType::Int64Array(val) => Value::List(val.into_iter().map(Value::Int).collect()),
+            DataType::FloatArray(val) => Value::List(val.into_iter().map(Value::Float).collect()),
+            DataType::DoubleArray(val) => Value::List(val.into_iter().map(Value::Double).collect()),
+            DataType::StringArray(val) => Value::List(
+                val.into_iter()
+                    .map(|val| Value::String(val.to_string()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn decode(typ: &str, data: &[u8], structs: &StructRegistry) -> Value {
+    if let Some(value) = structs.decode(typ, data) {
+        return value.into();
+    }
+
+    match structs.resolve_type(typ) {
+        // undecodable struct data is kept as raw bytes, matching the binary column type
+        Some(_) => Value::Bytes(data.to_vec()),
+        None => parse_data(typ, data)
+            .map(|(_, value)| value)
+            .unwrap_or(DataType::Raw(data))
+            .into(),
+    }
+}
+
+fn list_of(item: ArrowType) -> ArrowType {
+    ArrowType::List(Arc::new(Field::new("item", item, true)))
+}
+
+fn entry_type(typ: &str, structs: &StructRegistry) -> ArrowType {
+    match typ {
+        "boolean" => ArrowType::Boolean,
+        "int64" => ArrowType::Int64,
+        "float" => ArrowType::Float32,
+        "double" => ArrowType::Float64,
+        "string" | "json" | "structschema" => ArrowType::Utf8,
+        "boolean[]" => list_of(ArrowType::Boolean),
+        "int64[]" => list_of(ArrowType::Int64),
+        "float[]" => list_of(ArrowType::Float32),
+        "double[]" => list_of(ArrowType::Float64),
+        "string[]" => list_of(ArrowType::Utf8),
+        _ => match structs.resolve_type(typ) {
+            Some((schema, is_array)) => match struct_type(schema, structs, 0) {
+                Some(typ) if is_array => list_of(typ),
+                Some(typ) => typ,
+                None => ArrowType::Binary,
+            },
+            None => ArrowType::Binary,
+        },
+    }
+}
+
+fn struct_type(schema: &StructSchema, structs: &StructRegistry, depth: usize) -> Option<ArrowType> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    let fields = schema
+        .fields
+        .iter()
+        .map(|field| {
+            let typ = match &field.typ {
+                StructFieldType::Bool => ArrowType::Boolean,
+                StructFieldType::Char => ArrowType::Utf8,
+                StructFieldType::Int8 => ArrowType::Int8,
+                StructFieldType::Int16 => ArrowType::Int16,
+                StructFieldType::Int32 => ArrowType::Int32,
+                StructFieldType::Int64 => ArrowType::Int64,
+                StructFieldType::UInt8 => ArrowType::UInt8,
+                StructFieldType::UInt16 => ArrowType::UInt16,
+                StructFieldType::UInt32 => ArrowType::UInt32,
+                StructFieldType::UInt64 => ArrowType::UInt64,
+                StructFieldType::Float => ArrowType::Float32,
+                StructFieldType::Double => ArrowType::Float64,
+                StructFieldType::Struct(name) => {
+                    struct_type(structs.get(name)?, structs, depth + 1)?
+                }
+            };
+
+            let typ = match field.array_len {
+                // char arrays decode to strings
+                Some(len) if field.typ != StructFieldType::Char => {
+                    ArrowType::FixedSizeList(Arc::new(Field::new("item", typ, true)), len as i32)
+                }
+                _ => typ,
+            };
+
+            Some(Field::new(&field.name, typ, true))
+        })
+        .collect::<Option<Fields>>()?;
+
+    Some(ArrowType::Struct(fields))
+}
+
+macro_rules! primitive_array {
+    ($array:ty, $cells:expr, $variant:ident, $cast:ty) => {
+        Arc::new(<$array>::from(
+            $cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(Value::$variant(val)) => Some(*val as $cast),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        ))
+    };
+}
+
+fn build_array(typ: &ArrowType, cells: &[Option<&Value>]) -> Result<ArrayRef, ArrowError> {
+    let nulls = || NullBuffer::from(cells.iter().map(Option::is_some).collect::<Vec<_>>());
+
+    let array: ArrayRef = match typ {
+        ArrowType::Boolean => primitive_array!(BooleanArray, cells, Bool, bool),
+        ArrowType::Int8 => primitive_array!(Int8Array, cells, Int, i8),
+        ArrowType::Int16 => primitive_array!(Int16Array, cells, Int, i16),
+        ArrowType::Int32 => primitive_array!(Int32Array, cells, Int, i32),
+        ArrowType::Int64 => primitive_array!(Int64Array, cells, Int, i64),
+        ArrowType::UInt8 => primitive_array!(UInt8Array, cells, UInt, u8),
+        ArrowType::UInt16 => primitive_array!(UInt16Array, cells, UInt, u16),
+        ArrowType::UInt32 => primitive_array!(UInt32Array, cells, UInt, u32),
+        ArrowType::UInt64 => primitive_array!(UInt64Array, cells, UInt, u64),
+        ArrowType::Float32 => primitive_array!(Float32Array, cells, Float, f32),
+        ArrowType::Float64 => primitive_array!(Float64Array, cells, Double, f64),
+        ArrowType::Utf8 => Arc::new(StringArray::from(
+            cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(Value::String(val)) => Some(val.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        )),
+        ArrowType::List(field) => {
+            let items = cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(Value::List(items)) => items.as_slice(),
+                    _ => &[],
+                })
+                .collect::<Vec<_>>();
+            let values = build_array(
+                field.data_type(),
+                &items
+                    .iter()
+                    .flat_map(|items| items.iter().map(Some))
+                    .collect::<Vec<_>>(),
+            )?;
+
+            Arc::new(ListArray::try_new(
+                field.clone(),
+                OffsetBuffer::from_lengths(items.iter().map(|items| items.len())),
+                values,
+                Some(nulls()),
+            )?)
+        }
+        ArrowType::FixedSizeList(field, len) => {
+            let len = *len as usize;
+            let items = cells
+                .iter()
+                .flat_map(|cell| match cell {
+                    Some(Value::List(items)) if items.len() == len => {
+                        items.iter().map(Some).collect::<Vec<_>>()
+                    }
+                    _ => vec![None; len],
+                })
+                .collect::<Vec<_>>();
+
+            Arc::new(FixedSizeListArray::try_new(
+                field.clone(),
+                len as i32,
+                build_array(field.data_type(), &items)?,
+                Some(nulls()),
+            )?)
+        }
+        ArrowType::Struct(fields) => {
+            let children = fields
+                .iter()
+                .enumerate()
+                .map(|(ind, field)| {
+                    let members = cells
+                        .iter()
+                        .map(|cell| match cell {
+                            Some(Value::Struct(members)) => members.get(ind),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+
+                    build_array(field.data_type(), &members)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Arc::new(StructArray::try_new(
+                fields.clone(),
+                children,
+                Some(nulls()),
+            )?)
+        }
+        _ => Arc::new(BinaryArray::from(
+            cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(Value::Bytes(val)) => Some(val.as_slice()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        )),
+    };
+
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Array, AsArray},
+        datatypes::{Float64Type, Int64Type, TimestampMicrosecondType},
+        ipc::reader::FileReader,
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::wpilog::{parser::parse_wpilog, writer::WpiLogWriter};
+
+    /// A log with two entries named `/Speed`, a struct entry and a string array entry.
+    fn write_log() -> Vec<u8> {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let schema = log
+            .start("/.schema/struct:Translation2d", "structschema", "", 0)
+            .unwrap();
+        let speed = log.start("/Speed", "double", "", 0).unwrap();
+        let pose = log.start("/Pose", "struct:Translation2d", "", 0).unwrap();
+        let tags = log.start("/Tags", "string[]", "", 0).unwrap();
+        log.finish(speed, 2).unwrap();
+        let restarted = log.start("/Speed", "int64", "", 3).unwrap();
+
+        log.append(schema, 0, b"double x;double y").unwrap();
+        log.append_value(speed, 1, &DataType::Double(1.0)).unwrap();
+        let translation = [1.0f64, 2.0, 3.0, 4.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        log.append(pose, 1, &translation[..16]).unwrap();
+        log.append_value(speed, 1, &DataType::Double(1.5)).unwrap();
+        log.append_value(tags, 2, &DataType::StringArray(vec!["a", "b"]))
+            .unwrap();
+        log.append(pose, 2, &translation[16..]).unwrap();
+        log.append_value(speed, 2, &DataType::Double(2.0)).unwrap();
+        log.append_value(restarted, 3, &DataType::Int64(5)).unwrap();
+
+        log.into_inner().unwrap()
+    }
+
+    fn metadata<'a>(log: &WpiLog<'a>) -> Vec<MetadataEntry<'a>> {
+        let mut metadata = log
+            .get_entry_metadata()
+            .into_iter()
+            .filter(|entry| entry.typ != "structschema")
+            .collect::<Vec<_>>();
+        metadata.sort_by_key(|entry| entry.entry_id);
+        metadata
+    }
+
+    #[test]
+    fn wide_parquet_round_trips() {
+        let data = write_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = metadata(&log);
+
+        let mut parquet = Vec::new();
+        export_parquet(&mut parquet, &log, &metadata, Layout::Wide).unwrap();
+        let batches = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(parquet))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let batch = concat_batches(&batches[0].schema(), &batches).unwrap();
+        assert_eq!(
+            batch,
+            to_record_batch(&log, &metadata, Layout::Wide).unwrap()
+        );
+
+        let names = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["timestamp", "/Speed", "/Pose", "/Tags", "/Speed#5"]);
+
+        // the second value of /Speed at 1 us starts a new row
+        let timestamps = batch.column(0).as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(timestamps.values(), &[1, 1, 2, 3]);
+        let speed = batch.column(1).as_primitive::<Float64Type>();
+        assert_eq!(
+            speed.iter().collect::<Vec<_>>(),
+            [Some(1.0), Some(1.5), Some(2.0), None]
+        );
+        let restarted = batch.column(4).as_primitive::<Int64Type>();
+        assert_eq!(
+            restarted.iter().collect::<Vec<_>>(),
+            [None, None, None, Some(5)]
+        );
+
+        let pose = batch.column(2).as_struct();
+        assert_eq!(pose.null_count(), 2);
+        let x = pose
+            .column_by_name("x")
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        assert_eq!(
+            x.iter().collect::<Vec<_>>(),
+            [Some(1.0), None, Some(3.0), None]
+        );
+
+        let tags = batch.column(3).as_list::<i32>();
+        assert!(tags.is_null(0));
+        assert_eq!(
+            tags.value(2).as_string::<i32>().iter().collect::<Vec<_>>(),
+            [Some("a"), Some("b")]
+        );
+    }
+
+    #[test]
+    fn batches_are_written_as_they_fill_up() {
+        let data = write_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = metadata(&log);
+
+        for layout in [Layout::Wide, Layout::Tidy] {
+            let columns = Columns::new(&log, &metadata, layout);
+            let mut ipc = FileWriter::try_new(Vec::new(), &columns.schema()).unwrap();
+            columns
+                .for_each_batch(&log, 2, |batch| {
+                    assert!(batch.num_rows() <= 2);
+                    ipc.write(&batch)
+                })
+                .unwrap();
+            ipc.finish().unwrap();
+
+            let reader =
+                FileReader::try_new(std::io::Cursor::new(ipc.into_inner().unwrap()), None).unwrap();
+            assert_eq!(
+                reader.num_batches(),
+                if layout == Layout::Wide { 2 } else { 4 }
+            );
+            let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+            let batch = concat_batches(&columns.schema(), &batches).unwrap();
+            assert_eq!(batch, to_record_batch(&log, &metadata, layout).unwrap());
+        }
+    }
+
+    #[test]
+    fn tidy_rows_have_a_column_per_type() {
+        let data = write_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = metadata(&log);
+
+        let batch = to_record_batch(&log, &metadata, Layout::Tidy).unwrap();
+        let names = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "timestamp",
+                "entry",
+                "type",
+                "value_double",
+                "value_struct:Translation2d",
+                "value_string[]",
+                "value_int64"
+            ]
+        );
+        assert_eq!(batch.num_rows(), 7);
+
+        let entries = batch.column(1).as_string::<i32>();
+        assert_eq!(entries.value(6), "/Speed");
+        let doubles = batch.column(3).as_primitive::<Float64Type>();
+        assert_eq!(
+            doubles.iter().collect::<Vec<_>>(),
+            [Some(1.0), None, Some(1.5), None, None, Some(2.0), None]
+        );
+    }
+}
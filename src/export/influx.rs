@@ -0,0 +1,393 @@
+use std::{collections::HashMap, io};
+
+use crate::wpilog::{
+    parser::{parse_data, parse_int64},
+    structs::{StructRegistry, StructValue},
+    types::{ControlRecord, DataType, MetadataEntry, Record, WpiLog},
+};
+
+use super::format_value;
+
+#[derive(Debug, Clone)]
+pub struct InfluxOptions {
+    /// Entry names are split at the last separator into measurement and field key.
+    pub separator: String,
+    /// Measurement used for entry names without a separator.
+    pub measurement: String,
+    /// Tag keys and the names of the entries holding their values. A key listed with several
+    /// entries takes the latest value of any of them.
+    pub tags: Vec<(String, String)>,
+    /// Also writes string entries and struct `char` members as string fields.
+    pub strings: bool,
+}
+
+impl Default for InfluxOptions {
+    fn default() -> Self {
+        Self {
+            separator: "/".to_string(),
+            measurement: "wpilog".to_string(),
+            // AdvantageKit logs the match info under /DriverStation, NetworkTables logging
+            // under NT:/FMSInfo
+            tags: [
+                ("event", "/DriverStation/EventName"),
+                ("event", "NT:/FMSInfo/EventName"),
+                ("match_type", "/DriverStation/MatchType"),
+                ("match_type", "NT:/FMSInfo/MatchType"),
+                ("match", "/DriverStation/MatchNumber"),
+                ("match", "NT:/FMSInfo/MatchNumber"),
+            ]
+            .iter()
+            .map(|(key, name)| (key.to_string(), name.to_string()))
+            .collect(),
+            strings: false,
+        }
+    }
+}
+
+/// Writes one line of InfluxDB line protocol per data record of the boolean, numeric, numeric
+/// array and struct entries in `metadata`, in timestamp order. String entries and the `char`
+/// members of structs are only written with [`InfluxOptions::strings`].
+///
+/// `Drive/LeftVelocity` becomes field `LeftVelocity` of measurement `Drive`, array elements become
+/// `<field>[<index>]` and struct members `<field>.<member>`. Each line is tagged with the latest
+/// value of the tag entries, which don't need to be in `metadata`. Timestamps are nanoseconds
+/// since the Unix epoch, using the offset to the last `systemTime` record, or relative to the
+/// start of the log if there is none.
+pub fn export_influx<W: io::Write>(
+    mut writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+    options: &InfluxOptions,
+) -> io::Result<()> {
+    let structs = StructRegistry::from_log(log);
+    let entries = metadata
+        .iter()
+        .map(|entry| (entry.entry_id, entry))
+        .collect::<HashMap<_, _>>();
+
+    let mut records = log.records.iter().collect::<Vec<_>>();
+    records.sort_by_key(|record| record.timestamp_us);
+
+    let mut tag_keys = Vec::new();
+    for (key, _) in &options.tags {
+        if !tag_keys.contains(&key) {
+            tag_keys.push(key);
+        }
+    }
+
+    // tags and the system time usually aren't exported themselves, so look them up in the log
+    let mut tag_ids = HashMap::new();
+    let mut system_time_id = None;
+    for record in &records {
+        if let Record::Control(ControlRecord::Start(start)) = &record.data {
+            if let Some((key, _)) = options.tags.iter().find(|(_, name)| name == start.name) {
+                let ind = tag_keys.iter().position(|tag_key| *tag_key == key).unwrap();
+                tag_ids.insert(start.entry_id, (ind, start.typ));
+            }
+            if start.name == "systemTime" {
+                system_time_id = Some(start.entry_id);
+            }
+        }
+    }
+
+    let offset = records
+        .iter()
+        .rev()
+        .filter(|record| Some(record.entry_id) == system_time_id)
+        .find_map(|record| match record.data {
+            Record::Data(data) => parse_int64(data)
+                .ok()
+                .map(|(_, time)| time - record.timestamp_us as i64),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let mut tag_values: Vec<Option<String>> = vec![None; tag_keys.len()];
+
+    for record in records {
+        let Record::Data(data) = record.data else {
+            continue;
+        };
+
+        if let Some((ind, typ)) = tag_ids.get(&record.entry_id) {
+            tag_values[*ind] = parse_data(typ, data)
+                .ok()
+                .map(|(_, value)| format_value(&value))
+                .filter(|value| !value.is_empty());
+        }
+
+        let Some(entry) = entries.get(&record.entry_id) else {
+            continue;
+        };
+
+        let name = entry.name.trim_start_matches(options.separator.as_str());
+        let (measurement, key) = match name.rsplit_once(options.separator.as_str()) {
+            Some((measurement, key)) if !measurement.is_empty() && !key.is_empty() => {
+                (measurement, key)
+            }
+            _ => (options.measurement.as_str(), name),
+        };
+
+        let mut fields = Vec::new();
+        match structs.decode(entry.typ, data) {
+            Some(value) => push_struct_fields(key.to_string(), &value, options, &mut fields),
+            None => match parse_data(entry.typ, data) {
+                Ok((_, value)) => push_fields(key, &value, options, &mut fields),
+                Err(_) => continue,
+            },
+        }
+
+        if fields.is_empty() {
+            continue;
+        }
+
+        let mut line = escape(measurement, &[',', ' ']);
+        for (tag, value) in tag_keys.iter().zip(&tag_values) {
+            if let Some(value) = value {
+                line.push(',');
+                line.push_str(&escape(tag, &[',', '=', ' ']));
+                line.push('=');
+                line.push_str(&escape(value, &[',', '=', ' ']));
+            }
+        }
+
+        for (ind, (key, value)) in fields.iter().enumerate() {
+            line.push(if ind == 0 { ' ' } else { ',' });
+            line.push_str(&escape(key, &[',', '=', ' ']));
+            line.push('=');
+            line.push_str(value);
+        }
+
+        let timestamp_ns = (record.timestamp_us as i64 + offset) * 1000;
+
+        writeln!(writer, "{} {}", line, timestamp_ns)?;
+    }
+
+    writer.flush()
+}
+
+fn push_fields(
+    key: &str,
+    value: &DataType,
+    options: &InfluxOptions,
+    fields: &mut Vec<(String, String)>,
+) {
+    match value {
+        DataType::Boolean(val) => fields.push((key.to_string(), val.to_string())),
+        DataType::Int64(val) => fields.push((key.to_string(), format!("{}i", val))),
+        DataType::Float(val) if val.is_finite() => fields.push((key.to_string(), val.to_string())),
+        DataType::Double(val) if val.is_finite() => fields.push((key.to_string(), val.to_string())),
+        DataType::String(val) if options.strings => fields.push((key.to_string(), quote(val))),
+        DataType::BooleanArray(val) => {
+            for (ind, val) in val.iter().enumerate() {
+                fields.push((format!("{}[{}]", key, ind), val.to_string()));
+            }
+        }
+        DataType::Int64Array(val) => {
+            for (ind, val) in val.iter().enumerate() {
+                fields.push((format!("{}[{}]", key, ind), format!("{}i", val)));
+            }
+        }
+        DataType::FloatArray(val) => {
+            for (ind, val) in val.iter().enumerate().filter(|(_, val)| val.is_finite()) {
+                fields.push((format!("{}[{}]", key, ind), val.to_string()));
+            }
+        }
+        DataType::DoubleArray(val) => {
+            for (ind, val) in val.iter().enumerate().filter(|(_, val)| val.is_finite()) {
+                fields.push((format!("{}[{}]", key, ind), val.to_string()));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn push_struct_fields(
+    key: String,
+    value: &StructValue,
+    options: &InfluxOptions,
+    fields: &mut Vec<(String, String)>,
+) {
+    match value {
+        StructValue::Bool(val) => fields.push((key, val.to_string())),
+        StructValue::Int(val) => fields.push((key, format!("{}i", val))),
+        StructValue::UInt(val) => {
+            if let Ok(val) = i64::try_from(*val) {
+                fields.push((key, format!("{}i", val)));
+            }
+        }
+        StructValue::Float(val) if val.is_finite() => fields.push((key, val.to_string())),
+        StructValue::Double(val) if val.is_finite() => fields.push((key, val.to_string())),
+        StructValue::String(val) if options.strings => fields.push((key, quote(val))),
+        StructValue::Array(values) => {
+            for (ind, value) in values.iter().enumerate() {
+                push_struct_fields(format!("{}[{}]", key, ind), value, options, fields);
+            }
+        }
+        StructValue::Struct(members) => {
+            for (name, value) in members {
+                push_struct_fields(format!("{}.{}", key, name), value, options, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Escapes a measurement, tag or field key. Line breaks can't be escaped there, so they are
+/// replaced by spaces.
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        let c = match c {
+            '\n' | '\r' => ' ',
+            c => c,
+        };
+        if special.contains(&c) || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quotes a string field value, escaping quotes, backslashes and line breaks.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filter::EntryFilter,
+        wpilog::{parser::parse_wpilog, writer::WpiLogWriter},
+    };
+
+    fn write_log() -> Vec<u8> {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let event = log
+            .start("/DriverStation/EventName", "string", "", 0)
+            .unwrap();
+        let fms_match = log
+            .start("NT:/FMSInfo/MatchNumber", "int64", "", 0)
+            .unwrap();
+        let ds_match = log
+            .start("/DriverStation/MatchNumber", "int64", "", 0)
+            .unwrap();
+        let system_time = log.start("systemTime", "int64", "", 0).unwrap();
+        let schema = log
+            .start("/.schema/struct:Module", "structschema", "", 0)
+            .unwrap();
+        let speed = log.start("/Drive/Speed", "double", "", 0).unwrap();
+        let name = log.start("/Drive/Name", "string", "", 0).unwrap();
+        let status = log.start("Status", "boolean", "", 0).unwrap();
+        let array = log.start("/Drive/Array", "int64[]", "", 0).unwrap();
+        let module = log.start("/Drive/Module", "struct:Module", "", 0).unwrap();
+        let multiline = log.start("/Drive/Multi\nLine", "double", "", 0).unwrap();
+
+        log.append_value(event, 1, &DataType::String("CMP\nDiv"))
+            .unwrap();
+        log.append_value(fms_match, 1, &DataType::Int64(12))
+            .unwrap();
+        log.append_value(system_time, 2, &DataType::Int64(1_000_002))
+            .unwrap();
+        log.append(schema, 2, b"char name[2];double angle").unwrap();
+        log.append_value(speed, 3, &DataType::Double(1.5)).unwrap();
+        log.append_value(name, 3, &DataType::String("a \"b\"\nc\\"))
+            .unwrap();
+        log.append_value(status, 4, &DataType::Boolean(true))
+            .unwrap();
+        log.append_value(array, 4, &DataType::Int64Array(vec![1, -2]))
+            .unwrap();
+        log.append_value(ds_match, 5, &DataType::Int64(13)).unwrap();
+        let mut value = b"fl".to_vec();
+        value.extend(0.5f64.to_le_bytes());
+        log.append(module, 5, &value).unwrap();
+        log.append_value(multiline, 5, &DataType::Double(f64::NAN))
+            .unwrap();
+        log.append_value(multiline, 6, &DataType::Double(2.0))
+            .unwrap();
+
+        log.into_inner().unwrap()
+    }
+
+    fn export(options: &InfluxOptions) -> String {
+        let data = write_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = EntryFilter::new()
+            .include("/Drive/*")
+            .unwrap()
+            .include("Status")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+
+        let mut out = Vec::new();
+        export_influx(&mut out, &log, &metadata, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn lines_are_tagged_and_escaped() {
+        let options = InfluxOptions {
+            strings: true,
+            ..InfluxOptions::default()
+        };
+        assert_eq!(
+            export(&options),
+            r#"Drive,event=CMP\ Div,match=12 Speed=1.5 1000003000
+Drive,event=CMP\ Div,match=12 Name="a \"b\"\nc\\" 1000003000
+wpilog,event=CMP\ Div,match=12 Status=true 1000004000
+Drive,event=CMP\ Div,match=12 Array[0]=1i,Array[1]=-2i 1000004000
+Drive,event=CMP\ Div,match=13 Module.name="fl",Module.angle=0.5 1000005000
+Drive,event=CMP\ Div,match=13 Multi\ Line=2 1000006000
+"#
+        );
+    }
+
+    #[test]
+    fn measurements_are_split_at_the_separator() {
+        let options = InfluxOptions {
+            separator: "ive/".to_string(),
+            measurement: "robot log".to_string(),
+            tags: vec![("event,name".to_string(), "Status".to_string())],
+            strings: true,
+        };
+        assert_eq!(
+            export(&options),
+            r#"/Dr Speed=1.5 1000003000
+/Dr Name="a \"b\"\nc\\" 1000003000
+robot\ log,event\,name=1 Status=true 1000004000
+/Dr,event\,name=1 Array[0]=1i,Array[1]=-2i 1000004000
+/Dr,event\,name=1 Module.name="fl",Module.angle=0.5 1000005000
+/Dr,event\,name=1 Multi\ Line=2 1000006000
+"#
+        );
+    }
+
+    #[test]
+    fn strings_are_left_out_by_default() {
+        assert_eq!(
+            export(&InfluxOptions::default()),
+            r#"Drive,event=CMP\ Div,match=12 Speed=1.5 1000003000
+wpilog,event=CMP\ Div,match=12 Status=true 1000004000
+Drive,event=CMP\ Div,match=12 Array[0]=1i,Array[1]=-2i 1000004000
+Drive,event=CMP\ Div,match=13 Module.angle=0.5 1000005000
+Drive,event=CMP\ Div,match=13 Multi\ Line=2 1000006000
+"#
+        );
+    }
+}
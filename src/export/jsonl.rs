@@ -0,0 +1,292 @@
+use std::{collections::HashMap, io};
+
+use serde::Serialize;
+
+use crate::{
+    source::{Entry, LogSource, Value},
+    wpilog::{
+        parser::parse_data,
+        structs::StructRegistry,
+        types::{ControlRecord, DataType, MetadataEntry, Record, WpiLog},
+    },
+};
+
+#[derive(Serialize)]
+struct DataLine<'a> {
+    t: u64,
+    entry: &'a str,
+    #[serde(rename = "type")]
+    typ: &'a str,
+    value: Value<'a>,
+}
+
+#[derive(Serialize)]
+struct ControlLine<'a> {
+    t: u64,
+    control: &'static str,
+    id: u32,
+    entry: &'a str,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    typ: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a str>,
+}
+
+/// Writes one `{"t":..., "entry":..., "type":..., "value":...}` line per data record of the
+/// entries in `metadata`, in timestamp order. `t` is in microseconds.
+///
+/// Values are fully decoded: arrays as JSON arrays, `struct:` entries as objects when their
+/// schema is in the log, and raw data as an array of bytes. Start, finish and set metadata
+/// records are written to `control` if given, otherwise inline, marked by a `control` key.
+pub fn export_jsonl<W: io::Write>(
+    mut writer: W,
+    mut control: Option<&mut dyn io::Write>,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+) -> io::Result<()> {
+    let structs = StructRegistry::from_log(log);
+    let entries = metadata
+        .iter()
+        .map(|entry| (entry.entry_id, entry))
+        .collect::<HashMap<_, _>>();
+
+    let mut records = log.records.iter().collect::<Vec<_>>();
+    records.sort_by_key(|record| record.timestamp_us);
+
+    for record in records {
+        match &record.data {
+            Record::Data(data) => {
+                let Some(entry) = entries.get(&record.entry_id) else {
+                    continue;
+                };
+
+                let value = match structs.decode(entry.typ, data) {
+                    Some(value) => Value::Struct(value),
+                    None => Value::Data(
+                        parse_data(entry.typ, data)
+                            .map(|(_, value)| value)
+                            .unwrap_or(DataType::Raw(data)),
+                    ),
+                };
+
+                let line = DataLine {
+                    t: record.timestamp_us,
+                    entry: entry.name,
+                    typ: entry.typ,
+                    value,
+                };
+
+                serde_json::to_writer(&mut writer, &line)?;
+                writer.write_all(b"\n")?;
+            }
+            Record::Control(control_record) => {
+                let id = match control_record {
+                    ControlRecord::Start(start) => start.entry_id,
+                    ControlRecord::Finish(finish) => finish.entry_id,
+                    ControlRecord::SetMetadata(set_metadata) => set_metadata.entry_id,
+                };
+                let Some(entry) = entries.get(&id) else {
+                    continue;
+                };
+
+                let (kind, typ, metadata) = match control_record {
+                    ControlRecord::Start(start) => ("start", Some(start.typ), Some(start.metadata)),
+                    ControlRecord::Finish(_) => ("finish", None, None),
+                    ControlRecord::SetMetadata(set_metadata) => {
+                        ("set_metadata", None, Some(set_metadata.metadata))
+                    }
+                };
+
+                let line = ControlLine {
+                    t: record.timestamp_us,
+                    control: kind,
+                    id,
+                    entry: entry.name,
+                    typ,
+                    metadata,
+                };
+
+                match control.as_mut() {
+                    Some(control) => {
+                        serde_json::to_writer(&mut *control, &line)?;
+                        control.write_all(b"\n")?;
+                    }
+                    None => {
+                        serde_json::to_writer(&mut writer, &line)?;
+                        writer.write_all(b"\n")?;
+                    }
+                }
+            }
+        }
+    }
+
+    writer.flush()?;
+    if let Some(control) = control {
+        control.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Writes the same data lines as [`export_jsonl`] for the `entries` of any [`LogSource`],
+/// without control records.
+pub fn export_source_jsonl<W: io::Write, S: LogSource + ?Sized>(
+    mut writer: W,
+    source: &S,
+    entries: &[Entry],
+) -> io::Result<()> {
+    let entries_by_id = entries
+        .iter()
+        .map(|entry| (entry.id, entry))
+        .collect::<HashMap<_, _>>();
+
+    for sample in source.samples(entries) {
+        let sample =
+            sample.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let entry = entries_by_id[&sample.entry_id];
+
+        let line = DataLine {
+            t: sample.timestamp_us(),
+            entry: &entry.name,
+            typ: &entry.typ,
+            value: sample.value,
+        };
+
+        serde_json::to_writer(&mut writer, &line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::tests::sample_log,
+        filter::EntryFilter,
+        wpilog::{parser::parse_wpilog, structs::StructValue, writer::WpiLogWriter},
+    };
+
+    #[test]
+    fn control_records_are_written_inline() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let mut out = Vec::new();
+        export_jsonl(&mut out, None, &log, &log.get_entry_metadata()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"t":0,"control":"start","id":1,"entry":"/Drive/Speed","type":"double","metadata":"{\"unit\":\"m/s\"}"}
+{"t":0,"control":"start","id":2,"entry":"/Drive/Modules","type":"string[]","metadata":""}
+{"t":0,"control":"start","id":3,"entry":"/Enabled","type":"boolean","metadata":""}
+{"t":0,"control":"start","id":4,"entry":"/Raw","type":"raw","metadata":""}
+{"t":1000000,"entry":"/Drive/Speed","type":"double","value":1.5}
+{"t":1000000,"entry":"/Drive/Modules","type":"string[]","value":["a","b"]}
+{"t":1500000,"entry":"/Drive/Modules","type":"string[]","value":[]}
+{"t":1500000,"entry":"/Enabled","type":"boolean","value":true}
+{"t":2000000,"entry":"/Drive/Speed","type":"double","value":2.5}
+{"t":2000000,"entry":"/Raw","type":"raw","value":[1,2]}
+{"t":2500000,"control":"finish","id":3,"entry":"/Enabled"}
+"#
+        );
+    }
+
+    #[test]
+    fn control_records_can_be_split_off() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = EntryFilter::new()
+            .include("/Enabled")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+
+        let mut out = Vec::new();
+        let mut control = Vec::new();
+        export_jsonl(&mut out, Some(&mut control), &log, &metadata).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"t\":1500000,\"entry\":\"/Enabled\",\"type\":\"boolean\",\"value\":true}\n"
+        );
+        assert_eq!(
+            String::from_utf8(control).unwrap(),
+            "{\"t\":0,\"control\":\"start\",\"id\":3,\"entry\":\"/Enabled\",\"type\":\"boolean\",\"metadata\":\"\"}\n\
+             {\"t\":2500000,\"control\":\"finish\",\"id\":3,\"entry\":\"/Enabled\"}\n"
+        );
+    }
+
+    #[test]
+    fn structs_are_objects() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let schema = log
+            .start("/.schema/struct:Module", "structschema", "", 0)
+            .unwrap();
+        let module = log.start("/Module", "struct:Module", "", 0).unwrap();
+        log.append(
+            schema,
+            0,
+            b"char name[2];int8 offset;uint8 flags:2;float angles[2]",
+        )
+        .unwrap();
+        let mut value = vec![b'f', b'l', 0xFE, 0b11];
+        value.extend(0.5f32.to_le_bytes());
+        value.extend((-1.0f32).to_le_bytes());
+        log.append(module, 1, &value).unwrap();
+        let data = log.into_inner().unwrap();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let metadata = EntryFilter::new()
+            .include("/Module")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+        let mut out = Vec::new();
+        let mut control = Vec::new();
+        export_jsonl(&mut out, Some(&mut control), &log, &metadata).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"t\":1,\"entry\":\"/Module\",\"type\":\"struct:Module\",\
+             \"value\":{\"name\":\"fl\",\"offset\":-2,\"flags\":3,\"angles\":[0.5,-1.0]}}\n"
+        );
+    }
+
+    #[test]
+    fn values_serialize_untagged() {
+        let values = [
+            Value::Data(DataType::Raw(&[0xAB])),
+            Value::Data(DataType::Float(0.25)),
+            Value::Data(DataType::String("a\"b")),
+            Value::Data(DataType::BooleanArray(vec![true, false])),
+            Value::Data(DataType::Int64Array(vec![-1, i64::MAX])),
+            Value::Struct(StructValue::Array(vec![
+                StructValue::UInt(u64::MAX),
+                StructValue::Bool(false),
+                StructValue::Struct(vec![]),
+            ])),
+        ];
+        assert_eq!(
+            serde_json::to_string(&values).unwrap(),
+            r#"[[171],0.25,"a\"b",[true,false],[-1,9223372036854775807],[18446744073709551615,false,{}]]"#
+        );
+    }
+
+    #[test]
+    fn sources_write_the_same_data_lines() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let entries = log.filter_entries(&EntryFilter::new()).unwrap();
+
+        let mut out = Vec::new();
+        let mut control = Vec::new();
+        export_jsonl(
+            &mut out,
+            Some(&mut control),
+            &log,
+            &log.get_entry_metadata(),
+        )
+        .unwrap();
+        let mut source_out = Vec::new();
+        export_source_jsonl(&mut source_out, &log, &entries).unwrap();
+
+        assert_eq!(String::from_utf8(source_out), String::from_utf8(out));
+    }
+}
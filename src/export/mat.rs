@@ -0,0 +1,631 @@
+use std::{collections::HashMap, io};
+
+use crate::wpilog::{
+    parser::parse_data,
+    types::{DataType, MetadataEntry, Record, WpiLog},
+};
+
+// data element types
+const MI_INT8: u32 = 1;
+const MI_UINT8: u32 = 2;
+const MI_UINT16: u32 = 4;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_SINGLE: u32 = 7;
+const MI_DOUBLE: u32 = 9;
+const MI_INT64: u32 = 12;
+const MI_MATRIX: u32 = 14;
+
+// array classes
+const MX_CELL_CLASS: u32 = 1;
+const MX_STRUCT_CLASS: u32 = 2;
+const MX_CHAR_CLASS: u32 = 4;
+const MX_DOUBLE_CLASS: u32 = 6;
+const MX_SINGLE_CLASS: u32 = 7;
+const MX_UINT8_CLASS: u32 = 9;
+const MX_INT64_CLASS: u32 = 14;
+
+const LOGICAL_FLAG: u32 = 0x02 << 8;
+const NAME_LENGTH_MAX: usize = 63;
+
+/// Writes a Level 5 MAT-file with one struct per entry in `metadata`, each holding a `time`
+/// column (seconds) and a `value` column.
+///
+/// Entry names are split on `/` into nested structs, so `Drive/LeftVelocity` is read in MATLAB
+/// as `Drive.LeftVelocity.time` and `Drive.LeftVelocity.value`. Each part of a name is made a
+/// valid identifier the way `matlab.lang.makeValidName` does.
+///
+/// Booleans become logical columns, int64, float and double keep their class, and constant
+/// length numeric arrays become `samples x length` matrices. Strings, variable length arrays and
+/// raw data become cell columns.
+pub fn export_mat<W: io::Write>(
+    mut writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+) -> io::Result<()> {
+    let mut samples: HashMap<u32, Vec<(u64, &[u8])>> = HashMap::new();
+    for record in &log.records {
+        if let Record::Data(data) = record.data {
+            samples
+                .entry(record.entry_id)
+                .or_default()
+                .push((record.timestamp_us, data));
+        }
+    }
+
+    let mut root = Node::default();
+
+    for entry in metadata {
+        let Some(samples) = samples.get_mut(&entry.entry_id) else {
+            continue;
+        };
+        samples.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let path = entry
+            .name
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(make_valid_name)
+            .collect::<Vec<_>>();
+        if path.is_empty() {
+            continue;
+        }
+
+        root.insert(&path, entry_struct(entry, samples));
+    }
+
+    let mut header = b"MATLAB 5.0 MAT-file, written by wpilog-reader".to_vec();
+    header.resize(116, b' ');
+    // no subsystem data, version 0x0100, little endian
+    header.extend([0; 8]);
+    header.extend(0x0100u16.to_le_bytes());
+    header.extend(b"IM");
+    writer.write_all(&header)?;
+
+    for (name, node) in root.children {
+        let mut out = Vec::new();
+        write_matrix(&mut out, &name, &node.into_array())?;
+        writer.write_all(&out)?;
+    }
+
+    writer.flush()
+}
+
+#[derive(Debug, Clone)]
+enum MatArray {
+    Logical(usize, usize, Vec<u8>),
+    Int64(usize, usize, Vec<i64>),
+    Single(usize, usize, Vec<f32>),
+    Double(usize, usize, Vec<f64>),
+    UInt8(Vec<u8>),
+    Char(String),
+    Cell(Vec<MatArray>),
+    Struct(Vec<(String, MatArray)>),
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    fields: Option<Vec<(String, MatArray)>>,
+    children: Vec<(String, Node)>,
+}
+
+impl Node {
+    fn insert(&mut self, path: &[String], fields: Vec<(String, MatArray)>) {
+        let (name, rest) = path.split_first().unwrap();
+
+        // entries whose names sanitize to the same path get a numeric suffix
+        let mut candidate = name.clone();
+        let mut suffix = 1;
+        loop {
+            match self
+                .children
+                .iter_mut()
+                .find(|(child, _)| *child == candidate)
+            {
+                Some((_, node)) if !rest.is_empty() => return node.insert(rest, fields),
+                Some((_, node)) if node.fields.is_none() => {
+                    node.fields = Some(fields);
+                    return;
+                }
+                Some(_) => {
+                    candidate = with_suffix(name, &format!("_{}", suffix));
+                    suffix += 1;
+                }
+                None => {
+                    let mut node = Node::default();
+                    if rest.is_empty() {
+                        node.fields = Some(fields);
+                    } else {
+                        node.insert(rest, fields);
+                    }
+                    self.children.push((candidate, node));
+                    return;
+                }
+            }
+        }
+    }
+
+    fn into_array(self) -> MatArray {
+        let mut fields = self.fields.unwrap_or_default();
+
+        for (name, node) in self.children {
+            let mut candidate = name.clone();
+            let mut suffix = String::new();
+            while fields.iter().any(|(field, _)| *field == candidate) {
+                suffix.push('_');
+                candidate = with_suffix(&name, &suffix);
+            }
+            fields.push((candidate, node.into_array()));
+        }
+
+        MatArray::Struct(fields)
+    }
+}
+
+fn make_valid_name(name: &str) -> String {
+    let mut valid = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    if !valid.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        valid.insert(0, 'x');
+    }
+    valid.truncate(NAME_LENGTH_MAX);
+
+    valid
+}
+
+/// Appends `suffix` to a valid name, shortening the name to keep it within `namelengthmax`.
+fn with_suffix(name: &str, suffix: &str) -> String {
+    let len = name.len().min(NAME_LENGTH_MAX.saturating_sub(suffix.len()));
+    format!("{}{}", &name[..len], suffix)
+}
+
+fn entry_struct(entry: &MetadataEntry, samples: &[(u64, &[u8])]) -> Vec<(String, MatArray)> {
+    let values = samples
+        .iter()
+        .map(|(timestamp, data)| {
+            let value = parse_data(entry.typ, data)
+                .map(|(_, value)| value)
+                .unwrap_or(DataType::Raw(data));
+
+            (*timestamp, value)
+        })
+        .collect::<Vec<_>>();
+
+    let rows = values.len();
+    let time = values
+        .iter()
+        .map(|(timestamp, _)| *timestamp as f64 / 1_000_000.0)
+        .collect();
+
+    let value = match (entry.typ, entry.all_same_length) {
+        ("boolean", _) => MatArray::Logical(
+            rows,
+            1,
+            values
+                .iter()
+                .map(|(_, value)| matches!(value, DataType::Boolean(true)) as u8)
+                .collect(),
+        ),
+        ("int64", _) => MatArray::Int64(
+            rows,
+            1,
+            values
+                .iter()
+                .map(|(_, value)| match value {
+                    DataType::Int64(val) => *val,
+                    _ => 0,
+                })
+                .collect(),
+        ),
+        ("float", _) => MatArray::Single(
+            rows,
+            1,
+            values
+                .iter()
+                .map(|(_, value)| match value {
+                    DataType::Float(val) => *val,
+                    _ => f32::NAN,
+                })
+                .collect(),
+        ),
+        ("double", _) => MatArray::Double(
+            rows,
+            1,
+            values
+                .iter()
+                .map(|(_, value)| match value {
+                    DataType::Double(val) => *val,
+                    _ => f64::NAN,
+                })
+                .collect(),
+        ),
+        ("boolean[]" | "int64[]" | "float[]" | "double[]", Some((cols, _)))
+            if values.iter().all(|(_, value)| value.is_array()) =>
+        {
+            // column major, so element j of row i lives at j * rows + i
+            let mut elements = vec![Vec::new(); cols];
+            for (_, value) in &values {
+                for (col, element) in array_elements(value).into_iter().enumerate() {
+                    elements[col].push(element);
+                }
+            }
+
+            let elements = elements.into_iter().flatten().collect::<Vec<_>>();
+            matrix_of(entry.typ, rows, cols, elements)
+        }
+        _ => MatArray::Cell(values.iter().map(|(_, value)| cell_value(value)).collect()),
+    };
+
+    vec![
+        ("time".to_string(), MatArray::Double(rows, 1, time)),
+        ("value".to_string(), value),
+    ]
+}
+
+/// Numeric elements of an array value, widened so they can share one buffer.
+#[derive(Debug, Clone, Copy)]
+enum Element {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Double(f64),
+}
+
+fn array_elements(value: &DataType) -> Vec<Element> {
+    match value {
+        DataType::BooleanArray(val) => val.iter().map(|val| Element::Bool(*val)).collect(),
+        DataType::Int64Array(val) => val.iter().map(|val| Element::Int(*val)).collect(),
+        DataType::FloatArray(val) => val.iter().map(|val| Element::Float(*val)).collect(),
+        DataType::DoubleArray(val) => val.iter().map(|val| Element::Double(*val)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn matrix_of(typ: &str, rows: usize, cols: usize, elements: Vec<Element>) -> MatArray {
+    let elements = elements.into_iter();
+
+    match typ {
+        "boolean[]" => MatArray::Logical(
+            rows,
+            cols,
+            elements
+                .map(|element| matches!(element, Element::Bool(true)) as u8)
+                .collect(),
+        ),
+        "int64[]" => MatArray::Int64(
+            rows,
+            cols,
+            elements
+                .map(|element| match element {
+                    Element::Int(val) => val,
+                    _ => 0,
+                })
+                .collect(),
+        ),
+        "float[]" => MatArray::Single(
+            rows,
+            cols,
+            elements
+                .map(|element| match element {
+                    Element::Float(val) => val,
+                    _ => f32::NAN,
+                })
+                .collect(),
+        ),
+        _ => MatArray::Double(
+            rows,
+            cols,
+            elements
+                .map(|element| match element {
+                    Element::Double(val) => val,
+                    _ => f64::NAN,
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn cell_value(value: &DataType) -> MatArray {
+    match value {
+        DataType::Raw(val) => MatArray::UInt8(val.to_vec()),
+        DataType::Boolean(val) => MatArray::Logical(1, 1, vec![*val as u8]),
+        DataType::Int64(val) => MatArray::Int64(1, 1, vec![*val]),
+        DataType::Float(val) => MatArray::Single(1, 1, vec![*val]),
+        DataType::Double(val) => MatArray::Double(1, 1, vec![*val]),
+        DataType::String(val) => MatArray::Char(val.to_string()),
+        DataType::BooleanArray(val) => {
+            MatArray::Logical(1, val.len(), val.iter().map(|val| *val as u8).collect())
+        }
+        DataType::Int64Array(val) => MatArray::Int64(1, val.len(), val.clone()),
+        DataType::FloatArray(val) => MatArray::Single(1, val.len(), val.clone()),
+        DataType::DoubleArray(val) => MatArray::Double(1, val.len(), val.clone()),
+        DataType::StringArray(val) => MatArray::Cell(
+            val.iter()
+                .map(|val| MatArray::Char(val.to_string()))
+                .collect(),
+        ),
+    }
+}
+
+/// Converts a size to the 32 bit integer the format stores it as.
+fn mat_size<T: TryFrom<usize>>(size: usize) -> io::Result<T> {
+    T::try_from(size).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is too large for a Level 5 MAT-file", size),
+        )
+    })
+}
+
+fn write_element(out: &mut Vec<u8>, typ: u32, data: &[u8]) -> io::Result<()> {
+    out.extend(typ.to_le_bytes());
+    out.extend(mat_size::<u32>(data.len())?.to_le_bytes());
+    out.extend(data);
+
+    // every element is padded to a multiple of 8 bytes
+    out.resize(out.len() + (8 - data.len() % 8) % 8, 0);
+
+    Ok(())
+}
+
+fn write_matrix(out: &mut Vec<u8>, name: &str, array: &MatArray) -> io::Result<()> {
+    let (class, dims) = match array {
+        MatArray::Logical(rows, cols, _) => (MX_UINT8_CLASS | LOGICAL_FLAG, [*rows, *cols]),
+        MatArray::Int64(rows, cols, _) => (MX_INT64_CLASS, [*rows, *cols]),
+        MatArray::Single(rows, cols, _) => (MX_SINGLE_CLASS, [*rows, *cols]),
+        MatArray::Double(rows, cols, _) => (MX_DOUBLE_CLASS, [*rows, *cols]),
+        MatArray::UInt8(val) => (MX_UINT8_CLASS, [1, val.len()]),
+        MatArray::Char(val) => (MX_CHAR_CLASS, [1, val.encode_utf16().count()]),
+        // cells hold one sample per row
+        MatArray::Cell(val) => (MX_CELL_CLASS, [val.len(), 1]),
+        MatArray::Struct(_) => (MX_STRUCT_CLASS, [1, 1]),
+    };
+
+    let mut body = Vec::new();
+
+    let mut flags = class.to_le_bytes().to_vec();
+    flags.extend(0u32.to_le_bytes());
+    write_element(&mut body, MI_UINT32, &flags)?;
+    let mut dim_data = Vec::with_capacity(8);
+    for dim in dims {
+        dim_data.extend(mat_size::<i32>(dim)?.to_le_bytes());
+    }
+    write_element(&mut body, MI_INT32, &dim_data)?;
+    write_element(&mut body, MI_INT8, name.as_bytes())?;
+
+    match array {
+        MatArray::Logical(_, _, val) | MatArray::UInt8(val) => {
+            write_element(&mut body, MI_UINT8, val)?
+        }
+        MatArray::Int64(_, _, val) => write_element(
+            &mut body,
+            MI_INT64,
+            &val.iter()
+                .flat_map(|val| val.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )?,
+        MatArray::Single(_, _, val) => write_element(
+            &mut body,
+            MI_SINGLE,
+            &val.iter()
+                .flat_map(|val| val.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )?,
+        MatArray::Double(_, _, val) => write_element(
+            &mut body,
+            MI_DOUBLE,
+            &val.iter()
+                .flat_map(|val| val.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )?,
+        MatArray::Char(val) => write_element(
+            &mut body,
+            MI_UINT16,
+            &val.encode_utf16()
+                .flat_map(|val| val.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )?,
+        MatArray::Cell(val) => {
+            for cell in val {
+                write_matrix(&mut body, "", cell)?;
+            }
+        }
+        MatArray::Struct(fields) => {
+            let name_len = fields
+                .iter()
+                .map(|(name, _)| name.len() + 1)
+                .max()
+                .unwrap_or(1);
+            write_element(&mut body, MI_INT32, &(name_len as i32).to_le_bytes())?;
+
+            let mut names = Vec::with_capacity(fields.len() * name_len);
+            for (name, _) in fields {
+                names.extend(name.as_bytes());
+                names.resize(names.len() + name_len - name.len(), 0);
+            }
+            write_element(&mut body, MI_INT8, &names)?;
+
+            for (_, field) in fields {
+                write_matrix(&mut body, "", field)?;
+            }
+        }
+    }
+
+    write_element(out, MI_MATRIX, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wpilog::{parser::parse_wpilog, writer::WpiLogWriter};
+
+    fn field_names(array: &MatArray) -> Vec<&str> {
+        match array {
+            MatArray::Struct(fields) => fields.iter().map(|(name, _)| name.as_str()).collect(),
+            _ => panic!("{:?} is not a struct", array),
+        }
+    }
+
+    fn field<'a>(array: &'a MatArray, name: &str) -> &'a MatArray {
+        match array {
+            MatArray::Struct(fields) => &fields.iter().find(|(field, _)| field == name).unwrap().1,
+            _ => panic!("{:?} is not a struct", array),
+        }
+    }
+
+    fn path(path: &[&str]) -> Vec<String> {
+        path.iter().map(|part| part.to_string()).collect()
+    }
+
+    fn value(val: f64) -> Vec<(String, MatArray)> {
+        vec![
+            ("time".to_string(), MatArray::Double(1, 1, vec![0.0])),
+            ("value".to_string(), MatArray::Double(1, 1, vec![val])),
+        ]
+    }
+
+    #[test]
+    fn names_are_made_valid() {
+        assert_eq!(make_valid_name("LeftVelocity"), "LeftVelocity");
+        assert_eq!(
+            make_valid_name("Left Velocity (m/s)"),
+            "Left_Velocity__m_s_"
+        );
+        assert_eq!(make_valid_name("1stStage"), "x1stStage");
+        assert_eq!(make_valid_name("_private"), "x_private");
+        assert_eq!(make_valid_name("Ä"), "x_");
+        assert_eq!(make_valid_name(&"a".repeat(70)).len(), NAME_LENGTH_MAX);
+    }
+
+    #[test]
+    fn suffixed_names_stay_within_the_limit() {
+        let prefix = "a".repeat(66);
+        let mut root = Node::default();
+        for name in [format!("{}Left", prefix), format!("{}Rght", prefix)] {
+            assert_eq!(name.len(), 70);
+            root.insert(&[make_valid_name(&name)], value(1.0));
+        }
+
+        let names = root
+            .children
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [prefix[..63].to_string(), format!("{}_1", &prefix[..61])]
+        );
+
+        // children named like the entry's own fields are shortened for their underscore too
+        let mut node = Node {
+            fields: Some(vec![("a".repeat(63), MatArray::UInt8(Vec::new()))]),
+            ..Node::default()
+        };
+        node.insert(&["a".repeat(63)], value(2.0));
+        node.insert(&["a".repeat(63)], value(3.0));
+        let array = node.into_array();
+        assert_eq!(
+            field_names(&array),
+            [
+                "a".repeat(63),
+                format!("{}_", "a".repeat(62)),
+                format!("{}_1", "a".repeat(61))
+            ]
+        );
+    }
+
+    #[test]
+    fn entries_nest_into_structs() {
+        let mut root = Node::default();
+        root.insert(&path(&["Drive", "Left"]), value(1.0));
+        root.insert(&path(&["Drive", "time"]), value(2.0));
+        root.insert(&path(&["Drive"]), value(3.0));
+        root.insert(&path(&["a_b"]), value(4.0));
+        // `a-b` and `a_b` both sanitize to `a_b`
+        root.insert(&path(&["a_b"]), value(5.0));
+
+        let names = root
+            .children
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Drive", "a_b", "a_b_1"]);
+
+        let drive = root.children.remove(0).1.into_array();
+        // children named like the entry's own fields get an underscore
+        assert_eq!(field_names(&drive), ["time", "value", "Left", "time_"]);
+        assert!(matches!(field(&drive, "value"), MatArray::Double(1, 1, val) if val[0] == 3.0));
+        assert!(matches!(
+            field(field(&drive, "time_"), "value"),
+            MatArray::Double(1, 1, val) if val[0] == 2.0
+        ));
+    }
+
+    #[test]
+    fn elements_are_tagged_and_padded() {
+        let mut out = Vec::new();
+        write_element(&mut out, MI_INT8, b"abc").unwrap();
+        assert_eq!(
+            out,
+            [1, 0, 0, 0, 3, 0, 0, 0, b'a', b'b', b'c', 0, 0, 0, 0, 0]
+        );
+
+        out.clear();
+        write_element(&mut out, MI_UINT8, &[]).unwrap();
+        assert_eq!(out, [2, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(mat_size::<u32>(u32::MAX as usize).unwrap(), u32::MAX);
+        let err = mat_size::<u32>(u32::MAX as usize + 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(mat_size::<i32>(i32::MAX as usize + 1).is_err());
+    }
+
+    #[test]
+    fn matrices_are_written_byte_for_byte() {
+        let mut out = Vec::new();
+        write_matrix(&mut out, "time", &MatArray::Double(2, 1, vec![1.0, 2.0])).unwrap();
+
+        let mut expected = vec![14, 0, 0, 0, 72, 0, 0, 0];
+        // array flags, double class
+        expected.extend([6, 0, 0, 0, 8, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+        // dimensions, 2 x 1
+        expected.extend([5, 0, 0, 0, 8, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0]);
+        // name, padded to 8 bytes
+        expected.extend([1, 0, 0, 0, 4, 0, 0, 0, b't', b'i', b'm', b'e', 0, 0, 0, 0]);
+        expected.extend([9, 0, 0, 0, 16, 0, 0, 0]);
+        expected.extend(1.0f64.to_le_bytes());
+        expected.extend(2.0f64.to_le_bytes());
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn files_start_with_a_header() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let entry = log.start("/Drive/Left Speed", "double", "", 0).unwrap();
+        log.append_value(entry, 1, &DataType::Double(1.0)).unwrap();
+        let data = log.into_inner().unwrap();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let mut out = Vec::new();
+        export_mat(&mut out, &log, &log.get_entry_metadata()).unwrap();
+
+        assert!(out.starts_with(b"MATLAB 5.0 MAT-file, written by wpilog-reader   "));
+        assert!(out[..116].is_ascii());
+        assert_eq!(&out[116..128], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 1, b'I', b'M']);
+
+        // one top-level struct variable, named after the first part of the entry name
+        assert_eq!(&out[128..132], &14u32.to_le_bytes());
+        assert_eq!(
+            u32::from_le_bytes(out[132..136].try_into().unwrap()) as usize,
+            out.len() - 136
+        );
+        assert_eq!(&out[136..148], &[6, 0, 0, 0, 8, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(&out[168..181], b"\x01\x00\x00\x00\x05\x00\x00\x00Drive");
+        assert!(out
+            .windows("Left_Speed".len())
+            .any(|window| window == b"Left_Speed"));
+    }
+}
@@ -0,0 +1,335 @@
+use std::{collections::HashMap, io};
+
+use serde_json::{json, Value};
+
+use crate::{
+    mcap::writer::McapWriter,
+    wpilog::{
+        parser::parse_data,
+        structs::{StructFieldType, StructRegistry, StructSchema},
+        types::{DataType, MetadataEntry, Record, WpiLog},
+    },
+};
+
+const MAX_DEPTH: usize = 32;
+
+/// Writes an MCAP file with a JSON channel per entry in `metadata`, named after the entry, and a
+/// JSON Schema per entry type. Log times are the record timestamps in nanoseconds.
+///
+/// Structs with a schema in the log are written as objects and everything else, including arrays
+/// of structs, as `{"value": ...}`. Struct records that don't decode with their schema are left
+/// out, as they wouldn't match the channel's schema. `json` entries are embedded as JSON when they
+/// are valid.
+pub fn export_mcap<W: io::Write>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+) -> io::Result<()> {
+    let structs = StructRegistry::from_log(log);
+    let mut mcap = McapWriter::new(writer)?;
+
+    let mut schemas = HashMap::new();
+    let mut channels = HashMap::new();
+
+    for entry in metadata {
+        let schema_id = match schemas.get(entry.typ) {
+            Some(id) => *id,
+            None => {
+                let schema = json_schema(entry.typ, &structs);
+                let id = mcap.add_schema(entry.typ, "jsonschema", schema.to_string().as_bytes())?;
+                schemas.insert(entry.typ, id);
+                id
+            }
+        };
+
+        let channel_id = mcap.add_channel(
+            schema_id,
+            entry.name,
+            "json",
+            &HashMap::from([("type", entry.typ), ("metadata", entry.metadata)]),
+        )?;
+        channels.insert(entry.entry_id, (entry, channel_id, 0u32));
+    }
+
+    let mut records = log.records.iter().collect::<Vec<_>>();
+    records.sort_by_key(|record| record.timestamp_us);
+
+    for record in records {
+        let Record::Data(data) = record.data else {
+            continue;
+        };
+        let Some((entry, channel_id, sequence)) = channels.get_mut(&record.entry_id) else {
+            continue;
+        };
+
+        let message = match structs.decode(entry.typ, data) {
+            Some(value) if !entry.typ.ends_with("[]") => serde_json::to_vec(&value),
+            Some(value) => serde_json::to_vec(&json!({ "value": value })),
+            None if structs.resolve_type(entry.typ).is_some() => continue,
+            None => match parse_data(entry.typ, data)
+                .map(|(_, value)| value)
+                .unwrap_or(DataType::Raw(data))
+            {
+                DataType::String(val) if entry.typ == "json" => {
+                    let val = serde_json::from_str::<Value>(val)
+                        .unwrap_or_else(|_| Value::String(val.to_string()));
+                    serde_json::to_vec(&json!({ "value": val }))
+                }
+                value => serde_json::to_vec(&json!({ "value": value })),
+            },
+        }?;
+
+        let log_time = record.timestamp_us * 1000;
+        mcap.write_message(*channel_id, *sequence, log_time, log_time, &message)?;
+        *sequence += 1;
+    }
+
+    mcap.finish()?;
+
+    Ok(())
+}
+
+fn json_schema(typ: &str, structs: &StructRegistry) -> Value {
+    match structs.resolve_type(typ) {
+        Some((schema, false)) => struct_schema(schema, structs, 0),
+        Some((schema, true)) => value_schema(
+            typ,
+            json!({ "type": "array", "items": struct_schema(schema, structs, 0) }),
+        ),
+        None => {
+            let item = |typ| match typ {
+                "boolean" => json!({ "type": "boolean" }),
+                "int64" => json!({ "type": "integer" }),
+                "float" | "double" => json!({ "type": "number" }),
+                "string" => json!({ "type": "string" }),
+                _ => json!({}),
+            };
+
+            let value = match typ {
+                "json" => json!({}),
+                "boolean" | "int64" | "float" | "double" | "string" => item(typ),
+                "structschema" => item("string"),
+                "boolean[]" | "int64[]" | "float[]" | "double[]" | "string[]" => {
+                    json!({ "type": "array", "items": item(typ.trim_end_matches("[]")) })
+                }
+                // raw data is written as an array of bytes
+                _ => json!({
+                    "type": "array",
+                    "items": { "type": "integer", "minimum": 0, "maximum": 255 },
+                }),
+            };
+
+            value_schema(typ, value)
+        }
+    }
+}
+
+fn value_schema(typ: &str, value: Value) -> Value {
+    json!({
+        "title": typ,
+        "type": "object",
+        "properties": { "value": value },
+        "required": ["value"],
+    })
+}
+
+fn struct_schema(schema: &StructSchema, structs: &StructRegistry, depth: usize) -> Value {
+    let mut properties = serde_json::Map::new();
+
+    for field in &schema.fields {
+        let item = match &field.typ {
+            StructFieldType::Bool => json!({ "type": "boolean" }),
+            StructFieldType::Char => json!({ "type": "string" }),
+            StructFieldType::Float | StructFieldType::Double => json!({ "type": "number" }),
+            StructFieldType::Struct(name) => match structs.get(name) {
+                Some(schema) if depth < MAX_DEPTH => struct_schema(schema, structs, depth + 1),
+                _ => json!({}),
+            },
+            _ => json!({ "type": "integer" }),
+        };
+
+        let property = match (field.array_len, &field.typ) {
+            // char arrays are decoded as strings
+            (Some(_), StructFieldType::Char) | (None, _) => item,
+            (Some(len), _) => json!({
+                "type": "array",
+                "items": item,
+                "minItems": len,
+                "maxItems": len,
+            }),
+        };
+
+        properties.insert(field.name.clone(), property);
+    }
+
+    json!({
+        "title": schema.name,
+        "type": "object",
+        "properties": properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::tests::sample_log,
+        mcap::{MCap, Parse},
+        wpilog::{parser::parse_wpilog, writer::WpiLogWriter},
+    };
+
+    fn export(data: &[u8]) -> Vec<u8> {
+        let (_, log) = parse_wpilog(data).unwrap();
+        let mut metadata = log.get_entry_metadata();
+        metadata.sort_by_key(|entry| entry.entry_id);
+
+        let mut out = Vec::new();
+        export_mcap(&mut out, &log, &metadata).unwrap();
+        out
+    }
+
+    fn messages(mcap: &MCap) -> Vec<(String, u32, u64, String)> {
+        mcap.messages()
+            .map(|event| {
+                let event = event.unwrap();
+                (
+                    event.channel.topic().to_string(),
+                    event.message.sequence(),
+                    event.message.log_time(),
+                    String::from_utf8(event.message.data().to_vec()).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn channel_schema(mcap: &MCap, topic: &str) -> Value {
+        let (_, schema) = mcap
+            .channels()
+            .unwrap()
+            .into_iter()
+            .find(|(channel, _)| channel.topic() == topic)
+            .unwrap();
+        serde_json::from_slice(schema.unwrap().data()).unwrap()
+    }
+
+    #[test]
+    fn entries_become_json_channels() {
+        let data = export(&sample_log());
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        let channels = mcap.channels().unwrap();
+        assert_eq!(channels.len(), 4);
+        let (channel, schema) = &channels[0];
+        assert_eq!(channel.topic(), "/Drive/Speed");
+        assert_eq!(channel.message_encoding(), "json");
+        assert_eq!(channel.metadata()["type"], "double");
+        assert_eq!(channel.metadata()["metadata"], "{\"unit\":\"m/s\"}");
+        let schema = schema.as_ref().unwrap();
+        assert_eq!(schema.name(), "double");
+        assert_eq!(schema.encoding(), "jsonschema");
+
+        let message = |topic: &str, sequence, log_time, data: &str| {
+            (topic.to_string(), sequence, log_time, data.to_string())
+        };
+        assert_eq!(
+            messages(&mcap),
+            [
+                message("/Drive/Speed", 0, 1_000_000_000, r#"{"value":1.5}"#),
+                message("/Drive/Modules", 0, 1_000_000_000, r#"{"value":["a","b"]}"#),
+                message("/Drive/Modules", 1, 1_500_000_000, r#"{"value":[]}"#),
+                message("/Enabled", 0, 1_500_000_000, r#"{"value":true}"#),
+                message("/Drive/Speed", 1, 2_000_000_000, r#"{"value":2.5}"#),
+                message("/Raw", 0, 2_000_000_000, r#"{"value":[1,2]}"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn primitive_types_have_value_schemas() {
+        let data = export(&sample_log());
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        assert_eq!(
+            channel_schema(&mcap, "/Drive/Modules"),
+            json!({
+                "title": "string[]",
+                "type": "object",
+                "properties": {
+                    "value": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["value"],
+            })
+        );
+        assert_eq!(
+            channel_schema(&mcap, "/Raw")["properties"]["value"]["items"],
+            json!({ "type": "integer", "minimum": 0, "maximum": 255 })
+        );
+    }
+
+    #[test]
+    fn structs_and_json_are_embedded() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let schema = log
+            .start("/.schema/struct:Module", "structschema", "", 0)
+            .unwrap();
+        let module = log.start("/Module", "struct:Module", "", 0).unwrap();
+        let modules = log.start("/Modules", "struct:Module[]", "", 0).unwrap();
+        let json = log.start("/Json", "json", "", 0).unwrap();
+
+        log.append(schema, 1, b"char name[2];double angle[2]")
+            .unwrap();
+        let mut value = b"fl".to_vec();
+        value.extend(0.5f64.to_le_bytes());
+        value.extend(1.5f64.to_le_bytes());
+        log.append(module, 2, &value).unwrap();
+        log.append(modules, 3, &[value.clone(), value].concat())
+            .unwrap();
+        log.append_value(json, 4, &DataType::String(r#"{"a":[1,null]}"#))
+            .unwrap();
+        log.append_value(json, 5, &DataType::String("{")).unwrap();
+        // too short for a Module, or for a whole number of them
+        log.append(module, 6, b"fl").unwrap();
+        log.append(modules, 7, b"fl").unwrap();
+
+        let data = export(&log.into_inner().unwrap());
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        let data = messages(&mcap)
+            .into_iter()
+            .map(|(_, _, _, data)| serde_json::from_str::<Value>(&data).unwrap())
+            .collect::<Vec<_>>();
+        let value = json!({ "name": "fl", "angle": [0.5, 1.5] });
+        assert_eq!(
+            data[1..],
+            [
+                value.clone(),
+                json!({ "value": [value.clone(), value] }),
+                json!({ "value": { "a": [1, null] } }),
+                json!({ "value": "{" }),
+            ]
+        );
+
+        let module = json!({
+            "title": "Module",
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "angle": {
+                    "type": "array",
+                    "items": { "type": "number" },
+                    "minItems": 2,
+                    "maxItems": 2,
+                },
+            },
+        });
+        assert_eq!(channel_schema(&mcap, "/Module"), module);
+        assert_eq!(
+            channel_schema(&mcap, "/Modules")["properties"]["value"],
+            json!({ "type": "array", "items": module })
+        );
+        assert_eq!(
+            channel_schema(&mcap, "/Json")["properties"]["value"],
+            json!({})
+        );
+    }
+}
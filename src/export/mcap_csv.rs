@@ -0,0 +1,150 @@
+use std::{collections::HashMap, fmt, io};
+
+use crate::mcap::{messages::MessageError, MCap, Record};
+
+#[derive(Debug)]
+pub enum McapCsvError {
+    Csv(csv::Error),
+    Mcap(MessageError),
+}
+
+impl fmt::Display for McapCsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            McapCsvError::Csv(err) => write!(f, "{}", err),
+            McapCsvError::Mcap(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for McapCsvError {}
+
+impl From<csv::Error> for McapCsvError {
+    fn from(value: csv::Error) -> Self {
+        McapCsvError::Csv(value)
+    }
+}
+
+impl From<io::Error> for McapCsvError {
+    fn from(value: io::Error) -> Self {
+        McapCsvError::Csv(value.into())
+    }
+}
+
+impl From<MessageError> for McapCsvError {
+    fn from(value: MessageError) -> Self {
+        McapCsvError::Mcap(value)
+    }
+}
+
+/// Writes one row per channel with its schema and message count. Counts come from the
+/// `Statistics` record when it has one for every channel, and are counted from the messages
+/// otherwise, as the per channel counts are optional.
+pub fn export_mcap_channels<W: io::Write>(writer: W, mcap: &MCap) -> Result<(), McapCsvError> {
+    let channels = mcap.channels()?;
+    let statistics = mcap
+        .records
+        .iter()
+        .find_map(|raw| match Record::try_from(*raw) {
+            Ok(Record::Statistics(statistics)) => Some(statistics),
+            _ => None,
+        });
+
+    let counts = match statistics {
+        Some(statistics)
+            if channels.iter().all(|(channel, _)| {
+                statistics
+                    .channel_message_counts()
+                    .contains_key(&channel.id())
+            }) =>
+        {
+            statistics.channel_message_counts().clone()
+        }
+        _ => {
+            let mut counts = HashMap::new();
+            for message in mcap.messages() {
+                *counts.entry(message?.message.channel_id()).or_insert(0) += 1;
+            }
+            counts
+        }
+    };
+
+    let mut csvwriter = csv::Writer::from_writer(writer);
+    csvwriter.write_record([
+        "channel_id",
+        "topic",
+        "message_encoding",
+        "schema_name",
+        "schema_encoding",
+        "message_count",
+    ])?;
+
+    for (channel, schema) in &channels {
+        csvwriter.write_record([
+            &channel.id().to_string(),
+            channel.topic(),
+            channel.message_encoding(),
+            schema.as_ref().map(|schema| schema.name()).unwrap_or(""),
+            schema
+                .as_ref()
+                .map(|schema| schema.encoding())
+                .unwrap_or(""),
+            &counts.get(&channel.id()).copied().unwrap_or(0).to_string(),
+        ])?;
+    }
+
+    csvwriter.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export::tests::sample_mcap, mcap::Parse};
+
+    #[test]
+    fn channels_are_summarized() {
+        let data = sample_mcap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        let mut out = Vec::new();
+        export_mcap_channels(&mut out, &mcap).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "channel_id,topic,message_encoding,schema_name,schema_encoding,message_count\n\
+             0,/pose,json,Pose,jsonschema,2\n\
+             1,/raw,,,,1\n\
+             2,/quiet,json,,,0\n"
+        );
+    }
+
+    #[test]
+    fn channels_are_counted_without_statistics_counts() {
+        let data = sample_mcap();
+        let (_, mut mcap) = MCap::parse(&data).unwrap();
+
+        // the same statistics with an empty channel_message_counts map, which follows the
+        // fixed size fields
+        let index = mcap
+            .records
+            .iter()
+            .position(|raw| matches!(Record::try_from(*raw), Ok(Record::Statistics(_))))
+            .unwrap();
+        let mut content = mcap.records[index].content[..42].to_vec();
+        content.extend(0u32.to_le_bytes());
+        mcap.records[index].content = &content;
+        assert!(matches!(
+            Record::try_from(mcap.records[index]),
+            Ok(Record::Statistics(statistics)) if statistics.channel_message_counts().is_empty()
+        ));
+
+        let mut out = Vec::new();
+        export_mcap_channels(&mut out, &mcap).unwrap();
+
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .ends_with("0,/pose,json,Pose,jsonschema,2\n1,/raw,,,,1\n2,/quiet,json,,,0\n"));
+    }
+}
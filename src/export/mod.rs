@@ -0,0 +1,310 @@
+use std::{collections::HashMap, fmt, io};
+
+use crate::{
+    source::{Entry, SourceError, Value},
+    wpilog::{structs::StructValue, types::DataType},
+};
+
+#[cfg(feature = "parquet")]
+pub mod columnar;
+pub mod influx;
+pub mod jsonl;
+pub mod mat;
+pub mod mcap;
+pub mod mcap_csv;
+pub mod npz;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod tidy;
+pub mod wide;
+
+#[derive(Debug)]
+pub enum CsvError {
+    Csv(csv::Error),
+    Source(SourceError),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Csv(err) => write!(f, "{}", err),
+            CsvError::Source(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<csv::Error> for CsvError {
+    fn from(value: csv::Error) -> Self {
+        CsvError::Csv(value)
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(value: io::Error) -> Self {
+        CsvError::Csv(value.into())
+    }
+}
+
+impl From<SourceError> for CsvError {
+    fn from(value: SourceError) -> Self {
+        CsvError::Source(value)
+    }
+}
+
+/// Makes `(name, entry id)` pairs into unique column or array names. Entries sharing a name, like
+/// a restarted entry, are told apart by appending `#<entry id>` to all but the one with the
+/// lowest id.
+pub(crate) fn unique_names<'a>(entries: impl IntoIterator<Item = (&'a str, u32)>) -> Vec<String> {
+    let entries = entries.into_iter().collect::<Vec<_>>();
+    let mut first_ids = HashMap::new();
+    for &(name, id) in &entries {
+        let first_id = first_ids.entry(name).or_insert(id);
+        *first_id = (*first_id).min(id);
+    }
+
+    entries
+        .iter()
+        .map(|&(name, id)| match first_ids[name] == id {
+            true => name.to_string(),
+            false => format!("{}#{}", name, id),
+        })
+        .collect()
+}
+
+/// Formats a decoded value the same way the wide CSV export does.
+pub(crate) fn format_value(value: &DataType) -> String {
+    match value {
+        DataType::Raw(data) => format!("{:X?}", data),
+        DataType::Boolean(val) => format!("{:X?}", *val as u8),
+        DataType::Int64(val) => format!("{}", val),
+        DataType::Float(val) => format!("{}", val),
+        DataType::Double(val) => format!("{}", val),
+        DataType::String(val) => val.to_string(),
+        DataType::BooleanArray(val) => serde_json::to_string(val).unwrap(),
+        DataType::Int64Array(val) => serde_json::to_string(val).unwrap(),
+        DataType::FloatArray(val) => serde_json::to_string(val).unwrap(),
+        DataType::DoubleArray(val) => serde_json::to_string(val).unwrap(),
+        DataType::StringArray(val) => serde_json::to_string(val).unwrap(),
+    }
+}
+
+/// Formats each element of an array value, scalars produce a single element.
+pub(crate) fn format_elements(value: &DataType) -> Vec<String> {
+    match value {
+        DataType::BooleanArray(val) => val.iter().map(|v| format!("{:X?}", *v as u8)).collect(),
+        DataType::Int64Array(val) => val.iter().map(|v| format!("{}", v)).collect(),
+        DataType::FloatArray(val) => val.iter().map(|v| format!("{}", v)).collect(),
+        DataType::DoubleArray(val) => val.iter().map(|v| format!("{}", v)).collect(),
+        DataType::StringArray(val) => val.iter().map(|v| v.to_string()).collect(),
+        _ => vec![format_value(value)],
+    }
+}
+
+/// A value written to a single CSV cell.
+pub(crate) enum Leaf<'v> {
+    Data(&'v DataType<'v>),
+    Struct(&'v StructValue),
+    Raw(&'v [u8]),
+}
+
+impl Leaf<'_> {
+    pub(crate) fn format(&self) -> String {
+        match self {
+            Leaf::Data(value) => format_value(value),
+            Leaf::Struct(value) => format_struct_value(value),
+            Leaf::Raw(data) => format!("{:X?}", data),
+        }
+    }
+
+    /// Each formatted element of an array, `None` for other values.
+    pub(crate) fn elements(&self) -> Option<Vec<String>> {
+        match self {
+            Leaf::Data(value) if value.is_array() => Some(format_elements(value)),
+            Leaf::Struct(StructValue::Array(elements)) => {
+                Some(elements.iter().map(format_struct_value).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the value is an array that may get a column per element, which string arrays
+    /// don't.
+    pub(crate) fn is_expandable(&self) -> bool {
+        match self {
+            Leaf::Data(DataType::StringArray(_)) => false,
+            Leaf::Data(value) => value.is_array(),
+            Leaf::Struct(StructValue::Array(elements)) => !elements
+                .iter()
+                .any(|element| matches!(element, StructValue::String(_))),
+            _ => false,
+        }
+    }
+}
+
+/// Splits the value of a sample of `entry` into `(name, type, leaf)` cells. Values that aren't
+/// split are a single cell named and typed after the entry. Decoded structs are split into their
+/// members, and arrays holding structs or arrays into `[index]` elements, the way expanded wpilog
+/// arrays are named.
+pub(crate) fn leaves<'v>(entry: &Entry, value: &'v Value) -> Vec<(String, String, Leaf<'v>)> {
+    match value {
+        Value::Data(value) => vec![(entry.name.clone(), entry.typ.clone(), Leaf::Data(value))],
+        Value::Raw(data) => vec![(entry.name.clone(), "raw".to_string(), Leaf::Raw(data))],
+        Value::Struct(value) => {
+            let mut fields = Vec::new();
+            flatten(entry.name.clone(), value, &mut fields);
+            fields
+                .into_iter()
+                .map(|(name, value)| match name == entry.name {
+                    // a value that wasn't split, like a decoded `{"value": ...}` message
+                    true => (name, entry.typ.clone(), Leaf::Struct(value)),
+                    false => (name, type_name(value), Leaf::Struct(value)),
+                })
+                .collect()
+        }
+    }
+}
+
+fn flatten<'v>(name: String, value: &'v StructValue, fields: &mut Vec<(String, &'v StructValue)>) {
+    match value {
+        StructValue::Struct(members) => {
+            for (member, value) in members {
+                flatten(format!("{}/{}", name, member), value, fields);
+            }
+        }
+        StructValue::Array(elements)
+            if elements.iter().any(|element| {
+                matches!(element, StructValue::Struct(_) | StructValue::Array(_))
+            }) =>
+        {
+            for (index, element) in elements.iter().enumerate() {
+                flatten(format!("{}/[{}]", name, index), element, fields);
+            }
+        }
+        value => fields.push((name, value)),
+    }
+}
+
+/// The wpilog type name of a decoded value, `json` for arrays of mixed or unknown type.
+fn type_name(value: &StructValue) -> String {
+    match value {
+        StructValue::Bool(_) => "boolean".to_string(),
+        StructValue::Int(_) | StructValue::UInt(_) => "int64".to_string(),
+        StructValue::Float(_) => "float".to_string(),
+        StructValue::Double(_) => "double".to_string(),
+        StructValue::String(_) => "string".to_string(),
+        StructValue::Array(elements) => {
+            let mut types = elements.iter().map(type_name);
+            match types.next() {
+                Some(first) if types.all(|typ| typ == first) && !first.ends_with("[]") => {
+                    format!("{}[]", first)
+                }
+                _ => "json".to_string(),
+            }
+        }
+        StructValue::Struct(_) => "json".to_string(),
+    }
+}
+
+/// Formats a decoded value like [`format_value`] does wpilog values.
+fn format_struct_value(value: &StructValue) -> String {
+    match value {
+        StructValue::Bool(val) => format!("{:X?}", *val as u8),
+        StructValue::Int(val) => format!("{}", val),
+        StructValue::UInt(val) => format!("{}", val),
+        StructValue::Float(val) => format!("{}", val),
+        StructValue::Double(val) => format!("{}", val),
+        StructValue::String(val) => val.clone(),
+        StructValue::Array(_) | StructValue::Struct(_) => serde_json::to_string(value).unwrap(),
+    }
+}
+
+/// Formats a timestamp in nanoseconds as seconds, without losing precision.
+pub(crate) fn format_timestamp(timestamp_ns: u64) -> String {
+    let (seconds, nanos) = (timestamp_ns / 1_000_000_000, timestamp_ns % 1_000_000_000);
+    if nanos == 0 {
+        return seconds.to_string();
+    }
+
+    let nanos = format!("{:09}", nanos);
+    format!("{}.{}", seconds, nanos.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use super::format_timestamp;
+    use crate::{
+        mcap::writer::McapWriter,
+        wpilog::{types::DataType, writer::WpiLogWriter},
+    };
+
+    /// A small log shared by the export tests, with `/Enabled` finished at 2.5 s.
+    pub(crate) fn sample_log() -> Vec<u8> {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let speed = log
+            .start("/Drive/Speed", "double", "{\"unit\":\"m/s\"}", 0)
+            .unwrap();
+        let modules = log.start("/Drive/Modules", "string[]", "", 0).unwrap();
+        let enabled = log.start("/Enabled", "boolean", "", 0).unwrap();
+        let raw = log.start("/Raw", "raw", "", 0).unwrap();
+
+        log.append_value(speed, 1_000_000, &DataType::Double(1.5))
+            .unwrap();
+        log.append_value(modules, 1_000_000, &DataType::StringArray(vec!["a", "b"]))
+            .unwrap();
+        log.append_value(modules, 1_500_000, &DataType::StringArray(vec![]))
+            .unwrap();
+        log.append_value(enabled, 1_500_000, &DataType::Boolean(true))
+            .unwrap();
+        log.append_value(speed, 2_000_000, &DataType::Double(2.5))
+            .unwrap();
+        log.append(raw, 2_000_000, &[1, 2]).unwrap();
+        log.finish(enabled, 2_500_000).unwrap();
+
+        log.into_inner().unwrap()
+    }
+
+    /// An MCAP file shared by the export tests, with a JSON channel, a channel without a decoder
+    /// and a channel without messages.
+    pub(crate) fn sample_mcap() -> Vec<u8> {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+
+        let schema = mcap.add_schema("Pose", "jsonschema", b"{}").unwrap();
+        let pose = mcap
+            .add_channel(schema, "/pose", "json", &HashMap::new())
+            .unwrap();
+        let raw = mcap.add_channel(0, "/raw", "", &HashMap::new()).unwrap();
+        mcap.add_channel(0, "/quiet", "json", &HashMap::new())
+            .unwrap();
+
+        mcap.write_message(
+            pose,
+            0,
+            2_000_000_000,
+            0,
+            br#"{"x": 1.5, "ok": true, "tags": ["a"], "wheels": [{"v": 1}, {"v": 2}]}"#,
+        )
+        .unwrap();
+        mcap.write_message(raw, 0, 1_500_000_000, 0, &[1, 2])
+            .unwrap();
+        mcap.write_message(pose, 1, 3_000_000_000, 0, br#"{"x": 2.5}"#)
+            .unwrap();
+
+        mcap.finish().unwrap()
+    }
+
+    #[test]
+    fn timestamps_keep_every_nanosecond() {
+        assert_eq!(format_timestamp(0), "0");
+        assert_eq!(format_timestamp(2_000_000_000), "2");
+        assert_eq!(format_timestamp(1_500_000_000), "1.5");
+        assert_eq!(format_timestamp(1_000_000_001), "1.000000001");
+        assert_eq!(
+            format_timestamp(1_700_000_000_123_456_789),
+            "1700000000.123456789"
+        );
+    }
+}
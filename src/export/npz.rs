@@ -0,0 +1,370 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use zip::{result::ZipResult, write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use super::unique_names;
+use crate::wpilog::{
+    parser::parse_data,
+    types::{DataType, MetadataEntry, Record, WpiLog},
+};
+
+/// Writes a NumPy `.npz` archive with a `<name>_t` (int64 microseconds) and a `<name>_v` array per
+/// entry in `metadata`, as `numpy.savez` would.
+///
+/// Booleans, integers, floats and doubles keep their dtype, strings are fixed width unicode.
+/// Numeric array entries whose records all have the same length become 2-D `(samples, length)`
+/// arrays; otherwise the elements are concatenated into `<name>_v` and the length of each sample
+/// is stored in `<name>_len`. Entries of other types are skipped. Leading slashes are removed
+/// from entry names, and entries that end up sharing a name get an `#<entry id>` suffix.
+pub fn export_npz<W: io::Write + io::Seek>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+) -> ZipResult<()> {
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    let mut samples: HashMap<u32, Vec<(u64, &[u8])>> = HashMap::new();
+    for record in &log.records {
+        if let Record::Data(data) = record.data {
+            samples
+                .entry(record.entry_id)
+                .or_default()
+                .push((record.timestamp_us, data));
+        }
+    }
+
+    let names = unique_names(
+        metadata
+            .iter()
+            .map(|entry| (entry.name.trim_start_matches('/'), entry.entry_id)),
+    );
+
+    for (entry, name) in metadata.iter().zip(names) {
+        let Some(samples) = samples.get_mut(&entry.entry_id) else {
+            continue;
+        };
+        samples.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let descr = match entry.typ {
+            "boolean" | "boolean[]" => "|b1",
+            "int64" | "int64[]" => "<i8",
+            "float" | "float[]" => "<f4",
+            "double" | "double[]" => "<f8",
+            "string" => "<U",
+            _ => continue,
+        };
+
+        let mut timestamps = Vec::with_capacity(samples.len());
+        let mut values = Vec::new();
+        let mut lengths = Vec::new();
+        let mut strings = Vec::new();
+
+        for (timestamp, data) in samples.iter() {
+            let Ok((_, value)) = parse_data(entry.typ, data) else {
+                continue;
+            };
+
+            match value {
+                DataType::Boolean(val) => values.push(val as u8),
+                DataType::Int64(val) => values.extend(val.to_le_bytes()),
+                DataType::Float(val) => values.extend(val.to_le_bytes()),
+                DataType::Double(val) => values.extend(val.to_le_bytes()),
+                DataType::String(val) => strings.push(val),
+                DataType::BooleanArray(val) => {
+                    lengths.push(val.len() as i64);
+                    values.extend(val.iter().map(|val| *val as u8));
+                }
+                DataType::Int64Array(val) => {
+                    lengths.push(val.len() as i64);
+                    values.extend(val.iter().flat_map(|val| val.to_le_bytes()));
+                }
+                DataType::FloatArray(val) => {
+                    lengths.push(val.len() as i64);
+                    values.extend(val.iter().flat_map(|val| val.to_le_bytes()));
+                }
+                DataType::DoubleArray(val) => {
+                    lengths.push(val.len() as i64);
+                    values.extend(val.iter().flat_map(|val| val.to_le_bytes()));
+                }
+                _ => continue,
+            }
+
+            timestamps.push(*timestamp as i64);
+        }
+
+        zip.start_file(format!("{}_t.npy", name), options)?;
+        write_npy(
+            &mut zip,
+            "<i8",
+            &[timestamps.len()],
+            &timestamps
+                .iter()
+                .flat_map(|timestamp| timestamp.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )?;
+
+        zip.start_file(format!("{}_v.npy", name), options)?;
+        if entry.typ == "string" {
+            let width = strings
+                .iter()
+                .map(|val| val.chars().count())
+                .max()
+                .unwrap_or_default()
+                .max(1);
+
+            // fixed width UTF-32, zero padded
+            let mut data = Vec::with_capacity(strings.len() * width * 4);
+            for val in &strings {
+                let mut chars = 0;
+                for c in val.chars() {
+                    data.extend((c as u32).to_le_bytes());
+                    chars += 1;
+                }
+                data.resize(data.len() + (width - chars) * 4, 0);
+            }
+
+            write_npy(&mut zip, &format!("<U{}", width), &[strings.len()], &data)?;
+        } else if !entry.is_array() {
+            write_npy(&mut zip, descr, &[timestamps.len()], &values)?;
+        } else if let Some((len, _)) = entry.all_same_length {
+            write_npy(&mut zip, descr, &[timestamps.len(), len], &values)?;
+        } else {
+            let total = lengths.iter().sum::<i64>() as usize;
+            write_npy(&mut zip, descr, &[total], &values)?;
+
+            zip.start_file(format!("{}_len.npy", name), options)?;
+            write_npy(
+                &mut zip,
+                "<i8",
+                &[lengths.len()],
+                &lengths
+                    .iter()
+                    .flat_map(|len| len.to_le_bytes())
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+    }
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+/// Writes a version 1.0 `.npy` file holding a C ordered array.
+fn write_npy<W: Write>(
+    writer: &mut W,
+    descr: &str,
+    shape: &[usize],
+    data: &[u8],
+) -> io::Result<()> {
+    let shape = match shape {
+        [len] => format!("({},)", len),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|len| len.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+
+    // magic, version and header length take 10 bytes, the header ends in a newline and pads
+    // the data to a multiple of 64 bytes
+    let padding = 63 - (10 + header.len()) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::wpilog::{parser::parse_wpilog, writer::WpiLogWriter};
+
+    /// The header dict and data of every array in an archive, by file name.
+    fn read_npz(data: Vec<u8>) -> Vec<(String, String, Vec<u8>)> {
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        let mut arrays = Vec::new();
+
+        for ind in 0..archive.len() {
+            let mut file = archive.by_index(ind).unwrap();
+            let mut npy = Vec::new();
+            file.read_to_end(&mut npy).unwrap();
+
+            assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+            let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+            assert_eq!((10 + header_len) % 64, 0);
+            let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+
+            arrays.push((
+                file.name().to_string(),
+                header.trim_end().to_string(),
+                npy[10 + header_len..].to_vec(),
+            ));
+        }
+
+        arrays
+    }
+
+    fn export(log: Vec<u8>) -> Vec<(String, String, Vec<u8>)> {
+        let (_, log) = parse_wpilog(&log).unwrap();
+        let mut metadata = log.get_entry_metadata();
+        metadata.sort_by_key(|entry| entry.entry_id);
+
+        let mut out = Cursor::new(Vec::new());
+        export_npz(&mut out, &log, &metadata).unwrap();
+        read_npz(out.into_inner())
+    }
+
+    fn header(descr: &str, shape: &str) -> String {
+        format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr, shape
+        )
+    }
+
+    #[test]
+    fn npy_headers_are_padded() {
+        let mut npy = Vec::new();
+        write_npy(&mut npy, "<f8", &[2, 3], &[0; 48]).unwrap();
+
+        let header = header("<f8", "(2, 3)");
+        assert_eq!(npy.len(), 128 + 48);
+        assert_eq!(&npy[..10], b"\x93NUMPY\x01\x00\x76\x00");
+        assert_eq!(&npy[10..10 + header.len()], header.as_bytes());
+        assert_eq!(npy[127], b'\n');
+        assert!(npy[10 + header.len()..127].iter().all(|c| *c == b' '));
+    }
+
+    #[test]
+    fn entries_become_arrays() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let flag = log.start("/flag", "boolean", "", 0).unwrap();
+        let name = log.start("/name", "string", "", 0).unwrap();
+        let fixed = log.start("/fixed", "int64[]", "", 0).unwrap();
+        let ragged = log.start("/ragged", "float[]", "", 0).unwrap();
+        log.start("/raw", "raw", "", 0).unwrap();
+
+        log.append_value(flag, 2, &DataType::Boolean(true)).unwrap();
+        log.append_value(flag, 1, &DataType::Boolean(false))
+            .unwrap();
+        log.append_value(name, 1, &DataType::String("ab")).unwrap();
+        log.append_value(name, 2, &DataType::String("é")).unwrap();
+        log.append_value(fixed, 1, &DataType::Int64Array(vec![1, 2]))
+            .unwrap();
+        log.append_value(fixed, 2, &DataType::Int64Array(vec![3, 4]))
+            .unwrap();
+        log.append_value(ragged, 1, &DataType::FloatArray(vec![0.5]))
+            .unwrap();
+        log.append_value(ragged, 2, &DataType::FloatArray(vec![]))
+            .unwrap();
+
+        let arrays = export(log.into_inner().unwrap());
+        let names = arrays
+            .iter()
+            .map(|(name, ..)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "flag_t.npy",
+                "flag_v.npy",
+                "name_t.npy",
+                "name_v.npy",
+                "fixed_t.npy",
+                "fixed_v.npy",
+                "ragged_t.npy",
+                "ragged_v.npy",
+                "ragged_len.npy"
+            ]
+        );
+
+        let timestamps = [1i64, 2]
+            .iter()
+            .flat_map(|val| val.to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(arrays[0].1, header("<i8", "(2,)"));
+        assert_eq!(arrays[0].2, timestamps);
+        // samples are sorted by timestamp
+        assert_eq!(arrays[1].1, header("|b1", "(2,)"));
+        assert_eq!(arrays[1].2, [0, 1]);
+
+        assert_eq!(arrays[3].1, header("<U2", "(2,)"));
+        assert_eq!(
+            arrays[3].2,
+            [97, 0, 0, 0, 98, 0, 0, 0, 0xE9, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        assert_eq!(arrays[5].1, header("<i8", "(2, 2)"));
+        assert_eq!(
+            arrays[5].2,
+            [1i64, 2, 3, 4]
+                .iter()
+                .flat_map(|val| val.to_le_bytes())
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(arrays[7].1, header("<f4", "(1,)"));
+        assert_eq!(arrays[7].2, 0.5f32.to_le_bytes());
+        assert_eq!(arrays[8].1, header("<i8", "(2,)"));
+        assert_eq!(
+            arrays[8].2,
+            [1i64, 0]
+                .iter()
+                .flat_map(|val| val.to_le_bytes())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn shared_names_are_suffixed() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let first = log.start("/speed", "double", "", 0).unwrap();
+        log.finish(first, 1).unwrap();
+        let restarted = log.start("/speed", "double", "", 2).unwrap();
+        let unslashed = log.start("speed", "double", "", 2).unwrap();
+        for entry in [first, restarted, unslashed] {
+            log.append_value(entry, 1, &DataType::Double(entry as f64))
+                .unwrap();
+        }
+
+        let arrays = export(log.into_inner().unwrap());
+        let values = arrays
+            .iter()
+            .filter(|(name, ..)| name.ends_with("_v.npy"))
+            .map(|(name, _, data)| {
+                (
+                    name.as_str(),
+                    f64::from_le_bytes(data[..].try_into().unwrap()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("speed_v.npy", 1.0),
+                ("speed#2_v.npy", 2.0),
+                ("speed#3_v.npy", 3.0)
+            ]
+        );
+    }
+}
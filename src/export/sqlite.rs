@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection};
+
+use crate::wpilog::{
+    parser::parse_data,
+    structs::StructRegistry,
+    types::{DataType, MetadataEntry, Record, WpiLog},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS logs (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    extra_header TEXT NOT NULL,
+    start_time INTEGER,
+    end_time INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS entries (
+    log_id INTEGER NOT NULL REFERENCES logs (id),
+    id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    record_count INTEGER NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER NOT NULL,
+    finished INTEGER NOT NULL,
+    PRIMARY KEY (log_id, id)
+);
+
+CREATE TABLE IF NOT EXISTS samples (
+    log_id INTEGER NOT NULL,
+    entry_id INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    value_int INTEGER,
+    value_real REAL,
+    value_text TEXT,
+    value_blob BLOB,
+    FOREIGN KEY (log_id, entry_id) REFERENCES entries (log_id, id)
+);
+
+CREATE INDEX IF NOT EXISTS samples_by_entry ON samples (log_id, entry_id, timestamp);
+
+CREATE VIEW IF NOT EXISTS named_samples AS
+SELECT
+    logs.name AS log,
+    entries.name AS entry,
+    entries.type AS type,
+    samples.timestamp,
+    samples.value_int,
+    samples.value_real,
+    samples.value_text,
+    samples.value_blob
+FROM samples
+JOIN entries ON entries.log_id = samples.log_id AND entries.id = samples.entry_id
+JOIN logs ON logs.id = samples.log_id;
+";
+
+/// Appends a log to a SQLite database, creating the `logs`, `entries` and `samples` tables (and a
+/// `named_samples` view joining them) if they don't exist yet. Returns the new log's id.
+///
+/// Timestamps are in microseconds. Each sample fills the column matching its entry type:
+/// `value_int` for booleans and int64, `value_real` for float and double, `value_text` for
+/// strings and JSON encoded arrays or decoded structs, and `value_blob` for raw data.
+pub fn export_sqlite(
+    conn: &mut Connection,
+    name: &str,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+) -> rusqlite::Result<i64> {
+    conn.execute_batch(SCHEMA)?;
+
+    let structs = StructRegistry::from_log(log);
+    let entries = metadata
+        .iter()
+        .map(|entry| (entry.entry_id, entry))
+        .collect::<HashMap<_, _>>();
+
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO logs (name, extra_header, start_time, end_time) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            name,
+            log.extra_header,
+            log.records
+                .iter()
+                .map(|record| record.timestamp_us as i64)
+                .min(),
+            log.records
+                .iter()
+                .map(|record| record.timestamp_us as i64)
+                .max(),
+        ],
+    )?;
+    let log_id = tx.last_insert_rowid();
+
+    {
+        let mut insert_entry = tx.prepare(
+            "INSERT INTO entries
+                (log_id, id, name, type, metadata, record_count, start_time, end_time, finished)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+
+        for entry in metadata {
+            insert_entry.execute(params![
+                log_id,
+                entry.entry_id,
+                entry.name,
+                entry.typ,
+                entry.metadata,
+                entry.entry_count as i64,
+                entry.start_time as i64,
+                entry.end_time as i64,
+                entry.finished,
+            ])?;
+        }
+
+        let mut insert_sample = tx.prepare(
+            "INSERT INTO samples
+                (log_id, entry_id, timestamp, value_int, value_real, value_text, value_blob)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+
+        for record in &log.records {
+            let Record::Data(data) = record.data else {
+                continue;
+            };
+            let Some(entry) = entries.get(&record.entry_id) else {
+                continue;
+            };
+
+            let mut value_int = None;
+            let mut value_real = None;
+            let mut value_text = None;
+            let mut value_blob = None;
+
+            if let Some(value) = structs.decode(entry.typ, data) {
+                value_text = Some(serde_json::to_string(&value).unwrap());
+                value_blob = Some(data);
+            } else {
+                match parse_data(entry.typ, data)
+                    .map(|(_, value)| value)
+                    .unwrap_or(DataType::Raw(data))
+                {
+                    DataType::Raw(val) => value_blob = Some(val),
+                    DataType::Boolean(val) => value_int = Some(val as i64),
+                    DataType::Int64(val) => value_int = Some(val),
+                    DataType::Float(val) => value_real = Some(val as f64),
+                    DataType::Double(val) => value_real = Some(val),
+                    DataType::String(val) => value_text = Some(val.to_string()),
+                    value => value_text = Some(serde_json::to_string(&value).unwrap()),
+                }
+            }
+
+            insert_sample.execute(params![
+                log_id,
+                record.entry_id,
+                record.timestamp_us as i64,
+                value_int,
+                value_real,
+                value_text,
+                value_blob,
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(log_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::tests::sample_log,
+        filter::EntryFilter,
+        wpilog::{parser::parse_wpilog, writer::WpiLogWriter},
+    };
+
+    type Row = (
+        String,
+        String,
+        i64,
+        Option<i64>,
+        Option<f64>,
+        Option<String>,
+        Option<Vec<u8>>,
+    );
+
+    fn named_samples(conn: &Connection) -> Vec<Row> {
+        let mut query = conn
+            .prepare(
+                "SELECT log, entry, timestamp, value_int, value_real, value_text, value_blob
+                 FROM named_samples ORDER BY log, timestamp, entry",
+            )
+            .unwrap();
+        query
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn samples_fill_the_column_of_their_type() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        let log_id = export_sqlite(&mut conn, "match 1", &log, &log.get_entry_metadata()).unwrap();
+        assert_eq!(log_id, 1);
+
+        let row = |entry: &str, timestamp, int, real, text: Option<&str>, blob: Option<&[u8]>| {
+            (
+                "match 1".to_string(),
+                entry.to_string(),
+                timestamp,
+                int,
+                real,
+                text.map(str::to_string),
+                blob.map(<[u8]>::to_vec),
+            )
+        };
+        assert_eq!(
+            named_samples(&conn),
+            vec![
+                row(
+                    "/Drive/Modules",
+                    1_000_000,
+                    None,
+                    None,
+                    Some("[\"a\",\"b\"]"),
+                    None
+                ),
+                row("/Drive/Speed", 1_000_000, None, Some(1.5), None, None),
+                row("/Drive/Modules", 1_500_000, None, None, Some("[]"), None),
+                row("/Enabled", 1_500_000, Some(1), None, None, None),
+                row("/Drive/Speed", 2_000_000, None, Some(2.5), None, None),
+                row("/Raw", 2_000_000, None, None, None, Some(&[1, 2])),
+            ]
+        );
+
+        let entry: (String, String, i64, i64, i64, bool) = conn
+            .query_row(
+                "SELECT type, metadata, record_count, start_time, end_time, finished
+                 FROM entries WHERE log_id = 1 AND name = '/Enabled'",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            entry,
+            (
+                "boolean".to_string(),
+                String::new(),
+                1,
+                1_500_000,
+                1_500_000,
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn logs_are_appended() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = EntryFilter::new()
+            .include("/Enabled")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        assert_eq!(
+            export_sqlite(&mut conn, "first", &log, &metadata).unwrap(),
+            1
+        );
+        assert_eq!(
+            export_sqlite(&mut conn, "second", &log, &metadata).unwrap(),
+            2
+        );
+
+        let logs = named_samples(&conn)
+            .into_iter()
+            .map(|(log, entry, ..)| (log, entry))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            logs,
+            [
+                ("first".to_string(), "/Enabled".to_string()),
+                ("second".to_string(), "/Enabled".to_string())
+            ]
+        );
+
+        let range: (i64, i64) = conn
+            .query_row(
+                "SELECT start_time, end_time FROM logs WHERE id = 2",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(range, (0, 2_500_000));
+    }
+
+    #[test]
+    fn structs_are_json_with_their_raw_data() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let schema = log
+            .start("/.schema/struct:Rotation2d", "structschema", "", 0)
+            .unwrap();
+        let rotation = log.start("/Rotation", "struct:Rotation2d", "", 0).unwrap();
+        log.append(schema, 0, b"double value").unwrap();
+        log.append(rotation, 1, &0.5f64.to_le_bytes()).unwrap();
+        let data = log.into_inner().unwrap();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = EntryFilter::new()
+            .include("/Rotation")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        export_sqlite(&mut conn, "structs", &log, &metadata).unwrap();
+
+        let samples = named_samples(&conn);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].5.as_deref(), Some("{\"value\":0.5}"));
+        assert_eq!(samples[0].6.as_deref(), Some(&0.5f64.to_le_bytes()[..]));
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, io};
+
+use crate::{
+    source::{Entry, LogSource},
+    wpilog::types::{MetadataEntry, WpiLog},
+};
+
+use super::{format_timestamp, leaves, CsvError};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayMode {
+    /// Each array is written as a single JSON encoded value
+    #[default]
+    Json,
+    /// Each array element gets its own row, with its position in an `index` column
+    Explode,
+}
+
+/// Writes one `timestamp,entry,type,value` row per data record, for every entry in `metadata`.
+///
+/// Records belonging to entries missing from `metadata` are skipped, so a filtered metadata
+/// list limits the export. See [`export_source_tidy`].
+pub fn export_tidy<W: io::Write>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+    arrays: ArrayMode,
+) -> Result<(), CsvError> {
+    let entries = metadata.iter().map(Entry::from).collect::<Vec<_>>();
+    export_source_tidy(writer, log, &entries, arrays)
+}
+
+/// Writes one `timestamp,entry,type,value` row per sample of `entries`, in timestamp order, with
+/// timestamps in seconds.
+///
+/// Decoded structs and messages get a row per field, named `entry/field` and typed by their
+/// value. Samples without a decoder are a `raw` value. Empty arrays produce no rows when exploded.
+pub fn export_source_tidy<W: io::Write, S: LogSource + ?Sized>(
+    writer: W,
+    source: &S,
+    entries: &[Entry],
+    arrays: ArrayMode,
+) -> Result<(), CsvError> {
+    let mut csvwriter = csv::Writer::from_writer(writer);
+
+    match arrays {
+        ArrayMode::Json => csvwriter.write_record(["timestamp", "entry", "type", "value"])?,
+        ArrayMode::Explode => {
+            csvwriter.write_record(["timestamp", "entry", "type", "index", "value"])?
+        }
+    }
+
+    let entries_by_id = entries
+        .iter()
+        .map(|entry| (entry.id, entry))
+        .collect::<HashMap<_, _>>();
+
+    for sample in source.samples(entries) {
+        let sample = sample?;
+        let timestamp = format_timestamp(sample.timestamp_ns);
+
+        for (name, typ, leaf) in leaves(entries_by_id[&sample.entry_id], &sample.value) {
+            match (arrays, leaf.elements()) {
+                (ArrayMode::Explode, Some(elements)) => {
+                    for (index, element) in elements.iter().enumerate() {
+                        csvwriter.write_record([
+                            timestamp.as_str(),
+                            &name,
+                            &typ,
+                            &index.to_string(),
+                            element,
+                        ])?;
+                    }
+                }
+                (ArrayMode::Explode, None) => {
+                    csvwriter.write_record([
+                        timestamp.as_str(),
+                        &name,
+                        &typ,
+                        "",
+                        &leaf.format(),
+                    ])?;
+                }
+                (ArrayMode::Json, _) => {
+                    csvwriter.write_record([timestamp.as_str(), &name, &typ, &leaf.format()])?;
+                }
+            }
+        }
+    }
+
+    csvwriter.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::{
+            mcap::export_mcap,
+            tests::{sample_log, sample_mcap},
+        },
+        filter::EntryFilter,
+        mcap::{MCap, Parse},
+        wpilog::parser::parse_wpilog,
+    };
+
+    fn export(filter: &EntryFilter, arrays: ArrayMode) -> String {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = filter.filter_entries(&log.get_entry_metadata());
+
+        let mut out = Vec::new();
+        export_tidy(&mut out, &log, &metadata, arrays).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn arrays_are_json_values() {
+        assert_eq!(
+            export(&EntryFilter::new(), ArrayMode::Json),
+            "timestamp,entry,type,value\n\
+             1,/Drive/Speed,double,1.5\n\
+             1,/Drive/Modules,string[],\"[\"\"a\"\",\"\"b\"\"]\"\n\
+             1.5,/Drive/Modules,string[],[]\n\
+             1.5,/Enabled,boolean,1\n\
+             2,/Drive/Speed,double,2.5\n\
+             2,/Raw,raw,\"[1, 2]\"\n"
+        );
+    }
+
+    #[test]
+    fn exploded_arrays_get_a_row_per_element() {
+        let filter = EntryFilter::new().include("/Drive/*").unwrap();
+        assert_eq!(
+            export(&filter, ArrayMode::Explode),
+            "timestamp,entry,type,index,value\n\
+             1,/Drive/Speed,double,,1.5\n\
+             1,/Drive/Modules,string[],0,a\n\
+             1,/Drive/Modules,string[],1,b\n\
+             2,/Drive/Speed,double,,2.5\n"
+        );
+    }
+
+    #[test]
+    fn messages_get_a_row_per_field() {
+        let data = sample_mcap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+        let entries = mcap
+            .filter_entries(&EntryFilter::new().exclude("/quiet").unwrap())
+            .unwrap();
+
+        let mut out = Vec::new();
+        export_source_tidy(&mut out, &mcap, &entries, ArrayMode::Explode).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,entry,type,index,value\n\
+             1.5,/raw,raw,,\"[1, 2]\"\n\
+             2,/pose/ok,boolean,,1\n\
+             2,/pose/tags,string[],0,a\n\
+             2,/pose/wheels/[0]/v,int64,,1\n\
+             2,/pose/wheels/[1]/v,int64,,2\n\
+             2,/pose/x,double,,1.5\n\
+             3,/pose/x,double,,2.5\n"
+        );
+    }
+
+    #[test]
+    fn both_formats_give_the_same_rows() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = EntryFilter::new()
+            .exclude("/Raw")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &metadata).unwrap();
+        let (_, mcap) = MCap::parse(&converted).unwrap();
+
+        let export = |source: &dyn LogSource| {
+            let mut out = Vec::new();
+            let entries = source.entries().unwrap();
+            export_source_tidy(&mut out, source, &entries, ArrayMode::Json).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            export(&mcap),
+            export(&log).replace("2,/Raw,raw,\"[1, 2]\"\n", "")
+        );
+    }
+}
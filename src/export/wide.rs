@@ -0,0 +1,307 @@
+use std::{collections::HashMap, io};
+
+use crate::{
+    source::{Entry, LogSource},
+    wpilog::types::{MetadataEntry, WpiLog},
+};
+
+use super::{format_timestamp, leaves, CsvError, Leaf};
+
+/// Writes one row per data record, with a `timestamp` column in seconds and a column per field
+/// of the entries in `metadata`. Only the fields of the row's entry are filled. See
+/// [`export_source_csv`].
+pub fn export_csv<W: io::Write>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+) -> Result<(), CsvError> {
+    let entries = metadata.iter().map(Entry::from).collect::<Vec<_>>();
+    export_source_csv(writer, log, &entries)
+}
+
+/// A column of the wide export, or the columns of an expanded array.
+struct Column {
+    name: String,
+    samples: usize,
+    /// The length of every value, while all of them are arrays that can be expanded.
+    len: Option<usize>,
+}
+
+impl Column {
+    /// Arrays of one length with more than 16 samples get a column per element.
+    fn is_expanded(&self) -> bool {
+        self.len.is_some() && self.samples > 16
+    }
+
+    fn fields(&self) -> Vec<String> {
+        match self.len {
+            Some(len) if self.is_expanded() => std::iter::once(format!("{}/len", self.name))
+                .chain((0..len).map(|i| format!("{}/[{}]", self.name, i)))
+                .collect(),
+            _ => vec![self.name.clone()],
+        }
+    }
+
+    fn add(&mut self, leaf: &Leaf) {
+        let len = leaf
+            .elements()
+            .filter(|_| leaf.is_expandable())
+            .map(|elements| elements.len());
+        self.len = match self.samples {
+            0 => len,
+            _ => self.len.filter(|&expected| len == Some(expected)),
+        };
+        self.samples += 1;
+    }
+}
+
+/// Writes one row per sample of `entries` in timestamp order, with a `timestamp` column in
+/// seconds and a column per field. Only the fields of the row's entry are filled.
+///
+/// Columns follow the order of `entries`. Decoded structs and messages get a column per field,
+/// named `entry/field` in the order they first appear. Array entries of one length with more
+/// than 16 samples get a `name/len` column and a `name/[i]` column per element, other arrays are
+/// JSON encoded. The samples are read twice, once to find the columns and once to write the rows,
+/// so no rows are held in memory.
+pub fn export_source_csv<W: io::Write, S: LogSource + ?Sized>(
+    writer: W,
+    source: &S,
+    entries: &[Entry],
+) -> Result<(), CsvError> {
+    let entry_indices = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.id, index))
+        .collect::<HashMap<_, _>>();
+    let mut columns = entries.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    // (entry index, name) -> index in the entry's columns
+    let mut column_indices = HashMap::new();
+
+    for sample in source.samples(entries) {
+        let sample = sample?;
+        let index = entry_indices[&sample.entry_id];
+
+        for (name, _, leaf) in leaves(&entries[index], &sample.value) {
+            let columns = &mut columns[index];
+            let column = *column_indices
+                .entry((index, name.clone()))
+                .or_insert_with(|| {
+                    columns.push(Column {
+                        name,
+                        samples: 0,
+                        len: None,
+                    });
+                    columns.len() - 1
+                });
+            columns[column].add(&leaf);
+        }
+    }
+
+    let mut csvwriter = csv::Writer::from_writer(writer);
+    csvwriter.write_field("timestamp")?;
+
+    // (entry index, name) -> the column the fields start at, after the timestamp
+    let mut starts = HashMap::new();
+    let mut start = 1;
+    for (index, (entry, columns)) in entries.iter().zip(&mut columns).enumerate() {
+        if columns.is_empty() {
+            columns.push(Column {
+                name: entry.name.clone(),
+                samples: 0,
+                len: None,
+            });
+        }
+
+        for column in columns.iter() {
+            let fields = column.fields();
+            for field in &fields {
+                csvwriter.write_field(field)?;
+            }
+            starts.insert((index, column.name.clone()), (start, column));
+            start += fields.len();
+        }
+    }
+    csvwriter.write_record(None::<&[u8]>)?;
+    let template_row = vec![String::new(); start];
+
+    for sample in source.samples(entries) {
+        let sample = sample?;
+        let index = entry_indices[&sample.entry_id];
+
+        let mut row = template_row.clone();
+        let mut filled = false;
+        for (name, _, leaf) in leaves(&entries[index], &sample.value) {
+            let (start, column) = starts[&(index, name)];
+            filled = true;
+
+            match leaf.elements() {
+                Some(elements) if column.is_expanded() => {
+                    row[start] = elements.len().to_string();
+                    for (offset, element) in elements.into_iter().enumerate() {
+                        // expanded columns only hold arrays of one length
+                        if offset < column.len.unwrap_or_default() {
+                            row[start + offset + 1] = element;
+                        }
+                    }
+                }
+                _ => row[start] = leaf.format(),
+            }
+        }
+
+        if filled {
+            row[0] = format_timestamp(sample.timestamp_ns);
+            csvwriter.write_record(&row)?;
+        }
+    }
+
+    csvwriter.flush()?;
+
+    Ok(())
+}
+
+/// Writes a `timestamp` header with the entry names, and a row with their types.
+pub fn export_types<W: io::Write>(writer: W, entries: &[Entry]) -> csv::Result<()> {
+    export_entry_row(writer, entries, |entry| &entry.typ)
+}
+
+/// Writes a `timestamp` header with the entry names, and a row with their metadata.
+pub fn export_metadata<W: io::Write>(writer: W, entries: &[Entry]) -> csv::Result<()> {
+    export_entry_row(writer, entries, |entry| &entry.metadata)
+}
+
+fn export_entry_row<W: io::Write>(
+    writer: W,
+    entries: &[Entry],
+    field: impl Fn(&Entry) -> &str,
+) -> csv::Result<()> {
+    let mut csvwriter = csv::Writer::from_writer(writer);
+
+    csvwriter.write_field("timestamp")?;
+    for entry in entries {
+        csvwriter.write_field(&entry.name)?;
+    }
+    csvwriter.write_record(None::<&[u8]>)?;
+
+    csvwriter.write_field("0")?;
+    for entry in entries {
+        csvwriter.write_field(field(entry))?;
+    }
+    csvwriter.write_record(None::<&[u8]>)?;
+
+    csvwriter.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        export::{
+            mcap::export_mcap,
+            tests::{sample_log, sample_mcap},
+        },
+        filter::EntryFilter,
+        mcap::{writer::McapWriter, MCap, Parse},
+        source::SourceError,
+        wpilog::{parser::parse_wpilog, types::DataType, writer::WpiLogWriter},
+    };
+
+    fn export<S: LogSource + ?Sized>(source: &S, filter: &EntryFilter) -> String {
+        let entries = source.filter_entries(filter).unwrap();
+        let mut out = Vec::new();
+        export_source_csv(&mut out, source, &entries).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn control_records_get_no_rows() {
+        let data = sample_log();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let filter = EntryFilter::new().exclude("/Drive/Modules").unwrap();
+        let mut metadata = filter.filter_entries(&log.get_entry_metadata());
+        metadata.sort_by_key(|entry| entry.entry_id);
+
+        // the starts at 0 and the finish of /Enabled at 2.5 s write nothing
+        let mut out = Vec::new();
+        export_csv(&mut out, &log, &metadata).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,/Drive/Speed,/Enabled,/Raw\n\
+             1,1.5,,\n\
+             1.5,,1,\n\
+             2,2.5,,\n\
+             2,,,\"[1, 2]\"\n"
+        );
+    }
+
+    #[test]
+    fn messages_get_a_column_per_field() {
+        let data = sample_mcap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        assert_eq!(
+            export(&mcap, &EntryFilter::new()),
+            "timestamp,/pose/ok,/pose/tags,/pose/wheels/[0]/v,/pose/wheels/[1]/v,/pose/x,/raw,\
+             /quiet\n\
+             1.5,,,,,,\"[1, 2]\",\n\
+             2,1,\"[\"\"a\"\"]\",1,2,1.5,,\n\
+             3,,,,,2.5,,\n"
+        );
+    }
+
+    #[test]
+    fn both_formats_give_the_same_rows() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let speed = log.start("/speed", "double", "", 0).unwrap();
+        let wheels = log.start("/wheels", "double[]", "", 0).unwrap();
+        let names = log.start("/names", "string[]", "", 0).unwrap();
+        for i in 0..17 {
+            let time = i * 1000;
+            log.append_value(speed, time, &DataType::Double(i as f64 / 2.0))
+                .unwrap();
+            log.append_value(wheels, time, &DataType::DoubleArray(vec![1.5, i as f64]))
+                .unwrap();
+            log.append_value(names, time, &DataType::StringArray(vec!["a"]))
+                .unwrap();
+        }
+        let data = log.into_inner().unwrap();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let mut metadata = log.get_entry_metadata();
+        metadata.sort_by_key(|entry| entry.entry_id);
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &metadata).unwrap();
+        let (_, mcap) = MCap::parse(&converted).unwrap();
+
+        let csv = export(&log, &EntryFilter::new());
+        assert_eq!(csv, export(&mcap, &EntryFilter::new()));
+
+        // arrays of one length with more than 16 samples are expanded, string arrays aren't
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("timestamp,/speed,/wheels/len,/wheels/[0],/wheels/[1],/names")
+        );
+        assert_eq!(lines.nth(1), Some("0,,2,1.5,0,"));
+        assert_eq!(lines.next(), Some("0,,,,,\"[\"\"a\"\"]\""));
+    }
+
+    #[test]
+    fn undecodable_messages_are_errors() {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+        let pose = mcap
+            .add_channel(0, "/pose", "json", &HashMap::new())
+            .unwrap();
+        mcap.write_message(pose, 0, 0, 0, b"{").unwrap();
+        let data = mcap.finish().unwrap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        let entries = mcap.entries().unwrap();
+        assert!(matches!(
+            export_source_csv(Vec::new(), &mcap, &entries),
+            Err(CsvError::Source(SourceError::Decode { topic, .. })) if topic == "/pose"
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+
+use crate::wpilog::types::MetadataEntry;
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    // `Drive/*` should only match direct children, `Drive/**` matches everything below
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Selects log entries by name.
+///
+/// Patterns are globs (`Drive/*`, `/RealOutputs/Shooter/**`) unless prefixed with `re:`,
+/// in which case the remainder is a regular expression (`re:^DS:`). An entry is selected
+/// if it matches any include pattern (or no include patterns were given) and no exclude
+/// pattern.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    include: Vec<Matcher>,
+    exclude: Vec<Matcher>,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn parse(pattern: &str) -> Result<Self, FilterError> {
+        match pattern.strip_prefix("re:") {
+            Some(regex) => Ok(Matcher::Regex(Regex::new(regex)?)),
+            None => Ok(Matcher::Glob(Pattern::new(pattern)?)),
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Matcher::Glob(pattern) => pattern.matches_with(name, GLOB_OPTIONS),
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl EntryFilter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn include(mut self, pattern: &str) -> Result<Self, FilterError> {
+        self.include.push(Matcher::parse(pattern)?);
+        Ok(self)
+    }
+
+    pub fn exclude(mut self, pattern: &str) -> Result<Self, FilterError> {
+        self.exclude.push(Matcher::parse(pattern)?);
+        Ok(self)
+    }
+
+    /// Builds a filter from the `--include <pattern>` and `--exclude <pattern>` options in
+    /// `args`, returning it together with the other arguments in their original order.
+    pub fn from_args(args: &[String]) -> Result<(Self, Vec<&String>), FilterError> {
+        let mut filter = EntryFilter::new();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                option @ ("--include" | "--exclude") => {
+                    let pattern = args
+                        .next()
+                        .ok_or_else(|| FilterError::MissingPattern(option.to_string()))?;
+                    filter = match option {
+                        "--include" => filter.include(pattern)?,
+                        _ => filter.exclude(pattern)?,
+                    };
+                }
+                _ => rest.push(arg),
+            }
+        }
+
+        Ok((filter, rest))
+    }
+
+    /// True if no patterns were given, i.e. every entry is selected.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|m| m.matches(name)))
+            && !self.exclude.iter().any(|m| m.matches(name))
+    }
+
+    pub fn filter_entries<'a>(&self, entries: &[MetadataEntry<'a>]) -> Vec<MetadataEntry<'a>> {
+        entries
+            .iter()
+            .filter(|entry| self.matches(entry.name))
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum FilterError {
+    Glob(glob::PatternError),
+    Regex(regex::Error),
+    /// An `--include` or `--exclude` option without a pattern.
+    MissingPattern(String),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::Glob(e) => write!(f, "invalid glob pattern: {}", e),
+            FilterError::Regex(e) => write!(f, "invalid regex pattern: {}", e),
+            FilterError::MissingPattern(option) => write!(f, "{} requires a pattern", option),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl From<glob::PatternError> for FilterError {
+    fn from(value: glob::PatternError) -> Self {
+        FilterError::Glob(value)
+    }
+}
+
+impl From<regex::Error> for FilterError {
+    fn from(value: regex::Error) -> Self {
+        FilterError::Regex(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn globs_only_match_within_a_level() {
+        let filter = EntryFilter::new().include("/Drive/*").unwrap();
+        assert!(filter.matches("/Drive/Speed"));
+        assert!(!filter.matches("/Drive/Left/Speed"));
+        assert!(!filter.matches("/DriveSpeed"));
+
+        let filter = EntryFilter::new().include("/Drive/**").unwrap();
+        assert!(filter.matches("/Drive/Speed"));
+        assert!(filter.matches("/Drive/Left/Speed"));
+
+        let filter = EntryFilter::new().include("/Drive/Speed?").unwrap();
+        assert!(filter.matches("/Drive/Speed1"));
+        assert!(!filter.matches("/Drive/Speed/"));
+    }
+
+    #[test]
+    fn regexes_are_unanchored() {
+        let filter = EntryFilter::new().include("re:Speed$").unwrap();
+        assert!(filter.matches("/Drive/Speed"));
+        assert!(filter.matches("/Drive/Left/Speed"));
+        assert!(!filter.matches("/Drive/SpeedSetpoint"));
+
+        // without the prefix the same text is a glob that has to match the whole name
+        let filter = EntryFilter::new().include("Speed$").unwrap();
+        assert!(!filter.matches("/Drive/Speed"));
+        assert!(filter.matches("Speed$"));
+    }
+
+    #[test]
+    fn excludes_take_precedence_over_includes() {
+        assert!(EntryFilter::new().is_empty());
+        assert!(EntryFilter::new().matches("/anything"));
+
+        let filter = EntryFilter::new()
+            .include("/Drive/**")
+            .unwrap()
+            .include("re:^DS:")
+            .unwrap()
+            .exclude("/Drive/Left/*")
+            .unwrap();
+        assert!(!filter.is_empty());
+        assert!(filter.matches("/Drive/Speed"));
+        assert!(filter.matches("DS:enabled"));
+        assert!(!filter.matches("/Drive/Left/Speed"));
+        assert!(!filter.matches("/Shooter/Speed"));
+
+        let filter = EntryFilter::new().exclude("re:Speed").unwrap();
+        assert!(filter.matches("/Shooter/Angle"));
+        assert!(!filter.matches("/Shooter/Speed"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_errors() {
+        assert!(matches!(
+            EntryFilter::new().include("/Drive/[*"),
+            Err(FilterError::Glob(_))
+        ));
+        assert!(matches!(
+            EntryFilter::new().exclude("re:(Drive"),
+            Err(FilterError::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn filters_are_parsed_from_args() {
+        let args = strings(&[
+            "--include",
+            "/Drive/*",
+            "--tidy",
+            "--exclude",
+            "re:Left",
+            "out",
+        ]);
+        let (filter, rest) = EntryFilter::from_args(&args).unwrap();
+        assert_eq!(rest, vec!["--tidy", "out"]);
+        assert!(filter.matches("/Drive/Speed"));
+        assert!(!filter.matches("/Drive/LeftSpeed"));
+        assert!(!filter.matches("/Shooter/Speed"));
+
+        let args = strings(&["--exclude"]);
+        let missing = EntryFilter::from_args(&args);
+        assert!(
+            matches!(missing, Err(FilterError::MissingPattern(option)) if option == "--exclude")
+        );
+    }
+}
@@ -1,2 +1,7 @@
+pub mod export;
+pub mod filter;
 pub mod mcap;
+pub mod source;
+pub mod statistics;
+pub mod window;
 pub mod wpilog;
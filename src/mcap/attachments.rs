@@ -0,0 +1,363 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{
+    indexed::{IndexError, IndexedMcapReader},
+    messages::MessageError,
+    streaming::{McapEvent, StreamingMcapReader},
+    Attachment, AttachmentIndex, Metadata,
+};
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    Index(IndexError),
+    Scan(MessageError),
+    Io(io::Error),
+}
+
+impl fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttachmentError::Index(err) => write!(f, "{}", err),
+            AttachmentError::Scan(err) => write!(f, "{}", err),
+            AttachmentError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for AttachmentError {}
+
+impl From<IndexError> for AttachmentError {
+    fn from(value: IndexError) -> Self {
+        AttachmentError::Index(value)
+    }
+}
+
+impl From<MessageError> for AttachmentError {
+    fn from(value: MessageError) -> Self {
+        AttachmentError::Scan(value)
+    }
+}
+
+impl From<io::Error> for AttachmentError {
+    fn from(value: io::Error) -> Self {
+        AttachmentError::Io(value)
+    }
+}
+
+/// An attachment without its data, from its index or from the attachment itself.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttachmentInfo {
+    pub name: String,
+    pub media_type: String,
+    pub log_time: u64,
+    pub create_time: u64,
+    pub size: u64,
+}
+
+impl From<&AttachmentIndex<'_>> for AttachmentInfo {
+    fn from(index: &AttachmentIndex) -> Self {
+        AttachmentInfo {
+            name: index.name().to_string(),
+            media_type: index.media_type().to_string(),
+            log_time: index.log_time(),
+            create_time: index.create_time(),
+            size: index.data_size(),
+        }
+    }
+}
+
+impl From<&Attachment<'_>> for AttachmentInfo {
+    fn from(attachment: &Attachment) -> Self {
+        AttachmentInfo {
+            name: attachment.name().to_string(),
+            media_type: attachment.media_type().to_string(),
+            log_time: attachment.log_time(),
+            create_time: attachment.create_time(),
+            size: attachment.data().len() as u64,
+        }
+    }
+}
+
+/// Opens the file through its summary, if it indexes every attachment and metadata record.
+fn open_indexed<R: Read + Seek>(reader: &mut R) -> Option<IndexedMcapReader<&mut R>> {
+    let reader = IndexedMcapReader::new(reader).ok()?;
+    reader.indexes_all_attachments().then_some(reader)
+}
+
+/// Reads every attachment and metadata record by scanning the whole file.
+fn scan<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<(Vec<Attachment<'static>>, Vec<Metadata<'static>>), AttachmentError> {
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut attachments = Vec::new();
+    let mut metadata = Vec::new();
+    for event in StreamingMcapReader::new(reader) {
+        match event? {
+            McapEvent::Attachment(attachment) => attachments.push(attachment),
+            McapEvent::Metadata(record) => metadata.push(record),
+            _ => {}
+        }
+    }
+
+    Ok((attachments, metadata))
+}
+
+/// Lists the attachments of an MCAP file. Files whose summary indexes every attachment and
+/// metadata record are read through the indexes, other files are scanned.
+pub fn list_attachments<R: Read + Seek>(
+    mut reader: R,
+) -> Result<Vec<AttachmentInfo>, AttachmentError> {
+    if let Some(reader) = open_indexed(&mut reader) {
+        return Ok(reader
+            .attachment_indexes()
+            .iter()
+            .map(AttachmentInfo::from)
+            .collect());
+    }
+
+    let (attachments, _) = scan(&mut reader)?;
+    Ok(attachments.iter().map(AttachmentInfo::from).collect())
+}
+
+/// Reads every attachment of an MCAP file, like [`list_attachments`].
+pub fn read_attachments<R: Read + Seek>(
+    mut reader: R,
+) -> Result<Vec<Attachment<'static>>, AttachmentError> {
+    if let Some(mut reader) = open_indexed(&mut reader) {
+        return Ok(reader.read_attachments()?);
+    }
+
+    Ok(scan(&mut reader)?.0)
+}
+
+/// Reads every metadata record of an MCAP file, like [`list_attachments`].
+pub fn read_metadata<R: Read + Seek>(
+    mut reader: R,
+) -> Result<Vec<Metadata<'static>>, AttachmentError> {
+    if let Some(mut reader) = open_indexed(&mut reader) {
+        return Ok(reader.read_all_metadata()?);
+    }
+
+    Ok(scan(&mut reader)?.1)
+}
+
+/// Metadata records as a JSON array of `{"name": ..., "metadata": {...}}` objects, with sorted
+/// keys.
+pub fn metadata_json(metadata: &[Metadata]) -> Value {
+    metadata
+        .iter()
+        .map(|record| {
+            let values = record
+                .metadata()
+                .iter()
+                .map(|(key, value)| (key.as_ref(), value.as_ref()))
+                .collect::<BTreeMap<_, _>>();
+            json!({ "name": record.name(), "metadata": values })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    /// The stored CRC doesn't match the attachment.
+    Crc {
+        expected: u32,
+        computed: u32,
+    },
+    /// The name has no file name, or leads out of the output directory.
+    InvalidName,
+    /// An earlier attachment was already written to this path.
+    Collision(PathBuf),
+    Io(io::Error),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Crc { expected, computed } => write!(
+                f,
+                "crc mismatch, expected {:#010x}, computed {:#010x}",
+                expected, computed
+            ),
+            ExtractError::InvalidName => write!(f, "not a valid file name"),
+            ExtractError::Collision(path) => {
+                write!(
+                    f,
+                    "{} was already extracted from another attachment",
+                    path.display()
+                )
+            }
+            ExtractError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<io::Error> for ExtractError {
+    fn from(value: io::Error) -> Self {
+        ExtractError::Io(value)
+    }
+}
+
+/// Relative path an attachment is extracted to. Directories in the name are kept, leading roots
+/// are dropped and names with `..` are rejected.
+fn relative_path(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => return None,
+        }
+    }
+
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+/// Writes each attachment with a valid CRC below `out_dir`, at the relative path its name gives.
+/// Returns where each attachment was written, in order. Attachments that would overwrite one
+/// written before are not written.
+pub fn extract_attachments(
+    attachments: &[Attachment],
+    out_dir: &Path,
+) -> Vec<Result<PathBuf, ExtractError>> {
+    let mut written = HashSet::new();
+
+    attachments
+        .iter()
+        .map(|attachment| {
+            if !attachment.verify_crc() {
+                return Err(ExtractError::Crc {
+                    expected: attachment.crc(),
+                    computed: attachment.computed_crc(),
+                });
+            }
+
+            let path =
+                out_dir.join(relative_path(attachment.name()).ok_or(ExtractError::InvalidName)?);
+            if written.contains(&path) {
+                return Err(ExtractError::Collision(path));
+            }
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, attachment.data())?;
+            written.insert(path.clone());
+
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Cursor};
+
+    use super::*;
+    use crate::mcap::{writer::McapWriter, Parse, Record, MAGIC};
+
+    fn write_sample() -> Vec<u8> {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+        for name in [
+            "a/cal.json",
+            "b/cal.json",
+            "/abs.txt",
+            "a/cal.json",
+            "../up.txt",
+        ] {
+            mcap.write_attachment(1, 2, name, "text/plain", name.as_bytes())
+                .unwrap();
+        }
+        mcap.write_metadata("run", &HashMap::from([("team", "1234"), ("event", "cmp")]))
+            .unwrap();
+
+        mcap.finish().unwrap()
+    }
+
+    /// Zeroes the footer's summary start, so the file has to be scanned.
+    fn drop_summary(data: &mut [u8]) {
+        // the footer is 29 bytes with its opcode and length, summary start follows those
+        let footer = data.len() - MAGIC.len() - 29 + 9;
+        data[footer..footer + 8].fill(0);
+        assert!(IndexedMcapReader::new(Cursor::new(&*data)).is_err());
+    }
+
+    #[test]
+    fn attachments_are_listed_with_or_without_a_summary() {
+        let mut data = write_sample();
+        let indexed = list_attachments(Cursor::new(&data)).unwrap();
+        drop_summary(&mut data);
+        let scanned = list_attachments(Cursor::new(&data)).unwrap();
+
+        assert_eq!(indexed, scanned);
+        assert_eq!(
+            indexed[0],
+            AttachmentInfo {
+                name: "a/cal.json".to_string(),
+                media_type: "text/plain".to_string(),
+                log_time: 1,
+                create_time: 2,
+                size: 10,
+            }
+        );
+        assert_eq!(indexed.len(), 5);
+    }
+
+    #[test]
+    fn metadata_is_json() {
+        let mut data = write_sample();
+        drop_summary(&mut data);
+
+        for data in [write_sample(), data] {
+            let metadata = read_metadata(Cursor::new(&data)).unwrap();
+            assert_eq!(
+                metadata_json(&metadata),
+                json!([{ "name": "run", "metadata": { "event": "cmp", "team": "1234" } }])
+            );
+        }
+    }
+
+    #[test]
+    fn attachments_are_extracted_to_their_relative_paths() {
+        let dir = std::env::temp_dir().join(format!("mcap-attachments-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut attachments = read_attachments(Cursor::new(write_sample())).unwrap();
+        // a copy of the first one with a broken crc
+        let mut buf = Vec::new();
+        Record::from(attachments[0].clone()).encode(&mut buf);
+        let len = buf.len();
+        buf[len - 1] ^= 1;
+        let (_, Record::Attachment(corrupted)) = Record::parse(&buf).unwrap() else {
+            panic!("not an attachment");
+        };
+        attachments.push(corrupted.into_owned());
+
+        let results = extract_attachments(&attachments, &dir);
+        let cal = dir.join("a").join("cal.json");
+        assert_eq!(results[0].as_ref().unwrap(), &cal);
+        assert_eq!(fs::read(&cal).unwrap(), b"a/cal.json");
+        assert_eq!(
+            fs::read(dir.join("b").join("cal.json")).unwrap(),
+            b"b/cal.json"
+        );
+        assert_eq!(fs::read(dir.join("abs.txt")).unwrap(), b"/abs.txt");
+        assert!(matches!(&results[3], Err(ExtractError::Collision(path)) if *path == cal));
+        assert!(matches!(results[4], Err(ExtractError::InvalidName)));
+        assert!(matches!(results[5], Err(ExtractError::Crc { .. })));
+        assert!(!dir.join("up.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,582 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, io,
+};
+
+use serde_json::Value;
+
+use crate::wpilog::{types::DataType, writer::WpiLogWriter};
+
+use super::{Channel, Chunk, MCap, Parse, Record, Schema};
+
+#[derive(Debug)]
+pub enum ConvertError {
+    Parse(nom::error::ErrorKind),
+    Io(io::Error),
+    /// Two channels or fields map to the same entry name with different types.
+    EntryConflict {
+        name: String,
+        typ: String,
+        other: String,
+    },
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Parse(kind) => write!(f, "invalid MCAP file: {:?}", kind),
+            ConvertError::Io(err) => write!(f, "{}", err),
+            ConvertError::EntryConflict { name, typ, other } => write!(
+                f,
+                "entry {} would have both type {} and type {}",
+                name, typ, other
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<nom::Err<nom::error::Error<&[u8]>>> for ConvertError {
+    fn from(value: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        match value {
+            nom::Err::Incomplete(_) => ConvertError::Parse(nom::error::ErrorKind::Eof),
+            nom::Err::Error(err) | nom::Err::Failure(err) => ConvertError::Parse(err.code),
+        }
+    }
+}
+
+impl From<io::Error> for ConvertError {
+    fn from(value: io::Error) -> Self {
+        ConvertError::Io(value)
+    }
+}
+
+/// Type of a wpilog entry holding a JSON value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsonType {
+    Boolean,
+    Int64,
+    Double,
+    String,
+    BooleanArray,
+    Int64Array,
+    DoubleArray,
+    StringArray,
+    /// An empty array, whose element type is decided by other samples.
+    EmptyArray,
+    Json,
+}
+
+impl JsonType {
+    fn of(value: &Value) -> Option<JsonType> {
+        let typ = match value {
+            Value::Null => return None,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Number(val) if val.is_i64() => JsonType::Int64,
+            Value::Number(_) => JsonType::Double,
+            Value::String(_) => JsonType::String,
+            Value::Array(values) => values
+                .iter()
+                .map(|value| match JsonType::of(value) {
+                    Some(JsonType::Boolean) => JsonType::BooleanArray,
+                    Some(JsonType::Int64) => JsonType::Int64Array,
+                    Some(JsonType::Double) => JsonType::DoubleArray,
+                    Some(JsonType::String) => JsonType::StringArray,
+                    _ => JsonType::Json,
+                })
+                .reduce(JsonType::merge)
+                .unwrap_or(JsonType::EmptyArray),
+            Value::Object(_) => JsonType::Json,
+        };
+
+        Some(typ)
+    }
+
+    /// Common type of samples of two types, integers are widened to doubles.
+    fn merge(self, other: JsonType) -> JsonType {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (
+                JsonType::EmptyArray,
+                typ @ (JsonType::BooleanArray
+                | JsonType::Int64Array
+                | JsonType::DoubleArray
+                | JsonType::StringArray),
+            )
+            | (
+                typ @ (JsonType::BooleanArray
+                | JsonType::Int64Array
+                | JsonType::DoubleArray
+                | JsonType::StringArray),
+                JsonType::EmptyArray,
+            ) => typ,
+            (JsonType::Int64, JsonType::Double) | (JsonType::Double, JsonType::Int64) => {
+                JsonType::Double
+            }
+            (JsonType::Int64Array | JsonType::DoubleArray, JsonType::Int64Array)
+            | (JsonType::Int64Array, JsonType::DoubleArray) => JsonType::DoubleArray,
+            _ => JsonType::Json,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            JsonType::Boolean => "boolean",
+            JsonType::Int64 => "int64",
+            JsonType::Double => "double",
+            JsonType::String => "string",
+            JsonType::BooleanArray => "boolean[]",
+            JsonType::Int64Array => "int64[]",
+            JsonType::DoubleArray | JsonType::EmptyArray => "double[]",
+            JsonType::StringArray => "string[]",
+            JsonType::Json => "json",
+        }
+    }
+
+    fn encode(self, value: &Value) -> Vec<u8> {
+        let value = match (self, value) {
+            (JsonType::Boolean, Value::Bool(val)) => DataType::Boolean(*val),
+            (JsonType::Int64, Value::Number(val)) => DataType::Int64(val.as_i64().unwrap()),
+            (JsonType::Double, Value::Number(val)) => DataType::Double(val.as_f64().unwrap()),
+            (JsonType::String, Value::String(val)) => DataType::String(val),
+            (JsonType::BooleanArray, Value::Array(values)) => {
+                DataType::BooleanArray(values.iter().map(|val| val.as_bool().unwrap()).collect())
+            }
+            (JsonType::Int64Array, Value::Array(values)) => {
+                DataType::Int64Array(values.iter().map(|val| val.as_i64().unwrap()).collect())
+            }
+            (JsonType::DoubleArray | JsonType::EmptyArray, Value::Array(values)) => {
+                DataType::DoubleArray(values.iter().map(|val| val.as_f64().unwrap()).collect())
+            }
+            (JsonType::StringArray, Value::Array(values)) => {
+                DataType::StringArray(values.iter().map(|val| val.as_str().unwrap()).collect())
+            }
+            _ => return value.to_string().into_bytes(),
+        };
+
+        crate::wpilog::writer::encode_data(&value)
+    }
+}
+
+/// Splits a JSON message into `(name, value)` leaves. Objects are flattened into `/` separated
+/// names below the topic, and a message that is just `{"value": ...}` maps to the topic itself.
+fn flatten<'v>(name: String, value: &'v Value, leaves: &mut Vec<(String, &'v Value)>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                flatten(
+                    format!("{}/{}", name.trim_end_matches('/'), key),
+                    value,
+                    leaves,
+                );
+            }
+        }
+        value => leaves.push((name, value)),
+    }
+}
+
+fn flatten_message<'v>(topic: &str, message: &'v Value) -> Vec<(String, &'v Value)> {
+    let mut leaves = Vec::new();
+
+    match message.as_object() {
+        Some(fields) if fields.len() == 1 && fields.contains_key("value") => {
+            flatten(topic.to_string(), &fields["value"], &mut leaves)
+        }
+        _ => flatten(topic.to_string(), message, &mut leaves),
+    }
+
+    leaves
+}
+
+/// Converts an MCAP file to a wpilog, with an entry per channel named after its topic and
+/// timestamps from the messages' log time.
+///
+/// Messages of `json` channels are decoded and each field becomes a typed entry named
+/// `<topic>/<field>`, or `<topic>` for `{"value": ...}` messages. Fields whose type varies between
+/// messages fall back to `json` entries. Messages in other encodings are stored as raw data
+/// in an entry whose type is the schema name. Entries that would share a name but not a type are
+/// an error. Chunks may be uncompressed or compressed with zstd or lz4.
+pub fn convert_to_wpilog<W: io::Write>(input: &[u8], writer: W) -> Result<W, ConvertError> {
+    let (_, mcap) = MCap::parse(input)?;
+
+    let mut schemas: HashMap<u16, Schema> = HashMap::new();
+    let mut channels: BTreeMap<u16, Channel> = BTreeMap::new();
+    let mut messages = Vec::new();
+
+    // decompress every chunk up front so the records parsed from them can borrow the buffers
+    let mut chunks = Vec::new();
+    for raw in &mcap.records {
+        if let Record::Chunk(chunk) = Record::try_from(*raw)? {
+            chunks.push(chunk);
+        }
+    }
+    let chunks = chunks
+        .iter()
+        .map(|chunk| chunk.decompress())
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut records = mcap.records;
+    for chunk in &chunks {
+        let (_, chunk_records) = Chunk::parse_records(chunk)?;
+        records.extend(chunk_records);
+    }
+
+    for raw in records {
+        match Record::try_from(raw)? {
+            Record::Schema(schema) => {
+                schemas.insert(schema.id(), schema);
+            }
+            Record::Channel(channel) => {
+                channels.insert(channel.id(), channel);
+            }
+            Record::Message(message) => messages.push(message),
+            _ => {}
+        }
+    }
+
+    // summary sections repeat schemas and channels, so messages are matched up afterwards
+    messages.sort_by_key(|message| message.log_time());
+
+    // decode json messages first so every field's type is known before its entry starts
+    let mut decoded = Vec::with_capacity(messages.len());
+    let mut json_types: HashMap<String, JsonType> = HashMap::new();
+    for message in &messages {
+        let Some(channel) = channels.get(&message.channel_id()) else {
+            decoded.push(None);
+            continue;
+        };
+
+        let value = if channel.message_encoding() == "json" {
+            serde_json::from_slice::<Value>(message.data()).ok()
+        } else {
+            None
+        };
+
+        if let Some(value) = &value {
+            for (name, leaf) in flatten_message(channel.topic(), value) {
+                if let Some(typ) = JsonType::of(leaf) {
+                    json_types
+                        .entry(name)
+                        .and_modify(|existing| *existing = existing.merge(typ))
+                        .or_insert(typ);
+                }
+            }
+        }
+
+        decoded.push(Some((channel, value)));
+    }
+
+    let mut wpilog = WpiLogWriter::new(writer, "")?;
+    let mut entries = HashMap::new();
+
+    for (message, decoded) in messages.iter().zip(&decoded) {
+        let Some((channel, value)) = decoded else {
+            continue;
+        };
+        let timestamp_us = message.log_time() / 1000;
+        let metadata = || channel_metadata(channel, schemas.get(&channel.schema_id()));
+
+        match value {
+            Some(value) => {
+                for (name, leaf) in flatten_message(channel.topic(), value) {
+                    let Some(typ) = json_types.get(&name) else {
+                        continue;
+                    };
+                    if leaf.is_null() {
+                        continue;
+                    }
+
+                    let entry_id = start_entry(
+                        &mut wpilog,
+                        &mut entries,
+                        &name,
+                        typ.name(),
+                        metadata,
+                        timestamp_us,
+                    )?;
+                    wpilog.append(entry_id, timestamp_us, &typ.encode(leaf))?;
+                }
+            }
+            None => {
+                let typ = schemas
+                    .get(&channel.schema_id())
+                    .map(|schema| schema.name())
+                    .unwrap_or("raw");
+                let entry_id = start_entry(
+                    &mut wpilog,
+                    &mut entries,
+                    channel.topic(),
+                    typ,
+                    metadata,
+                    timestamp_us,
+                )?;
+                wpilog.append(entry_id, timestamp_us, message.data())?;
+            }
+        }
+    }
+
+    Ok(wpilog.into_inner()?)
+}
+
+/// Returns the id of the entry named `name`, starting it if needed. Fields and raw channels can
+/// map to the same name, which is only allowed if they agree on the type.
+fn start_entry<W: io::Write>(
+    wpilog: &mut WpiLogWriter<W>,
+    entries: &mut HashMap<String, (u32, String)>,
+    name: &str,
+    typ: &str,
+    metadata: impl FnOnce() -> String,
+    timestamp_us: u64,
+) -> Result<u32, ConvertError> {
+    match entries.get(name) {
+        Some((entry_id, existing)) if existing == typ => Ok(*entry_id),
+        Some((_, existing)) => Err(ConvertError::EntryConflict {
+            name: name.to_string(),
+            typ: existing.clone(),
+            other: typ.to_string(),
+        }),
+        None => {
+            let entry_id = wpilog.start(name, typ, &metadata(), timestamp_us)?;
+            entries.insert(name.to_string(), (entry_id, typ.to_string()));
+            Ok(entry_id)
+        }
+    }
+}
+
+/// Entry metadata holding the channel's metadata and encodings as a JSON object.
+pub(crate) fn channel_metadata(channel: &Channel, schema: Option<&Schema>) -> String {
+    let mut metadata = channel
+        .metadata()
+        .iter()
+        .map(|(key, value)| (key.to_string(), Value::from(&**value)))
+        .collect::<serde_json::Map<_, _>>();
+
+    metadata.insert(
+        "message_encoding".to_string(),
+        Value::from(channel.message_encoding()),
+    );
+    if let Some(schema) = schema {
+        metadata.insert(
+            "schema_encoding".to_string(),
+            Value::from(schema.encoding()),
+        );
+    }
+
+    Value::Object(metadata).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        export::mcap::export_mcap,
+        mcap::writer::McapWriter,
+        wpilog::{
+            parser::{parse_data, parse_wpilog},
+            types::Record as WpiRecord,
+        },
+    };
+
+    type Entries = BTreeMap<String, (String, Vec<(u64, Value)>)>;
+
+    /// Reads back every entry of a wpilog with its type and samples.
+    fn read_entries(data: &[u8]) -> Entries {
+        let (_, log) = parse_wpilog(data).unwrap();
+        let metadata = log.get_entry_metadata();
+
+        let mut entries = Entries::new();
+        for record in &log.records {
+            let WpiRecord::Data(data) = record.data else {
+                continue;
+            };
+            let entry = metadata
+                .iter()
+                .find(|entry| entry.entry_id == record.entry_id)
+                .unwrap();
+            let (_, value) = parse_data(entry.typ, data).unwrap();
+            entries
+                .entry(entry.name.to_string())
+                .or_insert_with(|| (entry.typ.to_string(), Vec::new()))
+                .1
+                .push((record.timestamp_us, serde_json::to_value(value).unwrap()));
+        }
+
+        entries
+    }
+
+    /// Exports a wpilog to MCAP and converts it back.
+    fn round_trip(data: &[u8]) -> Entries {
+        let (_, log) = parse_wpilog(data).unwrap();
+        let mut mcap = Vec::new();
+        export_mcap(&mut mcap, &log, &log.get_entry_metadata()).unwrap();
+
+        read_entries(&convert_to_wpilog(&mcap, Vec::new()).unwrap())
+    }
+
+    fn entry(typ: &str, samples: &[(u64, Value)]) -> (String, Vec<(u64, Value)>) {
+        (typ.to_string(), samples.to_vec())
+    }
+
+    #[test]
+    fn data_types_round_trip() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let values = [
+            ("boolean", DataType::Boolean(true), DataType::Boolean(false)),
+            ("int64", DataType::Int64(-3), DataType::Int64(1 << 60)),
+            ("float", DataType::Float(0.5), DataType::Float(-2.25)),
+            ("double", DataType::Double(0.1), DataType::Double(2.0)),
+            ("string", DataType::String("a\"b"), DataType::String("")),
+            (
+                "boolean[]",
+                DataType::BooleanArray(vec![true, false]),
+                DataType::BooleanArray(vec![]),
+            ),
+            (
+                "int64[]",
+                DataType::Int64Array(vec![1, -2]),
+                DataType::Int64Array(vec![3]),
+            ),
+            (
+                "float[]",
+                DataType::FloatArray(vec![0.5]),
+                DataType::FloatArray(vec![1.5, 2.5]),
+            ),
+            (
+                "double[]",
+                DataType::DoubleArray(vec![0.1, 0.2]),
+                DataType::DoubleArray(vec![]),
+            ),
+            (
+                "string[]",
+                DataType::StringArray(vec!["a", "b"]),
+                DataType::StringArray(vec![""]),
+            ),
+        ];
+        for (typ, first, second) in &values {
+            let id = log.start(&format!("/{}", typ), typ, "", 0).unwrap();
+            log.append_value(id, 1, first).unwrap();
+            log.append_value(id, 2, second).unwrap();
+        }
+        let raw = log.start("/raw", "raw", "", 0).unwrap();
+        log.append(raw, 3, &[0, 255]).unwrap();
+
+        let entries = round_trip(&log.into_inner().unwrap());
+
+        for (typ, first, second) in &values {
+            // floats are widened to doubles by the JSON encoding
+            let expected = typ.replace("float", "double");
+            let samples = [
+                (1, serde_json::to_value(first).unwrap()),
+                (2, serde_json::to_value(second).unwrap()),
+            ];
+            assert_eq!(
+                entries[&format!("/{}", typ)],
+                entry(&expected, &samples),
+                "{}",
+                typ
+            );
+        }
+        // raw data is written as an array of bytes
+        assert_eq!(entries["/raw"], entry("int64[]", &[(3, json!([0, 255]))]));
+    }
+
+    #[test]
+    fn structs_and_json_become_fields() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let schema = log
+            .start("/.schema/struct:Module", "structschema", "", 0)
+            .unwrap();
+        let module = log.start("/Module", "struct:Module", "", 0).unwrap();
+        let json = log.start("/Json", "json", "", 0).unwrap();
+
+        log.append(schema, 1, b"char name[2];double angle[2];bool ok")
+            .unwrap();
+        let mut value = b"fl".to_vec();
+        value.extend(0.5f64.to_le_bytes());
+        value.extend(1.5f64.to_le_bytes());
+        value.push(1);
+        log.append(module, 2, &value).unwrap();
+        log.append_value(json, 3, &DataType::String(r#"{"a":1,"b":{"c":"x"}}"#))
+            .unwrap();
+        log.append_value(json, 4, &DataType::String(r#"{"a":1.5,"b":{"c":[1]}}"#))
+            .unwrap();
+
+        let entries = round_trip(&log.into_inner().unwrap());
+
+        assert_eq!(
+            entries.keys().collect::<Vec<_>>(),
+            [
+                "/.schema/struct:Module",
+                "/Json/a",
+                "/Json/b/c",
+                "/Module/angle",
+                "/Module/name",
+                "/Module/ok",
+            ]
+        );
+        assert_eq!(
+            entries["/Module/name"],
+            entry("string", &[(2, json!("fl"))])
+        );
+        assert_eq!(
+            entries["/Module/angle"],
+            entry("double[]", &[(2, json!([0.5, 1.5]))])
+        );
+        assert_eq!(entries["/Module/ok"], entry("boolean", &[(2, json!(true))]));
+        // fields whose type changes are widened, or fall back to JSON
+        assert_eq!(
+            entries["/Json/a"],
+            entry("double", &[(3, json!(1.0)), (4, json!(1.5))])
+        );
+        assert_eq!(
+            entries["/Json/b/c"],
+            entry("json", &[(3, json!("\"x\"")), (4, json!("[1]"))])
+        );
+    }
+
+    #[test]
+    fn entries_keep_the_channel_metadata() {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+        let schema = mcap.add_schema("Pose", "protobuf", b"").unwrap();
+        let channel = mcap
+            .add_channel(schema, "/pose", "protobuf", &HashMap::from([("a", "b")]))
+            .unwrap();
+        mcap.write_message(channel, 0, 5000, 0, &[1, 2]).unwrap();
+        let data = convert_to_wpilog(&mcap.finish().unwrap(), Vec::new()).unwrap();
+
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = log.get_entry_metadata();
+        assert_eq!(metadata[0].name, "/pose");
+        assert_eq!(metadata[0].typ, "Pose");
+        assert_eq!(
+            serde_json::from_str::<Value>(metadata[0].metadata).unwrap(),
+            json!({ "a": "b", "message_encoding": "protobuf", "schema_encoding": "protobuf" })
+        );
+        assert_eq!(
+            read_entries(&data)["/pose"],
+            entry("Pose", &[(5, json!([1, 2]))])
+        );
+    }
+
+    #[test]
+    fn conflicting_entries_are_errors() {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+        let json = mcap.add_channel(0, "/a", "json", &HashMap::new()).unwrap();
+        let raw = mcap
+            .add_channel(0, "/a/b", "cbor", &HashMap::new())
+            .unwrap();
+        mcap.write_message(json, 0, 1000, 0, br#"{"b":1}"#).unwrap();
+        mcap.write_message(raw, 0, 2000, 0, &[1]).unwrap();
+        let data = mcap.finish().unwrap();
+
+        match convert_to_wpilog(&data, Vec::new()) {
+            Err(ConvertError::EntryConflict { name, typ, other }) => {
+                assert_eq!((&*name, &*typ, &*other), ("/a/b", "int64", "raw"));
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use serde_json::Value;
+
+use crate::wpilog::structs::StructValue;
+
+use super::{messages::MessageEvent, Channel, Schema};
+
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod ros2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// No decoder is registered for the channel's message encoding and schema encoding.
+    UnsupportedEncoding {
+        message_encoding: String,
+        schema_encoding: String,
+    },
+    /// The schema couldn't be parsed, or doesn't describe the message type.
+    Schema(String),
+    /// The message doesn't match its schema.
+    Message(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedEncoding {
+                message_encoding,
+                schema_encoding,
+            } => write!(
+                f,
+                "no decoder for message encoding {:?} with schema encoding {:?}",
+                message_encoding, schema_encoding
+            ),
+            DecodeError::Schema(err) => write!(f, "invalid schema: {}", err),
+            DecodeError::Message(err) => write!(f, "invalid message: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes the messages of the channels sharing one schema.
+pub trait MessageDecoder: Send + Sync {
+    fn decode(&self, data: &[u8]) -> Result<StructValue, DecodeError>;
+}
+
+/// Builds a decoder from a channel's schema, or from `None` for schemaless channels.
+pub type DecoderFactory =
+    Box<dyn Fn(Option<&Schema>) -> Result<Arc<dyn MessageDecoder>, DecodeError> + Send + Sync>;
+
+/// Decoders for MCAP messages, keyed by the channel's message encoding and the encoding of its
+/// schema (empty for channels without one).
+///
+/// The built-in decoders handle `json` messages with or without a `jsonschema`, `protobuf`
+/// messages with a `protobuf` schema holding a `FileDescriptorSet` (with the `protobuf` feature),
+/// and `cdr` messages with a `ros2msg` schema. Decoders are built once per schema, and rebuilt if
+/// a schema is redefined with the same id.
+pub struct DecoderRegistry {
+    factories: HashMap<(String, String), DecoderFactory>,
+    // keyed by message encoding and schema id
+    decoders: HashMap<(String, u16), BuiltDecoder>,
+}
+
+/// A decoder, or the error building it, with the schema it was built from.
+struct BuiltDecoder {
+    schema: Option<Schema<'static>>,
+    decoder: Result<Arc<dyn MessageDecoder>, DecodeError>,
+}
+
+impl Default for DecoderRegistry {
+    fn default() -> Self {
+        let mut registry = DecoderRegistry::empty();
+
+        registry.register("json", "", |_| Ok(Arc::new(JsonDecoder)));
+        registry.register("json", "jsonschema", |_| Ok(Arc::new(JsonDecoder)));
+        #[cfg(feature = "protobuf")]
+        registry.register("protobuf", "protobuf", |schema| {
+            Ok(Arc::new(protobuf::ProtobufDecoder::new(required(schema)?)?))
+        });
+        registry.register("cdr", "ros2msg", |schema| {
+            Ok(Arc::new(ros2::CdrDecoder::new(required(schema)?)?))
+        });
+
+        registry
+    }
+}
+
+impl DecoderRegistry {
+    /// A registry without any decoders.
+    pub fn empty() -> Self {
+        DecoderRegistry {
+            factories: HashMap::new(),
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Registers a decoder factory, replacing any for the same encodings.
+    pub fn register(
+        &mut self,
+        message_encoding: &str,
+        schema_encoding: &str,
+        factory: impl Fn(Option<&Schema>) -> Result<Arc<dyn MessageDecoder>, DecodeError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.factories.insert(
+            (message_encoding.to_string(), schema_encoding.to_string()),
+            Box::new(factory),
+        );
+        self.decoders.clear();
+    }
+
+    /// Returns the decoder for messages on `channel`, building it on first use. Errors building
+    /// it are returned again for later messages.
+    pub fn decoder(
+        &mut self,
+        channel: &Channel,
+        schema: Option<&Schema>,
+    ) -> Result<Arc<dyn MessageDecoder>, DecodeError> {
+        let key = (
+            channel.message_encoding().to_string(),
+            schema.map(|schema| schema.id()).unwrap_or(0),
+        );
+        if let Some(built) = self.decoders.get(&key) {
+            if built.schema.as_ref() == schema {
+                return built.decoder.clone();
+            }
+        }
+
+        let decoder = self.build(channel, schema);
+        self.decoders.insert(
+            key,
+            BuiltDecoder {
+                schema: schema.cloned().map(Schema::into_owned),
+                decoder: decoder.clone(),
+            },
+        );
+
+        decoder
+    }
+
+    fn build(
+        &self,
+        channel: &Channel,
+        schema: Option<&Schema>,
+    ) -> Result<Arc<dyn MessageDecoder>, DecodeError> {
+        let schema_encoding = schema.map(|schema| schema.encoding()).unwrap_or("");
+        let factory = self
+            .factories
+            .get(&(
+                channel.message_encoding().to_string(),
+                schema_encoding.to_string(),
+            ))
+            .ok_or_else(|| DecodeError::UnsupportedEncoding {
+                message_encoding: channel.message_encoding().to_string(),
+                schema_encoding: schema_encoding.to_string(),
+            })?;
+
+        factory(schema)
+    }
+
+    pub fn decode(&mut self, message: &MessageEvent) -> Result<StructValue, DecodeError> {
+        self.decoder(&message.channel, message.schema.as_deref())?
+            .decode(message.message.data())
+    }
+}
+
+fn required<'a, 'b>(schema: Option<&'a Schema<'b>>) -> Result<&'a Schema<'b>, DecodeError> {
+    schema.ok_or_else(|| DecodeError::Schema("channel has no schema".to_string()))
+}
+
+/// Decodes JSON messages, leaving out null members. Nulls in arrays become empty structs, so the
+/// elements after them keep their index. The schema, if any, isn't checked.
+pub struct JsonDecoder;
+
+impl MessageDecoder for JsonDecoder {
+    fn decode(&self, data: &[u8]) -> Result<StructValue, DecodeError> {
+        let value = serde_json::from_slice::<Value>(data)
+            .map_err(|err| DecodeError::Message(err.to_string()))?;
+
+        Ok(json_value(value).unwrap_or(StructValue::Struct(Vec::new())))
+    }
+}
+
+fn json_value(value: Value) -> Option<StructValue> {
+    let value = match value {
+        Value::Null => return None,
+        Value::Bool(val) => StructValue::Bool(val),
+        Value::Number(val) => match (val.as_i64(), val.as_u64()) {
+            (Some(val), _) => StructValue::Int(val),
+            (None, Some(val)) => StructValue::UInt(val),
+            _ => StructValue::Double(val.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(val) => StructValue::String(val),
+        Value::Array(values) => StructValue::Array(
+            values
+                .into_iter()
+                .map(|value| json_value(value).unwrap_or(StructValue::Struct(Vec::new())))
+                .collect(),
+        ),
+        Value::Object(members) => StructValue::Struct(
+            members
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, json_value(value)?)))
+                .collect(),
+        ),
+    };
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::mcap::Message;
+
+    fn event(
+        message_encoding: &str,
+        schema: Option<Schema<'static>>,
+        data: &[u8],
+    ) -> MessageEvent<'static> {
+        MessageEvent {
+            message: Message {
+                channel_id: 1,
+                sequence: 0,
+                log_time: 0,
+                publish_time: 0,
+                data: Cow::Owned(data.to_vec()),
+            },
+            channel: Arc::new(Channel {
+                id: 1,
+                schema_id: schema.as_ref().map(|schema| schema.id).unwrap_or(0),
+                topic: "/a".into(),
+                message_encoding: message_encoding.to_string().into(),
+                metadata: HashMap::new(),
+            }),
+            schema: schema.map(Arc::new),
+        }
+    }
+
+    #[test]
+    fn json_messages_are_decoded() {
+        let mut registry = DecoderRegistry::default();
+
+        let value = registry
+            .decode(&event(
+                "json",
+                None,
+                br#"{"a": 1, "b": [true, null, 2], "c": {"d": 1.5, "e": null}}"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            value,
+            StructValue::Struct(vec![
+                ("a".to_string(), StructValue::Int(1)),
+                (
+                    "b".to_string(),
+                    StructValue::Array(vec![
+                        StructValue::Bool(true),
+                        StructValue::Struct(Vec::new()),
+                        StructValue::Int(2),
+                    ])
+                ),
+                (
+                    "c".to_string(),
+                    StructValue::Struct(vec![("d".to_string(), StructValue::Double(1.5))])
+                ),
+            ])
+        );
+
+        assert!(matches!(
+            registry.decode(&event("json", None, b"{")),
+            Err(DecodeError::Message(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_encodings_are_errors() {
+        let mut registry = DecoderRegistry::default();
+        assert_eq!(
+            registry.decode(&event("cbor", None, b"")),
+            Err(DecodeError::UnsupportedEncoding {
+                message_encoding: "cbor".to_string(),
+                schema_encoding: "".to_string(),
+            })
+        );
+        assert!(matches!(
+            registry.decode(&event("cdr", None, b"")),
+            Err(DecodeError::UnsupportedEncoding { .. })
+        ));
+    }
+
+    #[test]
+    fn custom_decoders_are_used() {
+        struct Length;
+
+        impl MessageDecoder for Length {
+            fn decode(&self, data: &[u8]) -> Result<StructValue, DecodeError> {
+                Ok(StructValue::UInt(data.len() as u64))
+            }
+        }
+
+        let mut registry = DecoderRegistry::empty();
+        registry.register("raw", "", |_| Ok(Arc::new(Length)));
+        assert_eq!(
+            registry.decode(&event("raw", None, b"abc")),
+            Ok(StructValue::UInt(3))
+        );
+        assert!(registry.decode(&event("json", None, b"{}")).is_err());
+    }
+
+    #[test]
+    fn decoders_are_rebuilt_for_redefined_schemas() {
+        struct SchemaName(String);
+
+        impl MessageDecoder for SchemaName {
+            fn decode(&self, _: &[u8]) -> Result<StructValue, DecodeError> {
+                Ok(StructValue::String(self.0.clone()))
+            }
+        }
+
+        let mut registry = DecoderRegistry::empty();
+        registry.register("raw", "raw", |schema| {
+            Ok(Arc::new(SchemaName(required(schema)?.name().to_string())))
+        });
+
+        let schema = |name: &str| Schema {
+            id: 1,
+            name: name.to_string().into(),
+            encoding: "raw".into(),
+            data: Cow::Borrowed(b""),
+        };
+        for name in ["a", "a", "b"] {
+            assert_eq!(
+                registry.decode(&event("raw", Some(schema(name)), b"")),
+                Ok(StructValue::String(name.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn factory_errors_are_cached() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut registry = DecoderRegistry::empty();
+        registry.register("raw", "", {
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Err(DecodeError::Schema("broken".to_string()))
+            }
+        });
+
+        for _ in 0..3 {
+            assert_eq!(
+                registry.decode(&event("raw", None, b"")),
+                Err(DecodeError::Schema("broken".to_string()))
+            );
+        }
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,333 @@
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor,
+    ReflectMessage, Value,
+};
+
+use crate::{mcap::Schema, wpilog::structs::StructValue};
+
+use super::{DecodeError, MessageDecoder};
+
+/// Decodes protobuf messages with a `protobuf` schema, whose data is a serialized
+/// `FileDescriptorSet` holding the message type named by the schema.
+///
+/// Every field is decoded, with its default value if it wasn't set, except unset fields with
+/// presence, like members of a `oneof`. Enums are decoded to the name of their value, bytes to
+/// arrays and maps to structs keyed by the map key.
+pub struct ProtobufDecoder {
+    descriptor: MessageDescriptor,
+}
+
+impl ProtobufDecoder {
+    pub fn new(schema: &Schema) -> Result<Self, DecodeError> {
+        let pool = DescriptorPool::decode(schema.data())
+            .map_err(|err| DecodeError::Schema(err.to_string()))?;
+        let descriptor = pool.get_message_by_name(schema.name()).ok_or_else(|| {
+            DecodeError::Schema(format!("{} isn't in the descriptor set", schema.name()))
+        })?;
+
+        Ok(ProtobufDecoder { descriptor })
+    }
+}
+
+impl MessageDecoder for ProtobufDecoder {
+    fn decode(&self, data: &[u8]) -> Result<StructValue, DecodeError> {
+        let message = DynamicMessage::decode(self.descriptor.clone(), data)
+            .map_err(|err| DecodeError::Message(err.to_string()))?;
+
+        Ok(message_value(&message))
+    }
+}
+
+fn message_value(message: &DynamicMessage) -> StructValue {
+    let members = message
+        .descriptor()
+        .fields()
+        .filter(|field| !field.supports_presence() || message.has_field(field))
+        .map(|field| {
+            let value = field_value(&field, &message.get_field(&field));
+            (field.name().to_string(), value)
+        })
+        .collect();
+
+    StructValue::Struct(members)
+}
+
+fn field_value(field: &FieldDescriptor, value: &Value) -> StructValue {
+    match value {
+        Value::List(values) => StructValue::Array(
+            values
+                .iter()
+                .map(|value| value_of_kind(&field.kind(), value))
+                .collect(),
+        ),
+        Value::Map(entries) => {
+            let value_kind = match field.kind() {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                kind => kind,
+            };
+
+            let mut members = entries
+                .iter()
+                .map(|(key, value)| (map_key(key), value_of_kind(&value_kind, value)))
+                .collect::<Vec<_>>();
+            members.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            StructValue::Struct(members)
+        }
+        value => value_of_kind(&field.kind(), value),
+    }
+}
+
+fn value_of_kind(kind: &Kind, value: &Value) -> StructValue {
+    match value {
+        Value::Bool(val) => StructValue::Bool(*val),
+        Value::I32(val) => StructValue::Int(*val as i64),
+        Value::I64(val) => StructValue::Int(*val),
+        Value::U32(val) => StructValue::UInt(*val as u64),
+        Value::U64(val) => StructValue::UInt(*val),
+        Value::F32(val) => StructValue::Float(*val),
+        Value::F64(val) => StructValue::Double(*val),
+        Value::String(val) => StructValue::String(val.clone()),
+        Value::Bytes(val) => StructValue::Array(
+            val.iter()
+                .map(|byte| StructValue::UInt(*byte as u64))
+                .collect(),
+        ),
+        Value::EnumNumber(number) => {
+            let name = match kind {
+                Kind::Enum(descriptor) => descriptor.get_value(*number),
+                _ => None,
+            };
+            match name {
+                Some(name) => StructValue::String(name.name().to_string()),
+                None => StructValue::Int(*number as i64),
+            }
+        }
+        Value::Message(message) => message_value(message),
+        // only fields hold lists and maps
+        Value::List(values) => StructValue::Array(
+            values
+                .iter()
+                .map(|value| value_of_kind(kind, value))
+                .collect(),
+        ),
+        Value::Map(_) => StructValue::Struct(Vec::new()),
+    }
+}
+
+fn map_key(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(val) => val.to_string(),
+        MapKey::I32(val) => val.to_string(),
+        MapKey::I64(val) => val.to_string(),
+        MapKey::U32(val) => val.to_string(),
+        MapKey::U64(val) => val.to_string(),
+        MapKey::String(val) => val.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_reflect::{
+        prost::Message,
+        prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+            FileDescriptorProto, FileDescriptorSet, MessageOptions, OneofDescriptorProto,
+        },
+    };
+
+    use super::*;
+
+    fn field(name: &str, number: i32, typ: Type, label: Label) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(typ as i32),
+            label: Some(label as i32),
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn message_field(
+        name: &str,
+        number: i32,
+        type_name: &str,
+        label: Label,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            type_name: Some(type_name.to_string()),
+            ..field(name, number, Type::Message, label)
+        }
+    }
+
+    /// `test.Pose` with a nested point, a repeated double, an enum, a map, bytes and a oneof.
+    fn descriptor_set() -> Vec<u8> {
+        let point = DescriptorProto {
+            name: Some("Point".to_string()),
+            field: vec![
+                field("x", 1, Type::Double, Label::Optional),
+                field("y", 2, Type::Double, Label::Optional),
+            ],
+            ..Default::default()
+        };
+        let tags_entry = DescriptorProto {
+            name: Some("TagsEntry".to_string()),
+            field: vec![
+                field("key", 1, Type::String, Label::Optional),
+                field("value", 2, Type::Int32, Label::Optional),
+            ],
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mode = EnumDescriptorProto {
+            name: Some("Mode".to_string()),
+            value: ["IDLE", "AUTO"]
+                .iter()
+                .enumerate()
+                .map(|(number, name)| EnumValueDescriptorProto {
+                    name: Some(name.to_string()),
+                    number: Some(number as i32),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut text = field("text", 7, Type::String, Label::Optional);
+        text.oneof_index = Some(0);
+        let mut number = field("number", 8, Type::Int64, Label::Optional);
+        number.oneof_index = Some(0);
+
+        let pose = DescriptorProto {
+            name: Some("Pose".to_string()),
+            field: vec![
+                message_field("position", 1, ".test.Point", Label::Optional),
+                field("weights", 2, Type::Double, Label::Repeated),
+                FieldDescriptorProto {
+                    type_name: Some(".test.Mode".to_string()),
+                    ..field("mode", 3, Type::Enum, Label::Optional)
+                },
+                message_field("tags", 4, ".test.Pose.TagsEntry", Label::Repeated),
+                field("raw", 5, Type::Bytes, Label::Optional),
+                field("count", 6, Type::Uint32, Label::Optional),
+                text,
+                number,
+            ],
+            nested_type: vec![tags_entry],
+            oneof_decl: vec![OneofDescriptorProto {
+                name: Some("value".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("test.proto".to_string()),
+                package: Some("test".to_string()),
+                message_type: vec![point, pose],
+                enum_type: vec![mode],
+                syntax: Some("proto3".to_string()),
+                ..Default::default()
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    fn schema(name: &str) -> Schema<'static> {
+        Schema {
+            id: 1,
+            name: name.to_string().into(),
+            encoding: "protobuf".into(),
+            data: descriptor_set().into(),
+        }
+    }
+
+    #[test]
+    fn messages_are_decoded() {
+        let decoder = ProtobufDecoder::new(&schema("test.Pose")).unwrap();
+
+        let mut point = DynamicMessage::new(
+            decoder
+                .descriptor
+                .get_field_by_name("position")
+                .unwrap()
+                .kind()
+                .as_message()
+                .unwrap()
+                .clone(),
+        );
+        point.set_field_by_name("x", Value::F64(1.5));
+
+        let mut pose = DynamicMessage::new(decoder.descriptor.clone());
+        pose.set_field_by_name("position", Value::Message(point));
+        pose.set_field_by_name(
+            "weights",
+            Value::List(vec![Value::F64(0.5), Value::F64(2.0)]),
+        );
+        pose.set_field_by_name("mode", Value::EnumNumber(1));
+        pose.set_field_by_name(
+            "tags",
+            Value::Map(
+                [
+                    (MapKey::String("b".to_string()), Value::I32(2)),
+                    (MapKey::String("a".to_string()), Value::I32(1)),
+                ]
+                .into(),
+            ),
+        );
+        pose.set_field_by_name("raw", Value::Bytes(vec![1, 2].into()));
+        pose.set_field_by_name("number", Value::I64(-3));
+
+        assert_eq!(
+            decoder.decode(&pose.encode_to_vec()),
+            Ok(StructValue::Struct(vec![
+                (
+                    "position".to_string(),
+                    StructValue::Struct(vec![
+                        ("x".to_string(), StructValue::Double(1.5)),
+                        ("y".to_string(), StructValue::Double(0.0)),
+                    ])
+                ),
+                (
+                    "weights".to_string(),
+                    StructValue::Array(vec![StructValue::Double(0.5), StructValue::Double(2.0)])
+                ),
+                ("mode".to_string(), StructValue::String("AUTO".to_string())),
+                (
+                    "tags".to_string(),
+                    StructValue::Struct(vec![
+                        ("a".to_string(), StructValue::Int(1)),
+                        ("b".to_string(), StructValue::Int(2)),
+                    ])
+                ),
+                (
+                    "raw".to_string(),
+                    StructValue::Array(vec![StructValue::UInt(1), StructValue::UInt(2)])
+                ),
+                ("count".to_string(), StructValue::UInt(0)),
+                ("number".to_string(), StructValue::Int(-3)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn invalid_schemas_and_messages_are_errors() {
+        assert!(matches!(
+            ProtobufDecoder::new(&schema("test.Missing")),
+            Err(DecodeError::Schema(_))
+        ));
+
+        let decoder = ProtobufDecoder::new(&schema("test.Pose")).unwrap();
+        // a length delimited field running past the end
+        assert!(matches!(
+            decoder.decode(&[0x0A, 0x10, 0x00]),
+            Err(DecodeError::Message(_))
+        ));
+    }
+}
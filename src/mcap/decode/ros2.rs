@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use crate::{mcap::Schema, wpilog::structs::StructValue};
+
+use super::{DecodeError, MessageDecoder};
+
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitive {
+    Bool,
+    Byte,
+    Char,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float32,
+    Float64,
+    String,
+    WString,
+}
+
+impl Primitive {
+    fn from_name(name: &str) -> Option<Self> {
+        let primitive = match name {
+            "bool" => Primitive::Bool,
+            "byte" => Primitive::Byte,
+            "char" => Primitive::Char,
+            "int8" => Primitive::Int8,
+            "uint8" => Primitive::UInt8,
+            "int16" => Primitive::Int16,
+            "uint16" => Primitive::UInt16,
+            "int32" => Primitive::Int32,
+            "uint32" => Primitive::UInt32,
+            "int64" => Primitive::Int64,
+            "uint64" => Primitive::UInt64,
+            "float32" => Primitive::Float32,
+            "float64" => Primitive::Float64,
+            "string" => Primitive::String,
+            "wstring" => Primitive::WString,
+            _ => return None,
+        };
+
+        Some(primitive)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FieldType {
+    Primitive(Primitive),
+    /// A nested message, by its normalized `package/Type` name.
+    Message(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayKind {
+    Single,
+    Fixed(usize),
+    /// An unbounded or bounded sequence, prefixed with its length.
+    Sequence,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Field {
+    name: String,
+    typ: FieldType,
+    array: ArrayKind,
+}
+
+/// Decodes CDR serialized ROS 2 messages with a `ros2msg` schema: the message definition,
+/// followed by the definitions of every message it uses, each after a line of `=` and a
+/// `MSG: package/Type` line.
+pub struct CdrDecoder {
+    root: String,
+    definitions: HashMap<String, Vec<Field>>,
+}
+
+impl CdrDecoder {
+    pub fn new(schema: &Schema) -> Result<Self, DecodeError> {
+        let text = std::str::from_utf8(schema.data())
+            .map_err(|err| DecodeError::Schema(err.to_string()))?;
+
+        let root = normalize(schema.name());
+        let mut definitions = HashMap::new();
+        let mut name = root.clone();
+        let mut lines = Vec::new();
+
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.len() >= 3 && trimmed.chars().all(|c| c == '=') {
+                definitions.insert(name.clone(), parse_definition(&name, &lines)?);
+                name.clear();
+                lines.clear();
+            } else if let Some(msg) = trimmed.strip_prefix("MSG:").filter(|_| name.is_empty()) {
+                name = normalize(msg.trim());
+            } else {
+                lines.push(line);
+            }
+        }
+        if name.is_empty() {
+            return Err(DecodeError::Schema("missing MSG: line".to_string()));
+        }
+        definitions.insert(name.clone(), parse_definition(&name, &lines)?);
+
+        for fields in definitions.values() {
+            for field in fields {
+                if let FieldType::Message(typ) = &field.typ {
+                    if !definitions.contains_key(typ) {
+                        return Err(DecodeError::Schema(format!(
+                            "missing definition of {}",
+                            typ
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(CdrDecoder { root, definitions })
+    }
+
+    fn read_message(
+        &self,
+        reader: &mut CdrReader,
+        name: &str,
+        depth: usize,
+    ) -> Result<StructValue, DecodeError> {
+        if depth > MAX_DEPTH {
+            return Err(DecodeError::Schema(format!(
+                "{} is nested too deeply",
+                name
+            )));
+        }
+
+        let fields = &self.definitions[name];
+        // messages without fields are serialized as a single placeholder byte
+        if fields.is_empty() {
+            reader.read::<1>()?;
+            return Ok(StructValue::Struct(Vec::new()));
+        }
+
+        let mut members = Vec::with_capacity(fields.len());
+        for field in fields {
+            let value = match field.array {
+                ArrayKind::Single => self.read_field(reader, &field.typ, depth)?,
+                ArrayKind::Fixed(len) => self.read_array(reader, &field.typ, len, depth)?,
+                ArrayKind::Sequence => {
+                    let len = reader.read_u32()? as usize;
+                    if len > reader.remaining() {
+                        return Err(DecodeError::Message(format!(
+                            "{} has {} elements, but only {} bytes are left",
+                            field.name,
+                            len,
+                            reader.remaining()
+                        )));
+                    }
+                    self.read_array(reader, &field.typ, len, depth)?
+                }
+            };
+            members.push((field.name.clone(), value));
+        }
+
+        Ok(StructValue::Struct(members))
+    }
+
+    fn read_array(
+        &self,
+        reader: &mut CdrReader,
+        typ: &FieldType,
+        len: usize,
+        depth: usize,
+    ) -> Result<StructValue, DecodeError> {
+        let values = (0..len)
+            .map(|_| self.read_field(reader, typ, depth))
+            .collect::<Result<_, _>>()?;
+
+        Ok(StructValue::Array(values))
+    }
+
+    fn read_field(
+        &self,
+        reader: &mut CdrReader,
+        typ: &FieldType,
+        depth: usize,
+    ) -> Result<StructValue, DecodeError> {
+        let primitive = match typ {
+            FieldType::Message(name) => return self.read_message(reader, name, depth + 1),
+            FieldType::Primitive(primitive) => primitive,
+        };
+
+        let value = match primitive {
+            Primitive::Bool => StructValue::Bool(reader.read::<1>()?[0] != 0),
+            Primitive::Byte | Primitive::Char | Primitive::UInt8 => {
+                StructValue::UInt(reader.read::<1>()?[0] as u64)
+            }
+            Primitive::Int8 => StructValue::Int(reader.read::<1>()?[0] as i8 as i64),
+            Primitive::Int16 => StructValue::Int(i16::from_bytes(reader.read()?, reader.le) as i64),
+            Primitive::UInt16 => {
+                StructValue::UInt(u16::from_bytes(reader.read()?, reader.le) as u64)
+            }
+            Primitive::Int32 => StructValue::Int(i32::from_bytes(reader.read()?, reader.le) as i64),
+            Primitive::UInt32 => StructValue::UInt(reader.read_u32()? as u64),
+            Primitive::Int64 => StructValue::Int(i64::from_bytes(reader.read()?, reader.le)),
+            Primitive::UInt64 => StructValue::UInt(u64::from_bytes(reader.read()?, reader.le)),
+            Primitive::Float32 => StructValue::Float(f32::from_bytes(reader.read()?, reader.le)),
+            Primitive::Float64 => StructValue::Double(f64::from_bytes(reader.read()?, reader.le)),
+            Primitive::String => {
+                let len = reader.read_u32()? as usize;
+                let bytes = reader.read_bytes(len)?;
+                // the length includes a null terminator
+                let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+                let val = std::str::from_utf8(bytes)
+                    .map_err(|err| DecodeError::Message(err.to_string()))?;
+                StructValue::String(val.to_string())
+            }
+            Primitive::WString => {
+                let len = reader.read_u32()? as usize;
+                if len > reader.remaining() {
+                    return Err(DecodeError::Message("wstring is too long".to_string()));
+                }
+                let val = (0..len)
+                    .map(|_| Ok(char::from_u32(reader.read_u32()?).unwrap_or('\u{FFFD}')))
+                    .collect::<Result<String, DecodeError>>()?;
+                StructValue::String(val.trim_end_matches('\0').to_string())
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl MessageDecoder for CdrDecoder {
+    fn decode(&self, data: &[u8]) -> Result<StructValue, DecodeError> {
+        let mut reader = CdrReader::new(data)?;
+        self.read_message(&mut reader, &self.root, 0)
+    }
+}
+
+/// Turns `package/msg/Type` into `package/Type`.
+fn normalize(name: &str) -> String {
+    name.replace("/msg/", "/")
+}
+
+/// Parses the fields of one message definition, skipping comments and constants.
+fn parse_definition(name: &str, lines: &[&str]) -> Result<Vec<Field>, DecodeError> {
+    let package = name.split_once('/').map(|(package, _)| package);
+
+    let mut fields = Vec::new();
+    for line in lines {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let (Some(typ), Some(field_name)) = (tokens.next(), tokens.next()) else {
+            if line.is_empty() {
+                continue;
+            }
+            return Err(DecodeError::Schema(format!(
+                "invalid field {:?} in {}",
+                line, name
+            )));
+        };
+
+        // constants are declared as `type NAME=value`
+        if field_name.contains('=') || tokens.next().is_some_and(|token| token.starts_with('=')) {
+            continue;
+        }
+
+        let (base, array) = match typ.split_once('[') {
+            Some((base, array)) => {
+                let array = array.strip_suffix(']').ok_or_else(|| {
+                    DecodeError::Schema(format!("invalid array type {:?} in {}", typ, name))
+                })?;
+                let array = if array.is_empty() || array.starts_with("<=") {
+                    ArrayKind::Sequence
+                } else {
+                    ArrayKind::Fixed(array.parse().map_err(|_| {
+                        DecodeError::Schema(format!("invalid array length {:?} in {}", typ, name))
+                    })?)
+                };
+                (base, array)
+            }
+            None => (typ, ArrayKind::Single),
+        };
+        // bounded strings, like `string<=10`, are read like any other string
+        let base = base.split("<=").next().unwrap_or(base);
+
+        let typ = match Primitive::from_name(base) {
+            Some(primitive) => FieldType::Primitive(primitive),
+            None if base == "Header" => FieldType::Message("std_msgs/Header".to_string()),
+            None if base.contains('/') => FieldType::Message(normalize(base)),
+            None => match package {
+                Some(package) => FieldType::Message(format!("{}/{}", package, base)),
+                None => FieldType::Message(base.to_string()),
+            },
+        };
+
+        fields.push(Field {
+            name: field_name.to_string(),
+            typ,
+            array,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Reads plain CDR, aligning each primitive to its size from the end of the encapsulation header.
+struct CdrReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    le: bool,
+}
+
+impl<'a> CdrReader<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, DecodeError> {
+        let (header, data) = data
+            .split_first_chunk::<4>()
+            .ok_or_else(|| DecodeError::Message("missing CDR header".to_string()))?;
+
+        let le = match header[..2] {
+            [0x00, 0x00] => false,
+            [0x00, 0x01] => true,
+            _ => {
+                return Err(DecodeError::Message(format!(
+                    "unsupported CDR representation {:02x}{:02x}",
+                    header[0], header[1]
+                )))
+            }
+        };
+
+        Ok(CdrReader {
+            data,
+            position: 0,
+            le,
+        })
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        self.position = self.position.next_multiple_of(N);
+        let bytes = self.read_bytes(N)?;
+
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_bytes(self.read()?, self.le))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .position
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.position..end))
+            .ok_or_else(|| DecodeError::Message("message is too short".to_string()))?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+}
+
+trait FromBytes<const N: usize> {
+    fn from_bytes(bytes: [u8; N], le: bool) -> Self;
+}
+
+macro_rules! impl_from_bytes {
+    ($($typ:ty),*) => {
+        $(impl FromBytes<{ std::mem::size_of::<$typ>() }> for $typ {
+            fn from_bytes(bytes: [u8; std::mem::size_of::<$typ>()], le: bool) -> Self {
+                if le {
+                    <$typ>::from_le_bytes(bytes)
+                } else {
+                    <$typ>::from_be_bytes(bytes)
+                }
+            }
+        })*
+    };
+}
+
+impl_from_bytes!(i16, u16, i32, u32, i64, u64, f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSE: &str = "\
+# A pose
+geometry_msgs/Point position
+float64[<=4] weights
+string<=8 frame # bounded
+uint8 MODE_A=1
+int16 MODE_B = 2
+Stamp stamp
+bool[2] flags
+float64 t
+================================================================================
+MSG: geometry_msgs/Point
+float64 x
+float64 y
+float32 z
+================================================================================
+MSG: test_msgs/Stamp
+int32 sec
+uint32 nanosec
+";
+
+    fn schema(name: &str, data: &str) -> Schema<'static> {
+        Schema {
+            id: 1,
+            name: name.to_string().into(),
+            encoding: "ros2msg".into(),
+            data: data.as_bytes().to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn nested_messages_are_decoded() {
+        let decoder = CdrDecoder::new(&schema("test_msgs/msg/Pose", POSE)).unwrap();
+
+        let mut data = vec![0x00, 0x01, 0x00, 0x00];
+        data.extend(1.0f64.to_le_bytes());
+        data.extend(2.0f64.to_le_bytes());
+        data.extend(3.0f32.to_le_bytes());
+        // weights: a length, then an element at a multiple of 8
+        data.extend(1u32.to_le_bytes());
+        data.extend(0.5f64.to_le_bytes());
+        data.extend(4u32.to_le_bytes());
+        data.extend(b"map\0");
+        data.extend((-1i32).to_le_bytes());
+        data.extend(7u32.to_le_bytes());
+        data.extend([1, 0]);
+        // padding up to the next multiple of 8
+        data.extend([0; 6]);
+        data.extend(4.0f64.to_le_bytes());
+
+        let expected = StructValue::Struct(vec![
+            (
+                "position".to_string(),
+                StructValue::Struct(vec![
+                    ("x".to_string(), StructValue::Double(1.0)),
+                    ("y".to_string(), StructValue::Double(2.0)),
+                    ("z".to_string(), StructValue::Float(3.0)),
+                ]),
+            ),
+            (
+                "weights".to_string(),
+                StructValue::Array(vec![StructValue::Double(0.5)]),
+            ),
+            ("frame".to_string(), StructValue::String("map".to_string())),
+            (
+                "stamp".to_string(),
+                StructValue::Struct(vec![
+                    ("sec".to_string(), StructValue::Int(-1)),
+                    ("nanosec".to_string(), StructValue::UInt(7)),
+                ]),
+            ),
+            (
+                "flags".to_string(),
+                StructValue::Array(vec![StructValue::Bool(true), StructValue::Bool(false)]),
+            ),
+            ("t".to_string(), StructValue::Double(4.0)),
+        ]);
+
+        assert_eq!(decoder.decode(&data), Ok(expected.clone()));
+
+        let mut big_endian = vec![0x00, 0x00, 0x00, 0x00];
+        big_endian.extend(1.0f64.to_be_bytes());
+        big_endian.extend(2.0f64.to_be_bytes());
+        big_endian.extend(3.0f32.to_be_bytes());
+        big_endian.extend(1u32.to_be_bytes());
+        big_endian.extend(0.5f64.to_be_bytes());
+        big_endian.extend(4u32.to_be_bytes());
+        big_endian.extend(b"map\0");
+        big_endian.extend((-1i32).to_be_bytes());
+        big_endian.extend(7u32.to_be_bytes());
+        big_endian.extend([1, 0]);
+        big_endian.extend([0; 6]);
+        big_endian.extend(4.0f64.to_be_bytes());
+        assert_eq!(decoder.decode(&big_endian), Ok(expected));
+    }
+
+    #[test]
+    fn invalid_messages_are_errors() {
+        let decoder = CdrDecoder::new(&schema("test_msgs/msg/Pose", POSE)).unwrap();
+
+        assert!(matches!(
+            decoder.decode(&[0, 1]),
+            Err(DecodeError::Message(_))
+        ));
+        assert!(matches!(
+            decoder.decode(&[0, 1, 0, 0, 1, 2, 3]),
+            Err(DecodeError::Message(_))
+        ));
+        // a sequence longer than the message
+        let mut data = vec![0x00, 0x01, 0x00, 0x00];
+        data.extend([0; 24]);
+        data.extend(u32::MAX.to_le_bytes());
+        assert!(matches!(
+            decoder.decode(&data),
+            Err(DecodeError::Message(_))
+        ));
+    }
+
+    #[test]
+    fn missing_definitions_are_schema_errors() {
+        assert!(matches!(
+            CdrDecoder::new(&schema("test_msgs/msg/A", "test_msgs/B b\n")),
+            Err(DecodeError::Schema(_))
+        ));
+        assert!(matches!(
+            CdrDecoder::new(&schema("test_msgs/msg/A", "int32[x] a\n")),
+            Err(DecodeError::Schema(_))
+        ));
+    }
+}
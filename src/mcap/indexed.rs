@@ -0,0 +1,571 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap, VecDeque},
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use super::{
+    Attachment, AttachmentIndex, Channel, Chunk, ChunkIndex, Footer, Message, Metadata,
+    MetadataIndex, Parse, RawRecord, Record, Schema, Statistics, MAGIC,
+};
+
+/// Length of a footer record, with its opcode and length prefix.
+const FOOTER_LEN: u64 = 1 + 8 + 8 + 8 + 4;
+
+#[derive(Debug)]
+pub enum IndexError {
+    Parse(nom::error::ErrorKind),
+    Io(io::Error),
+    /// The footer doesn't point at a summary section, so there is nothing to index.
+    NoSummary,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::Parse(kind) => write!(f, "invalid MCAP file: {:?}", kind),
+            IndexError::Io(err) => write!(f, "{}", err),
+            IndexError::NoSummary => write!(f, "MCAP file has no summary section"),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+impl From<nom::Err<nom::error::Error<&[u8]>>> for IndexError {
+    fn from(value: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        match value {
+            nom::Err::Incomplete(_) => IndexError::Parse(nom::error::ErrorKind::Eof),
+            nom::Err::Error(err) | nom::Err::Failure(err) => IndexError::Parse(err.code),
+        }
+    }
+}
+
+impl From<io::Error> for IndexError {
+    fn from(value: io::Error) -> Self {
+        IndexError::Io(value)
+    }
+}
+
+/// Reads an MCAP file through its summary section, without walking the data section.
+///
+/// Only the footer and summary are read up front. Messages are read from the chunks whose
+/// [`ChunkIndex`] overlaps the requested time range and channels, seeking to each message
+/// through the chunk's message indexes. Messages outside of chunks aren't indexed and can't be
+/// read this way.
+pub struct IndexedMcapReader<R: Read + Seek> {
+    reader: R,
+    footer: Footer,
+    schemas: HashMap<u16, Schema<'static>>,
+    channels: BTreeMap<u16, Channel<'static>>,
+    chunk_indexes: Vec<ChunkIndex<'static>>,
+    attachment_indexes: Vec<AttachmentIndex<'static>>,
+    metadata_indexes: Vec<MetadataIndex<'static>>,
+    statistics: Option<Statistics>,
+}
+
+impl<R: Read + Seek> IndexedMcapReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, IndexError> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        let footer_offset = len
+            .checked_sub(FOOTER_LEN + MAGIC.len() as u64)
+            .ok_or(IndexError::Parse(nom::error::ErrorKind::Eof))?;
+
+        let tail = read_at(&mut reader, footer_offset, FOOTER_LEN + MAGIC.len() as u64)?;
+        let (magic, record) = Record::parse(&tail)?;
+        let Record::Footer(footer) = record else {
+            return Err(IndexError::Parse(nom::error::ErrorKind::Tag));
+        };
+        if magic != MAGIC {
+            return Err(IndexError::Parse(nom::error::ErrorKind::Tag));
+        }
+
+        if footer.summary_start == 0 || footer.summary_start > footer_offset {
+            return Err(IndexError::NoSummary);
+        }
+
+        let summary = read_at(
+            &mut reader,
+            footer.summary_start,
+            footer_offset - footer.summary_start,
+        )?;
+        let (_, records) =
+            nom::combinator::all_consuming(nom::multi::many0(Record::parse))(&summary[..])?;
+
+        let mut indexed = IndexedMcapReader {
+            reader,
+            footer,
+            schemas: HashMap::new(),
+            channels: BTreeMap::new(),
+            chunk_indexes: Vec::new(),
+            attachment_indexes: Vec::new(),
+            metadata_indexes: Vec::new(),
+            statistics: None,
+        };
+
+        for record in records {
+            match record.into_owned() {
+                Record::Schema(schema) => {
+                    indexed.schemas.insert(schema.id, schema);
+                }
+                Record::Channel(channel) => {
+                    indexed.channels.insert(channel.id, channel);
+                }
+                Record::ChunkIndex(index) => indexed.chunk_indexes.push(index),
+                Record::AttachmentIndex(index) => indexed.attachment_indexes.push(index),
+                Record::MetadataIndex(index) => indexed.metadata_indexes.push(index),
+                Record::Statistics(statistics) => indexed.statistics = Some(statistics),
+                _ => {}
+            }
+        }
+
+        indexed
+            .chunk_indexes
+            .sort_by_key(|index| index.chunk_start_offset);
+
+        Ok(indexed)
+    }
+
+    pub fn footer(&self) -> &Footer {
+        &self.footer
+    }
+
+    pub fn schemas(&self) -> &HashMap<u16, Schema<'static>> {
+        &self.schemas
+    }
+
+    pub fn channels(&self) -> &BTreeMap<u16, Channel<'static>> {
+        &self.channels
+    }
+
+    /// Chunk indexes in file order.
+    pub fn chunk_indexes(&self) -> &[ChunkIndex<'static>] {
+        &self.chunk_indexes
+    }
+
+    pub fn attachment_indexes(&self) -> &[AttachmentIndex<'static>] {
+        &self.attachment_indexes
+    }
+
+    pub fn metadata_indexes(&self) -> &[MetadataIndex<'static>] {
+        &self.metadata_indexes
+    }
+
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.statistics.as_ref()
+    }
+
+    /// Reads the attachment an index points at, without checking its CRC.
+    pub fn read_attachment(
+        &mut self,
+        index: &AttachmentIndex,
+    ) -> Result<Attachment<'static>, IndexError> {
+        let data = read_at(&mut self.reader, index.offset, index.length)?;
+        let (_, Record::Attachment(attachment)) = Record::parse(&data)? else {
+            return Err(IndexError::Parse(nom::error::ErrorKind::Tag));
+        };
+
+        Ok(attachment.into_owned())
+    }
+
+    /// Reads the metadata record an index points at.
+    pub fn read_metadata(
+        &mut self,
+        index: &MetadataIndex,
+    ) -> Result<Metadata<'static>, IndexError> {
+        let data = read_at(&mut self.reader, index.offset, index.length)?;
+        let (_, Record::Metadata(metadata)) = Record::parse(&data)? else {
+            return Err(IndexError::Parse(nom::error::ErrorKind::Tag));
+        };
+
+        Ok(metadata.into_owned())
+    }
+
+    /// Whether the summary indexes as many attachments and metadata records as its statistics
+    /// count. Writers may leave out the indexes, or the statistics.
+    pub fn indexes_all_attachments(&self) -> bool {
+        self.statistics.as_ref().is_some_and(|statistics| {
+            statistics.attachment_count as usize == self.attachment_indexes.len()
+                && statistics.metadata_count as usize == self.metadata_indexes.len()
+        })
+    }
+
+    /// Reads every indexed attachment, in the order of the indexes.
+    pub fn read_attachments(&mut self) -> Result<Vec<Attachment<'static>>, IndexError> {
+        self.attachment_indexes
+            .clone()
+            .iter()
+            .map(|index| self.read_attachment(index))
+            .collect()
+    }
+
+    /// Reads every indexed metadata record, in the order of the indexes.
+    pub fn read_all_metadata(&mut self) -> Result<Vec<Metadata<'static>>, IndexError> {
+        self.metadata_indexes
+            .clone()
+            .iter()
+            .map(|index| self.read_metadata(index))
+            .collect()
+    }
+
+    /// Iterates over the messages with a log time in `time` on the given channels, or on every
+    /// channel when `channels` is `None`, ordered by log time. Only the chunks that may hold such
+    /// messages are read, each one when the iterator reaches its start time.
+    pub fn read_messages(
+        &mut self,
+        time: Range<u64>,
+        channels: Option<&[u16]>,
+    ) -> IndexedMessages<'_, R> {
+        let channels = channels.map(<[u16]>::to_vec);
+        let wanted =
+            |channel_id: &u16| channels.as_ref().is_none_or(|ids| ids.contains(channel_id));
+
+        let mut chunks = self
+            .chunk_indexes
+            .iter()
+            .enumerate()
+            .filter(|(_, index)| {
+                index.message_start_time < time.end
+                    && index.message_end_time >= time.start
+                    && (index.message_index_offsets.is_empty()
+                        || index.message_index_offsets.keys().any(wanted))
+            })
+            .map(|(i, _)| i)
+            .collect::<VecDeque<_>>();
+        chunks
+            .make_contiguous()
+            .sort_by_key(|i| self.chunk_indexes[*i].message_start_time);
+
+        IndexedMessages {
+            reader: self,
+            time,
+            channels,
+            chunks,
+            pending: BinaryHeap::new(),
+        }
+    }
+
+    /// Reads the wanted messages of a chunk, in the order they were written.
+    fn read_chunk(
+        &mut self,
+        chunk: usize,
+        time: &Range<u64>,
+        wanted: impl Fn(u16) -> bool,
+    ) -> Result<Vec<Message<'static>>, IndexError> {
+        let index = self.chunk_indexes[chunk].clone();
+        let offsets = self.message_offsets(&index, time, &wanted)?;
+        if offsets.as_ref().is_some_and(|offsets| offsets.is_empty()) {
+            return Ok(Vec::new());
+        }
+
+        let data = read_at(
+            &mut self.reader,
+            index.chunk_start_offset,
+            index.chunk_length,
+        )?;
+        let (_, Record::Chunk(chunk)) = Record::parse(&data)? else {
+            return Err(IndexError::Parse(nom::error::ErrorKind::Tag));
+        };
+        let records = chunk.decompress()?;
+
+        let raws = match offsets {
+            Some(offsets) => offsets
+                .into_iter()
+                .map(|offset| {
+                    let record = records
+                        .get(offset as usize..)
+                        .ok_or(IndexError::Parse(nom::error::ErrorKind::Eof))?;
+                    Ok(RawRecord::parse(record)?.1)
+                })
+                .collect::<Result<Vec<_>, IndexError>>()?,
+            None => Chunk::parse_records(&records)?.1,
+        };
+
+        let mut messages = Vec::new();
+        for raw in raws {
+            if let Record::Message(message) = Record::try_from(raw)? {
+                if time.contains(&message.log_time) && wanted(message.channel_id) {
+                    messages.push(message.into_owned());
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Offsets of the wanted messages in the chunk's decompressed records, from its message
+    /// indexes. `None` when the chunk has no message indexes and has to be scanned.
+    fn message_offsets(
+        &mut self,
+        index: &ChunkIndex,
+        time: &Range<u64>,
+        wanted: impl Fn(u16) -> bool,
+    ) -> Result<Option<Vec<u64>>, IndexError> {
+        if index.message_index_offsets.is_empty() {
+            return Ok(None);
+        }
+
+        let start = index.chunk_start_offset + index.chunk_length;
+        let data = read_at(&mut self.reader, start, index.message_index_length)?;
+
+        let mut offsets = Vec::new();
+        for (channel_id, offset) in &index.message_index_offsets {
+            if !wanted(*channel_id) {
+                continue;
+            }
+
+            let record = offset
+                .checked_sub(start)
+                .and_then(|offset| data.get(offset as usize..))
+                .ok_or(IndexError::Parse(nom::error::ErrorKind::Eof))?;
+            let (_, Record::MessageIndex(message_index)) = Record::parse(record)? else {
+                return Err(IndexError::Parse(nom::error::ErrorKind::Tag));
+            };
+
+            offsets.extend(
+                message_index
+                    .records
+                    .iter()
+                    .filter(|(log_time, _)| time.contains(log_time))
+                    .map(|(_, offset)| *offset),
+            );
+        }
+
+        offsets.sort_unstable();
+
+        Ok(Some(offsets))
+    }
+}
+
+/// Iterator over messages read through the chunk indexes, see
+/// [`IndexedMcapReader::read_messages`].
+///
+/// Chunks are read in order of their start time. Messages are held back until no chunk left to
+/// read can hold an earlier one, so chunks whose time ranges overlap are merged.
+pub struct IndexedMessages<'r, R: Read + Seek> {
+    reader: &'r mut IndexedMcapReader<R>,
+    time: Range<u64>,
+    channels: Option<Vec<u16>>,
+    // positions of the chunks left to read in the chunk indexes
+    chunks: VecDeque<usize>,
+    pending: BinaryHeap<Pending>,
+}
+
+/// A message read from the chunk at position `.0`, ordered by log time, then by its position in
+/// the file, as a min heap.
+struct Pending(usize, usize, Message<'static>);
+
+impl Pending {
+    fn key(&self) -> (u64, usize, usize) {
+        (self.2.log_time, self.0, self.1)
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+impl<R: Read + Seek> Iterator for IndexedMessages<'_, R> {
+    type Item = Result<Message<'static>, IndexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next_start = self
+                .chunks
+                .front()
+                .map(|chunk| self.reader.chunk_indexes[*chunk].message_start_time);
+            if let Some(pending) = self.pending.peek() {
+                if next_start.is_none_or(|start| pending.2.log_time < start) {
+                    return self.pending.pop().map(|pending| Ok(pending.2));
+                }
+            }
+
+            let chunk = self.chunks.pop_front()?;
+            let channels = &self.channels;
+            let wanted = |channel_id| {
+                channels
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&channel_id))
+            };
+            match self.reader.read_chunk(chunk, &self.time, wanted) {
+                Ok(messages) => self.pending.extend(
+                    messages
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, message)| Pending(chunk, i, message)),
+                ),
+                Err(err) => {
+                    // nothing after a broken chunk is returned
+                    self.chunks.clear();
+                    self.pending.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Reads `len` bytes at `offset`, failing if the file ends first.
+fn read_at(reader: &mut (impl Read + Seek), offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+
+    let mut data = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::mcap::writer::{McapWriteOptions, McapWriter};
+
+    fn write_sample() -> Vec<u8> {
+        let mut mcap = McapWriter::with_options(
+            Vec::new(),
+            McapWriteOptions {
+                chunk_size: Some(200),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let schema = mcap.add_schema("Value", "jsonschema", b"{}").unwrap();
+        for topic in ["/a", "/b", "/c"] {
+            mcap.add_channel(schema, topic, "json", &HashMap::new())
+                .unwrap();
+        }
+
+        for i in 0..60u32 {
+            let data = format!("{{\"value\":{}}}", i);
+            mcap.write_message((i % 3) as u16, i / 3, i as u64 * 10, 0, data.as_bytes())
+                .unwrap();
+        }
+        mcap.write_attachment(5, 6, "calibration.json", "application/json", b"{}")
+            .unwrap();
+        mcap.write_metadata("run", &HashMap::from([("team", "1234")]))
+            .unwrap();
+
+        mcap.finish().unwrap()
+    }
+
+    #[test]
+    fn summary_is_loaded() {
+        let reader = IndexedMcapReader::new(Cursor::new(write_sample())).unwrap();
+
+        assert_eq!(reader.schemas().len(), 1);
+        assert_eq!(
+            reader
+                .channels()
+                .values()
+                .map(|channel| channel.topic())
+                .collect::<Vec<_>>(),
+            vec!["/a", "/b", "/c"]
+        );
+        assert!(reader.chunk_indexes().len() > 1);
+        assert_eq!(reader.statistics().unwrap().message_count(), 60);
+    }
+
+    #[test]
+    fn messages_are_filtered_by_time_and_channel() {
+        let mut reader = IndexedMcapReader::new(Cursor::new(write_sample())).unwrap();
+
+        let messages = reader
+            .read_messages(100..300, Some(&[1]))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| message.log_time())
+                .collect::<Vec<_>>(),
+            (10..30)
+                .filter(|i| i % 3 == 1)
+                .map(|i| i * 10)
+                .collect::<Vec<_>>()
+        );
+        assert!(messages.iter().all(|message| message.channel_id() == 1));
+
+        let all = reader
+            .read_messages(0..u64::MAX, None)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(all.len(), 60);
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].log_time() <= pair[1].log_time()));
+    }
+
+    #[test]
+    fn chunks_are_read_when_reached() {
+        let mut data = write_sample();
+        let reader = IndexedMcapReader::new(Cursor::new(data.clone())).unwrap();
+        // break the opcode of the last chunk
+        let last = reader.chunk_indexes().last().unwrap().chunk_start_offset;
+        data[last as usize] = 0xff;
+
+        let mut reader = IndexedMcapReader::new(Cursor::new(data)).unwrap();
+        let mut messages = reader.read_messages(0..u64::MAX, None);
+        assert_eq!(messages.next().unwrap().unwrap().log_time(), 0);
+        assert!(messages.any(|message| message.is_err()));
+        assert!(messages.next().is_none());
+    }
+
+    #[test]
+    fn attachments_and_metadata_are_read_through_indexes() {
+        let mut reader = IndexedMcapReader::new(Cursor::new(write_sample())).unwrap();
+
+        let index = reader.attachment_indexes()[0].clone();
+        assert_eq!(index.name(), "calibration.json");
+        assert_eq!(index.data_size(), 2);
+        let attachment = reader.read_attachment(&index).unwrap();
+        assert_eq!(attachment.media_type(), "application/json");
+        assert_eq!(attachment.data(), b"{}");
+        assert!(attachment.verify_crc());
+
+        let index = reader.metadata_indexes()[0].clone();
+        let metadata = reader.read_metadata(&index).unwrap();
+        assert_eq!(metadata.name(), "run");
+        assert_eq!(metadata.metadata()["team"], "1234");
+
+        assert!(reader.indexes_all_attachments());
+        assert_eq!(reader.read_attachments().unwrap(), vec![attachment]);
+        assert_eq!(reader.read_all_metadata().unwrap(), vec![metadata]);
+    }
+
+    #[test]
+    fn unindexed_files_are_rejected() {
+        let mut data = write_sample();
+        // zero the footer's summary_start
+        let footer = data.len() - MAGIC.len() - FOOTER_LEN as usize + 9;
+        data[footer..footer + 8].fill(0);
+
+        assert!(matches!(
+            IndexedMcapReader::new(Cursor::new(data)),
+            Err(IndexError::NoSummary)
+        ));
+    }
+}
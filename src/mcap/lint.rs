@@ -0,0 +1,727 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{Chunk, ChunkIndex, Parse, RawRecord, Record, Statistics, MAGIC};
+
+/// A way an MCAP file doesn't follow the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The file doesn't start with the magic bytes. Nothing after it is checked.
+    MissingLeadingMagic,
+    /// The file doesn't end with the magic bytes.
+    MissingTrailingMagic,
+    /// A record runs past the end of the file or chunk.
+    Truncated,
+    /// The content of a record with this opcode couldn't be parsed.
+    InvalidRecord { op: u8 },
+    /// The first record is not a header.
+    HeaderNotFirst { op: Option<u8> },
+    /// The last record is not a footer.
+    FooterNotLast { op: Option<u8> },
+    /// A chunk whose records couldn't be decompressed.
+    Chunk(String),
+    /// A message on a channel that wasn't defined before it.
+    UnknownChannel { channel_id: u16 },
+    /// A channel with a schema that wasn't defined before it.
+    UnknownSchema { channel_id: u16, schema_id: u16 },
+    /// A message logged outside of its chunk's time range.
+    MessageOutsideChunk { log_time: u64, start: u64, end: u64 },
+    /// A chunk index that doesn't point at a chunk record.
+    ChunkIndexOffset { chunk_start_offset: u64 },
+    /// A chunk index whose length differs from the length of the chunk record it points at.
+    ChunkIndexLength { expected: u64, actual: u64 },
+    /// A chunk index whose message index offset doesn't point at a message index for the channel.
+    MessageIndexOffset { channel_id: u16, offset: u64 },
+    /// A statistics field that doesn't match the file.
+    Statistics {
+        field: &'static str,
+        expected: u64,
+        actual: u64,
+    },
+    /// A channel's message count in the statistics that doesn't match the file.
+    ChannelMessageCount {
+        channel_id: u16,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingLeadingMagic => write!(f, "missing magic at the start of the file"),
+            Problem::MissingTrailingMagic => write!(f, "missing magic at the end of the file"),
+            Problem::Truncated => write!(f, "truncated record"),
+            Problem::InvalidRecord { op } => write!(f, "invalid record with opcode {:#04x}", op),
+            Problem::HeaderNotFirst { op: Some(op) } => {
+                write!(f, "first record has opcode {:#04x}, expected a header", op)
+            }
+            Problem::HeaderNotFirst { op: None } => write!(f, "missing header"),
+            Problem::FooterNotLast { op: Some(op) } => {
+                write!(f, "last record has opcode {:#04x}, expected a footer", op)
+            }
+            Problem::FooterNotLast { op: None } => write!(f, "missing footer"),
+            Problem::Chunk(err) => write!(f, "chunk records can't be read: {}", err),
+            Problem::UnknownChannel { channel_id } => {
+                write!(f, "message on undefined channel {}", channel_id)
+            }
+            Problem::UnknownSchema {
+                channel_id,
+                schema_id,
+            } => write!(
+                f,
+                "channel {} has undefined schema {}",
+                channel_id, schema_id
+            ),
+            Problem::MessageOutsideChunk {
+                log_time,
+                start,
+                end,
+            } => write!(
+                f,
+                "message log time {} is outside of its chunk's range {}..={}",
+                log_time, start, end
+            ),
+            Problem::ChunkIndexOffset { chunk_start_offset } => write!(
+                f,
+                "chunk index points at offset {}, which is not a chunk",
+                chunk_start_offset
+            ),
+            Problem::ChunkIndexLength { expected, actual } => write!(
+                f,
+                "chunk index has chunk length {}, the chunk is {} bytes",
+                expected, actual
+            ),
+            Problem::MessageIndexOffset { channel_id, offset } => write!(
+                f,
+                "chunk index points at offset {} for the message index of channel {}, which is not one",
+                offset, channel_id
+            ),
+            Problem::Statistics {
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "statistics {} is {}, the file has {}",
+                field, expected, actual
+            ),
+            Problem::ChannelMessageCount {
+                channel_id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "statistics message count of channel {} is {}, the file has {}",
+                channel_id, expected, actual
+            ),
+        }
+    }
+}
+
+/// A problem found by [`lint`], in the record at `offset`. Records inside a chunk are located by
+/// the chunk's offset and their offset in its decompressed records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub offset: u64,
+    pub chunk_offset: Option<u64>,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.chunk_offset {
+            Some(chunk_offset) => write!(
+                f,
+                "offset {} in chunk at offset {}: {}",
+                chunk_offset, self.offset, self.problem
+            ),
+            None => write!(f, "offset {}: {}", self.offset, self.problem),
+        }
+    }
+}
+
+/// What the file holds, to compare against its statistics.
+#[derive(Default)]
+struct Counts {
+    messages: u64,
+    schemas: HashSet<u16>,
+    channels: HashSet<u16>,
+    attachments: u64,
+    metadata: u64,
+    chunks: u64,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+    channel_messages: HashMap<u16, u64>,
+}
+
+#[derive(Default)]
+struct Linter<'a> {
+    findings: Vec<Finding>,
+    counts: Counts,
+    // offset -> length of each chunk record
+    chunks: HashMap<u64, u64>,
+    // offset -> channel id of each message index record
+    message_indexes: HashMap<u64, u16>,
+    chunk_indexes: Vec<(u64, ChunkIndex<'a>)>,
+    statistics: Vec<(u64, Statistics)>,
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, offset: u64, chunk_offset: Option<u64>, problem: Problem) {
+        self.findings.push(Finding {
+            offset,
+            chunk_offset,
+            problem,
+        });
+    }
+
+    /// Checks a record in the data or summary section, or inside the chunk at `chunk` with its
+    /// time range.
+    fn record(
+        &mut self,
+        record: &Record,
+        offset: u64,
+        chunk_offset: Option<u64>,
+        chunk: Option<&Chunk>,
+    ) {
+        match record {
+            Record::Schema(schema) => {
+                self.counts.schemas.insert(schema.id);
+            }
+            Record::Channel(channel) => {
+                if channel.schema_id != 0 && !self.counts.schemas.contains(&channel.schema_id) {
+                    let problem = Problem::UnknownSchema {
+                        channel_id: channel.id,
+                        schema_id: channel.schema_id,
+                    };
+                    self.report(offset, chunk_offset, problem);
+                }
+                self.counts.channels.insert(channel.id);
+            }
+            Record::Message(message) => {
+                if !self.counts.channels.contains(&message.channel_id) {
+                    let problem = Problem::UnknownChannel {
+                        channel_id: message.channel_id,
+                    };
+                    self.report(offset, chunk_offset, problem);
+                }
+                if let Some(chunk) = chunk {
+                    if !(chunk.message_start_time..=chunk.message_end_time)
+                        .contains(&message.log_time)
+                    {
+                        let problem = Problem::MessageOutsideChunk {
+                            log_time: message.log_time,
+                            start: chunk.message_start_time,
+                            end: chunk.message_end_time,
+                        };
+                        self.report(offset, chunk_offset, problem);
+                    }
+                }
+
+                let counts = &mut self.counts;
+                counts.messages += 1;
+                *counts
+                    .channel_messages
+                    .entry(message.channel_id)
+                    .or_default() += 1;
+                let log_time = message.log_time;
+                counts.start_time = Some(counts.start_time.map_or(log_time, |t| t.min(log_time)));
+                counts.end_time = Some(counts.end_time.map_or(log_time, |t| t.max(log_time)));
+            }
+            Record::Attachment(_) => self.counts.attachments += 1,
+            Record::Metadata(_) => self.counts.metadata += 1,
+            Record::MessageIndex(index) => {
+                self.message_indexes.insert(offset, index.channel_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn chunk(&mut self, chunk: &Chunk, offset: u64, length: u64) {
+        self.counts.chunks += 1;
+        self.chunks.insert(offset, length);
+
+        let records = match chunk.decompress() {
+            Ok(records) => records,
+            Err(err) => return self.report(offset, None, Problem::Chunk(err.to_string())),
+        };
+
+        let mut input = &records[..];
+        while !input.is_empty() {
+            let chunk_offset = Some((records.len() - input.len()) as u64);
+            let Ok((rest, raw)) = RawRecord::parse(input) else {
+                return self.report(offset, chunk_offset, Problem::Truncated);
+            };
+            input = rest;
+
+            match Record::try_from(raw) {
+                Ok(record) => self.record(&record, offset, chunk_offset, Some(chunk)),
+                Err(_) => self.report(offset, chunk_offset, Problem::InvalidRecord { op: raw.tag }),
+            }
+        }
+    }
+
+    fn check_indexes(&mut self) {
+        for (offset, index) in std::mem::take(&mut self.chunk_indexes) {
+            match self.chunks.get(&index.chunk_start_offset) {
+                None => self.report(
+                    offset,
+                    None,
+                    Problem::ChunkIndexOffset {
+                        chunk_start_offset: index.chunk_start_offset,
+                    },
+                ),
+                Some(&length) if length != index.chunk_length => self.report(
+                    offset,
+                    None,
+                    Problem::ChunkIndexLength {
+                        expected: index.chunk_length,
+                        actual: length,
+                    },
+                ),
+                Some(_) => {}
+            }
+
+            let mut message_indexes = index.message_index_offsets.iter().collect::<Vec<_>>();
+            message_indexes.sort();
+            for (&channel_id, &message_index) in message_indexes {
+                if self.message_indexes.get(&message_index) != Some(&channel_id) {
+                    let problem = Problem::MessageIndexOffset {
+                        channel_id,
+                        offset: message_index,
+                    };
+                    self.report(offset, None, problem);
+                }
+            }
+        }
+    }
+
+    fn check_statistics(&mut self) {
+        for (offset, statistics) in std::mem::take(&mut self.statistics) {
+            let counts = &self.counts;
+            let mut fields = vec![
+                ("message_count", statistics.message_count, counts.messages),
+                (
+                    "schema_count",
+                    statistics.schema_count as u64,
+                    counts.schemas.len() as u64,
+                ),
+                (
+                    "channel_count",
+                    statistics.channel_count as u64,
+                    counts.channels.len() as u64,
+                ),
+                (
+                    "attachment_count",
+                    statistics.attachment_count as u64,
+                    counts.attachments,
+                ),
+                (
+                    "metadata_count",
+                    statistics.metadata_count as u64,
+                    counts.metadata,
+                ),
+                ("chunk_count", statistics.chunk_count as u64, counts.chunks),
+            ];
+            // the time range is only set when there are messages
+            if let (Some(start), Some(end)) = (counts.start_time, counts.end_time) {
+                fields.push(("message_start_time", statistics.message_start_time, start));
+                fields.push(("message_end_time", statistics.message_end_time, end));
+            }
+
+            let mut channel_ids = statistics
+                .channel_message_counts
+                .keys()
+                .chain(counts.channel_messages.keys())
+                .copied()
+                .collect::<Vec<_>>();
+            channel_ids.sort();
+            channel_ids.dedup();
+            // the channel counts are optional, an empty map leaves them out
+            if statistics.channel_message_counts.is_empty() {
+                channel_ids.clear();
+            }
+            let channels = channel_ids
+                .into_iter()
+                .map(|channel_id| {
+                    let expected = statistics.channel_message_counts.get(&channel_id);
+                    let actual = counts.channel_messages.get(&channel_id);
+                    (
+                        channel_id,
+                        expected.copied().unwrap_or(0),
+                        actual.copied().unwrap_or(0),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            for (field, expected, actual) in fields {
+                if expected != actual {
+                    let problem = Problem::Statistics {
+                        field,
+                        expected,
+                        actual,
+                    };
+                    self.report(offset, None, problem);
+                }
+            }
+            for (channel_id, expected, actual) in channels {
+                if expected != actual {
+                    let problem = Problem::ChannelMessageCount {
+                        channel_id,
+                        expected,
+                        actual,
+                    };
+                    self.report(offset, None, problem);
+                }
+            }
+        }
+    }
+}
+
+/// Checks that an MCAP file follows the structure required by the specification, and returns
+/// every problem found in file order, followed by the problems with its indexes and statistics.
+///
+/// The file has to start and end with the magic bytes, start with a header and end with a footer.
+/// Every message has to be on a channel defined before it, and every channel has to have a schema
+/// defined before it or none. Chunks have to hold the messages of their time range, chunk indexes
+/// have to point at the chunks and message indexes they describe, and statistics have to match
+/// the records of the file. CRCs are checked by [`check_crcs`](super::validate::check_crcs).
+pub fn lint(input: &[u8]) -> Vec<Finding> {
+    let mut linter = Linter::default();
+
+    if !input.starts_with(MAGIC) {
+        linter.report(0, None, Problem::MissingLeadingMagic);
+        return linter.findings;
+    }
+
+    let mut last_op = None;
+    let mut rest = &input[MAGIC.len()..];
+    loop {
+        let offset = (input.len() - rest.len()) as u64;
+        if rest == MAGIC {
+            break;
+        }
+        if rest.is_empty() {
+            linter.report(offset, None, Problem::MissingTrailingMagic);
+            break;
+        }
+
+        let Ok((next, raw)) = RawRecord::parse(rest) else {
+            linter.report(offset, None, Problem::Truncated);
+            break;
+        };
+        let length = (rest.len() - next.len()) as u64;
+        rest = next;
+
+        if last_op.is_none() && raw.tag != 0x01 {
+            linter.report(offset, None, Problem::HeaderNotFirst { op: Some(raw.tag) });
+        }
+        last_op = Some((offset, raw.tag));
+
+        match Record::try_from(raw) {
+            Ok(Record::Chunk(chunk)) => linter.chunk(&chunk, offset, length),
+            Ok(Record::ChunkIndex(index)) => linter.chunk_indexes.push((offset, index)),
+            Ok(Record::Statistics(statistics)) => linter.statistics.push((offset, statistics)),
+            Ok(record) => linter.record(&record, offset, None, None),
+            Err(_) => linter.report(offset, None, Problem::InvalidRecord { op: raw.tag }),
+        }
+    }
+
+    match last_op {
+        Some((_, 0x02)) => {}
+        Some((offset, op)) => linter.report(offset, None, Problem::FooterNotLast { op: Some(op) }),
+        None => {
+            let offset = MAGIC.len() as u64;
+            linter.report(offset, None, Problem::HeaderNotFirst { op: None });
+            linter.report(offset, None, Problem::FooterNotLast { op: None });
+        }
+    }
+
+    linter.check_indexes();
+    linter.check_statistics();
+
+    linter.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::mcap::{
+        writer::{McapWriteOptions, McapWriter},
+        Channel, Footer, Header, Message, Schema,
+    };
+
+    fn write_sample(chunk_size: Option<usize>) -> Vec<u8> {
+        let mut mcap = McapWriter::with_options(
+            Vec::new(),
+            McapWriteOptions {
+                chunk_size,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let schema = mcap.add_schema("Value", "jsonschema", b"{}").unwrap();
+        let a = mcap
+            .add_channel(schema, "/a", "json", &HashMap::new())
+            .unwrap();
+        let b = mcap.add_channel(0, "/b", "json", &HashMap::new()).unwrap();
+        for time in 0..20 {
+            mcap.write_message(a, time as u32, time, time, b"1")
+                .unwrap();
+            mcap.write_message(b, time as u32, time + 5, time + 5, b"2")
+                .unwrap();
+        }
+        mcap.write_attachment(1, 1, "a.txt", "text/plain", b"a")
+            .unwrap();
+        mcap.write_metadata("m", &[("k", "v")].into()).unwrap();
+
+        mcap.finish().unwrap()
+    }
+
+    fn message(channel_id: u16, log_time: u64) -> Record<'static> {
+        Record::Message(Message {
+            channel_id,
+            sequence: 0,
+            log_time,
+            publish_time: log_time,
+            data: Cow::Borrowed(b"{}"),
+        })
+    }
+
+    fn header() -> Record<'static> {
+        Record::Header(Header {
+            profile: "".into(),
+            library: "".into(),
+        })
+    }
+
+    fn footer() -> Record<'static> {
+        Record::Footer(Footer {
+            summary_start: 0,
+            summary_offset_start: 0,
+            summary_crc: 0,
+        })
+    }
+
+    /// Encodes `records` between the magic bytes, returning the offset of each record.
+    fn encode(records: &[Record]) -> (Vec<u8>, Vec<u64>) {
+        let mut data = MAGIC.to_vec();
+        let mut offsets = Vec::new();
+        for record in records {
+            offsets.push(data.len() as u64);
+            record.encode(&mut data);
+        }
+        data.extend_from_slice(MAGIC);
+
+        (data, offsets)
+    }
+
+    fn problems(findings: Vec<Finding>) -> Vec<Problem> {
+        findings
+            .into_iter()
+            .map(|finding| finding.problem)
+            .collect()
+    }
+
+    #[test]
+    fn written_files_have_no_findings() {
+        assert_eq!(lint(&write_sample(Some(200))), vec![]);
+        assert_eq!(lint(&write_sample(None)), vec![]);
+    }
+
+    #[test]
+    fn magic_and_truncation_are_reported() {
+        let data = write_sample(Some(200));
+
+        assert_eq!(
+            problems(lint(&data[1..])),
+            vec![Problem::MissingLeadingMagic]
+        );
+        assert_eq!(
+            lint(&data[..data.len() - MAGIC.len()]),
+            vec![Finding {
+                offset: (data.len() - MAGIC.len()) as u64,
+                chunk_offset: None,
+                problem: Problem::MissingTrailingMagic,
+            }]
+        );
+
+        // the footer is cut short, leaving a summary offset as the last record
+        assert_eq!(
+            problems(lint(&data[..data.len() - MAGIC.len() - 1])),
+            vec![
+                Problem::Truncated,
+                Problem::FooterNotLast { op: Some(0x0E) }
+            ]
+        );
+
+        assert_eq!(
+            problems(lint(&[MAGIC, MAGIC].concat())),
+            vec![
+                Problem::HeaderNotFirst { op: None },
+                Problem::FooterNotLast { op: None }
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_references_are_reported() {
+        let (data, offsets) = encode(&[
+            message(1, 0),
+            header(),
+            Record::Channel(Channel {
+                id: 1,
+                schema_id: 2,
+                topic: "/a".into(),
+                message_encoding: "json".into(),
+                metadata: HashMap::new(),
+            }),
+            Record::Schema(Schema {
+                id: 2,
+                name: "A".into(),
+                encoding: "jsonschema".into(),
+                data: Cow::Borrowed(b"{}"),
+            }),
+            message(1, 0),
+        ]);
+
+        assert_eq!(
+            lint(&data),
+            vec![
+                Finding {
+                    offset: offsets[0],
+                    chunk_offset: None,
+                    problem: Problem::HeaderNotFirst { op: Some(0x05) },
+                },
+                Finding {
+                    offset: offsets[0],
+                    chunk_offset: None,
+                    problem: Problem::UnknownChannel { channel_id: 1 },
+                },
+                Finding {
+                    offset: offsets[2],
+                    chunk_offset: None,
+                    problem: Problem::UnknownSchema {
+                        channel_id: 1,
+                        schema_id: 2
+                    },
+                },
+                Finding {
+                    offset: offsets[4],
+                    chunk_offset: None,
+                    problem: Problem::FooterNotLast { op: Some(0x05) },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_indexes_and_statistics_are_checked() {
+        let mut records = Vec::new();
+        Record::Channel(Channel {
+            id: 1,
+            schema_id: 0,
+            topic: "/a".into(),
+            message_encoding: "json".into(),
+            metadata: HashMap::new(),
+        })
+        .encode(&mut records);
+        let message_offset = records.len() as u64;
+        message(1, 10).encode(&mut records);
+
+        let chunk = Record::Chunk(Chunk {
+            message_start_time: 0,
+            message_end_time: 5,
+            uncompressed_size: records.len() as u64,
+            uncompressed_crc: 0,
+            compression: "".into(),
+            records: Cow::Borrowed(&records),
+        });
+        let chunk_index = Record::ChunkIndex(ChunkIndex {
+            message_start_time: 0,
+            message_end_time: 5,
+            chunk_start_offset: 1,
+            chunk_length: 0,
+            message_index_offsets: [(1, 2)].into(),
+            message_index_length: 0,
+            compression: "".into(),
+            compressed_size: 0,
+            uncompressed_size: 0,
+        });
+        let statistics = Record::Statistics(Statistics {
+            message_count: 1,
+            schema_count: 0,
+            channel_count: 1,
+            attachment_count: 0,
+            metadata_count: 0,
+            chunk_count: 2,
+            message_start_time: 10,
+            message_end_time: 11,
+            channel_message_counts: [(1, 1), (2, 3)].into(),
+        });
+        let (data, offsets) = encode(&[header(), chunk, chunk_index, statistics, footer()]);
+
+        assert_eq!(
+            lint(&data),
+            vec![
+                Finding {
+                    offset: offsets[1],
+                    chunk_offset: Some(message_offset),
+                    problem: Problem::MessageOutsideChunk {
+                        log_time: 10,
+                        start: 0,
+                        end: 5
+                    },
+                },
+                Finding {
+                    offset: offsets[2],
+                    chunk_offset: None,
+                    problem: Problem::ChunkIndexOffset {
+                        chunk_start_offset: 1
+                    },
+                },
+                Finding {
+                    offset: offsets[2],
+                    chunk_offset: None,
+                    problem: Problem::MessageIndexOffset {
+                        channel_id: 1,
+                        offset: 2
+                    },
+                },
+                Finding {
+                    offset: offsets[3],
+                    chunk_offset: None,
+                    problem: Problem::Statistics {
+                        field: "chunk_count",
+                        expected: 2,
+                        actual: 1
+                    },
+                },
+                Finding {
+                    offset: offsets[3],
+                    chunk_offset: None,
+                    problem: Problem::Statistics {
+                        field: "message_end_time",
+                        expected: 11,
+                        actual: 10
+                    },
+                },
+                Finding {
+                    offset: offsets[3],
+                    chunk_offset: None,
+                    problem: Problem::ChannelMessageCount {
+                        channel_id: 2,
+                        expected: 3,
+                        actual: 0
+                    },
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap, VecDeque},
+    fmt, io,
+    sync::Arc,
+};
+
+use super::{Channel, Chunk, MCap, Message, RawRecord, Record, Schema};
+
+#[derive(Debug)]
+pub enum MessageError {
+    Parse(nom::error::ErrorKind),
+    Io(io::Error),
+    /// A message on a channel that wasn't defined before it.
+    UnknownChannel(u16),
+    /// A channel with a schema that wasn't defined before it.
+    UnknownSchema(u16),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::Parse(kind) => write!(f, "invalid MCAP file: {:?}", kind),
+            MessageError::Io(err) => write!(f, "{}", err),
+            MessageError::UnknownChannel(id) => write!(f, "message on unknown channel {}", id),
+            MessageError::UnknownSchema(id) => write!(f, "channel with unknown schema {}", id),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+impl From<nom::Err<nom::error::Error<&[u8]>>> for MessageError {
+    fn from(value: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        match value {
+            nom::Err::Incomplete(_) => MessageError::Parse(nom::error::ErrorKind::Eof),
+            nom::Err::Error(err) | nom::Err::Failure(err) => MessageError::Parse(err.code),
+        }
+    }
+}
+
+impl From<io::Error> for MessageError {
+    fn from(value: io::Error) -> Self {
+        MessageError::Io(value)
+    }
+}
+
+/// A message with the channel it was published on and the channel's schema, if it has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageEvent<'a> {
+    pub message: Message<'a>,
+    pub channel: Arc<Channel<'a>>,
+    pub schema: Option<Arc<Schema<'a>>>,
+}
+
+/// Iterator over the messages of an MCAP file, see [`MCap::messages`].
+pub struct Messages<'a> {
+    records: Vec<RawRecord<'a>>,
+    position: usize,
+    definitions: Definitions<'a>,
+    pending: Pending<'a>,
+}
+
+/// The schemas and channels seen so far, which messages are resolved against.
+#[derive(Default)]
+pub(super) struct Definitions<'a> {
+    schemas: HashMap<u16, Arc<Schema<'a>>>,
+    channels: HashMap<u16, Arc<Channel<'a>>>,
+}
+
+impl<'a> Definitions<'a> {
+    /// Adds a schema, returning `None` if an identical one was already added.
+    pub(super) fn add_schema(&mut self, schema: Schema<'a>) -> Option<Arc<Schema<'a>>> {
+        if self
+            .schemas
+            .get(&schema.id)
+            .is_some_and(|known| **known == schema)
+        {
+            return None;
+        }
+
+        let schema = Arc::new(schema);
+        self.schemas.insert(schema.id, schema.clone());
+        Some(schema)
+    }
+
+    /// Adds a channel, returning `None` if an identical one was already added.
+    pub(super) fn add_channel(
+        &mut self,
+        channel: Channel<'a>,
+    ) -> Result<Option<Arc<Channel<'a>>>, MessageError> {
+        if channel.schema_id != 0 && !self.schemas.contains_key(&channel.schema_id) {
+            return Err(MessageError::UnknownSchema(channel.schema_id));
+        }
+        if self
+            .channels
+            .get(&channel.id)
+            .is_some_and(|known| **known == channel)
+        {
+            return Ok(None);
+        }
+
+        let channel = Arc::new(channel);
+        self.channels.insert(channel.id, channel.clone());
+        Ok(Some(channel))
+    }
+
+    pub(super) fn resolve(&self, message: Message<'a>) -> Result<MessageEvent<'a>, MessageError> {
+        let channel = self
+            .channels
+            .get(&message.channel_id)
+            .ok_or(MessageError::UnknownChannel(message.channel_id))?
+            .clone();
+        let schema = self.schemas.get(&channel.schema_id).cloned();
+
+        Ok(MessageEvent {
+            message,
+            channel,
+            schema,
+        })
+    }
+}
+
+enum Pending<'a> {
+    FileOrder(VecDeque<MessageEvent<'a>>),
+    /// Messages are held back until no later record can hold an earlier message. `start_times`
+    /// has the earliest log time found in or after each record.
+    LogTimeOrder {
+        heap: BinaryHeap<Ordered<'a>>,
+        start_times: Vec<u64>,
+        next: u64,
+    },
+}
+
+/// Orders messages by log time, then by their position in the file, as a min heap.
+struct Ordered<'a>(u64, MessageEvent<'a>);
+
+impl Ordered<'_> {
+    fn key(&self) -> (u64, u64) {
+        (self.1.message.log_time, self.0)
+    }
+}
+
+impl PartialEq for Ordered<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Ordered<'_> {}
+
+impl PartialOrd for Ordered<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ordered<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+impl<'a> MCap<'a> {
+    /// Iterates over the messages in file order, including those in chunks, which are
+    /// decompressed when reached. Schemas and channels are collected along the way.
+    pub fn messages(&self) -> Messages<'a> {
+        Messages {
+            records: self.records.clone(),
+            position: 0,
+            definitions: Definitions::default(),
+            pending: Pending::FileOrder(VecDeque::new()),
+        }
+    }
+
+    /// Collects every channel with its schema, including those defined in chunks, ordered by
+    /// channel id.
+    pub fn channels(
+        &self,
+    ) -> Result<Vec<(Channel<'static>, Option<Schema<'static>>)>, MessageError> {
+        let mut schemas = HashMap::new();
+        let mut channels = BTreeMap::new();
+
+        let mut add = |record: Record<'_>| match record {
+            Record::Schema(schema) => {
+                schemas.insert(schema.id, schema.into_owned());
+            }
+            Record::Channel(channel) => {
+                channels.insert(channel.id, channel.into_owned());
+            }
+            _ => {}
+        };
+
+        for raw in &self.records {
+            match Record::try_from(*raw)? {
+                Record::Chunk(chunk) => {
+                    let records = chunk.decompress()?;
+                    for raw in Chunk::parse_records(&records)?.1 {
+                        add(Record::try_from(raw)?);
+                    }
+                }
+                record => add(record),
+            }
+        }
+
+        Ok(channels
+            .into_values()
+            .map(|channel| {
+                let schema = schemas.get(&channel.schema_id).cloned();
+                (channel, schema)
+            })
+            .collect())
+    }
+}
+
+impl<'a> Messages<'a> {
+    /// Yields messages in strict log time order instead, merging chunks whose time ranges
+    /// overlap. Messages are buffered until every later chunk starts after them.
+    pub fn ordered(mut self) -> Self {
+        let mut start_times = vec![u64::MAX; self.records.len() + 1];
+        for (i, raw) in self.records.iter().enumerate().rev() {
+            let start_time = match Record::try_from(*raw) {
+                Ok(Record::Chunk(chunk)) => chunk.message_start_time,
+                Ok(Record::Message(message)) => message.log_time,
+                _ => u64::MAX,
+            };
+            start_times[i] = start_time.min(start_times[i + 1]);
+        }
+
+        self.pending = Pending::LogTimeOrder {
+            heap: BinaryHeap::new(),
+            start_times,
+            next: 0,
+        };
+
+        self
+    }
+
+    fn pop(&mut self) -> Option<MessageEvent<'a>> {
+        match &mut self.pending {
+            Pending::FileOrder(queue) => queue.pop_front(),
+            Pending::LogTimeOrder {
+                heap, start_times, ..
+            } => {
+                let bound = start_times[self.position];
+                match heap.peek() {
+                    Some(first) if first.1.message.log_time <= bound => {
+                        heap.pop().map(|first| first.1)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, message: MessageEvent<'a>) {
+        match &mut self.pending {
+            Pending::FileOrder(queue) => queue.push_back(message),
+            Pending::LogTimeOrder { heap, next, .. } => {
+                heap.push(Ordered(*next, message));
+                *next += 1;
+            }
+        }
+    }
+
+    fn add(&mut self, record: Record<'a>) -> Result<(), MessageError> {
+        match record {
+            Record::Schema(schema) => {
+                self.definitions.add_schema(schema);
+            }
+            Record::Channel(channel) => {
+                self.definitions.add_channel(channel)?;
+            }
+            Record::Message(message) => {
+                let message = self.definitions.resolve(message)?;
+                self.push(message);
+            }
+            Record::Chunk(chunk) => self.add_chunk(chunk)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn add_chunk(&mut self, chunk: Chunk<'a>) -> Result<(), MessageError> {
+        match chunk.into_decompressed()? {
+            Cow::Borrowed(data) => {
+                for raw in Chunk::parse_records(data)?.1 {
+                    self.add(Record::try_from(raw)?)?;
+                }
+            }
+            Cow::Owned(data) => {
+                for raw in Chunk::parse_records(&data)?.1 {
+                    self.add(Record::try_from(raw)?.into_owned())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<MessageEvent<'a>, MessageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(message) = self.pop() {
+                return Some(Ok(message));
+            }
+
+            let raw = *self.records.get(self.position)?;
+            self.position += 1;
+
+            let added = Record::try_from(raw)
+                .map_err(MessageError::from)
+                .and_then(|record| self.add(record));
+            if let Err(err) = added {
+                // nothing after a broken record can be trusted
+                self.position = self.records.len();
+                self.pending = Pending::FileOrder(VecDeque::new());
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcap::{
+        writer::{McapWriteOptions, McapWriter},
+        Encode, Header, Magic, Parse,
+    };
+
+    fn log_times(messages: Messages) -> Vec<u64> {
+        messages
+            .map(|message| message.unwrap().message.log_time)
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_chunks_are_merged() {
+        let mut mcap = McapWriter::with_options(
+            Vec::new(),
+            McapWriteOptions {
+                chunk_size: Some(150),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let schema = mcap.add_schema("Value", "jsonschema", b"{}").unwrap();
+        let a = mcap
+            .add_channel(schema, "/a", "json", &HashMap::new())
+            .unwrap();
+        let b = mcap.add_channel(0, "/b", "", &HashMap::new()).unwrap();
+
+        // two interleaved streams, written one after the other
+        let written = (0..20).map(|i| i * 20).chain((0..20).map(|i| i * 20 + 10));
+        for (i, log_time) in written.clone().enumerate() {
+            let channel = if i < 20 { a } else { b };
+            mcap.write_message(channel, i as u32, log_time, 0, b"{}")
+                .unwrap();
+        }
+        let data = mcap.finish().unwrap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        assert_eq!(log_times(mcap.messages()), written.collect::<Vec<_>>());
+        assert_eq!(
+            log_times(mcap.messages().ordered()),
+            (0..40).map(|i| i * 10).collect::<Vec<_>>()
+        );
+
+        let first = mcap.messages().next().unwrap().unwrap();
+        assert_eq!(first.channel.topic(), "/a");
+        assert_eq!(first.schema.unwrap().name(), "Value");
+        let last = mcap.messages().last().unwrap().unwrap();
+        assert_eq!(last.channel.topic(), "/b");
+        assert!(last.schema.is_none());
+    }
+
+    #[test]
+    fn compressed_chunks_are_read() {
+        let mut records = Vec::new();
+        Record::from(Channel {
+            id: 1,
+            schema_id: 0,
+            topic: "/a".into(),
+            message_encoding: "".into(),
+            metadata: HashMap::new(),
+        })
+        .encode(&mut records);
+        for log_time in [3, 1, 2] {
+            Record::from(Message {
+                channel_id: 1,
+                sequence: 0,
+                log_time,
+                publish_time: 0,
+                data: b"data"[..].into(),
+            })
+            .encode(&mut records);
+        }
+
+        let mut data = Vec::new();
+        Magic {}.encode(&mut data);
+        Record::from(Header {
+            profile: "".into(),
+            library: "".into(),
+        })
+        .encode(&mut data);
+        Record::from(Chunk {
+            message_start_time: 1,
+            message_end_time: 3,
+            uncompressed_size: records.len() as u64,
+            uncompressed_crc: 0,
+            compression: "zstd".into(),
+            records: zstd::bulk::compress(&records, 0).unwrap().into(),
+        })
+        .encode(&mut data);
+        Magic {}.encode(&mut data);
+
+        let (_, mcap) = MCap::parse(&data).unwrap();
+        assert_eq!(log_times(mcap.messages()), vec![3, 1, 2]);
+        assert_eq!(log_times(mcap.messages().ordered()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn unknown_channels_are_errors() {
+        let mut data = Vec::new();
+        Magic {}.encode(&mut data);
+        Record::from(Message {
+            channel_id: 7,
+            sequence: 0,
+            log_time: 0,
+            publish_time: 0,
+            data: b""[..].into(),
+        })
+        .encode(&mut data);
+        Magic {}.encode(&mut data);
+
+        let (_, mcap) = MCap::parse(&data).unwrap();
+        let mut messages = mcap.messages();
+        assert!(matches!(
+            messages.next(),
+            Some(Err(MessageError::UnknownChannel(7)))
+        ));
+        assert!(messages.next().is_none());
+    }
+}
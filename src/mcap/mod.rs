@@ -1,19 +1,45 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{self, Read},
+};
 
 use nom::{
+    combinator::map,
+    error::ErrorKind,
     multi::length_data,
     number::complete::{le_u16, le_u32, le_u64, le_u8},
-    IResult, Parser, error::{ErrorKind, VerboseErrorKind},
+    IResult, Parser,
 };
 
+use serde::Serialize;
+
 use crate::mcap::parse_utils::parse_map;
 
 use self::parse_utils::{parse_array, parse_str, parse_tuple};
+use self::serialize_utils::{put_bytes32, put_bytes64, put_map, put_str};
+
+pub mod attachments;
+pub mod convert;
+pub mod decode;
+pub mod indexed;
+pub mod lint;
+pub mod messages;
+pub mod streaming;
+pub mod validate;
+pub mod writer;
+
+pub const MAGIC: &[u8] = b"\x89MCAP\x30\r\n";
 
 pub trait Parse<'a, T> {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], T>;
 }
 
+/// Serializes the content of a record, without its opcode and length.
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
 #[derive(Debug, Clone)]
 pub struct MCap<'a> {
     pub records: Vec<RawRecord<'a>>,
@@ -33,23 +59,9 @@ impl<'a> Parse<'a, MCap<'a>> for MCap<'a> {
 
 impl<'a> Parse<'a, Record<'a>> for Record<'a> {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], Record<'a>> {
-        nom::branch::alt((
-            nom::combinator::into(Header::parse),
-            nom::combinator::into(Footer::parse),
-            nom::combinator::into(Schema::parse),
-            nom::combinator::into(Channel::parse),
-            nom::combinator::into(Message::parse),
-            nom::combinator::into(Chunk::parse),
-            nom::combinator::into(MessageIndex::parse),
-            nom::combinator::into(ChunkIndex::parse),
-            nom::combinator::into(Attachment::parse),
-            nom::combinator::into(Metadata::parse),
-            nom::combinator::into(DataEnd::parse),
-            nom::combinator::into(AttachmentIndex::parse),
-            nom::combinator::into(MetadataIndex::parse),
-            nom::combinator::into(Statistics::parse),
-            nom::combinator::into(SummaryOffset::parse),
-        ))(input)
+        let (input, raw) = RawRecord::parse(input)?;
+
+        Ok((input, Record::try_from(raw)?))
     }
 }
 
@@ -73,30 +85,11 @@ impl<'a> TryFrom<RawRecord<'a>> for Record<'a> {
     type Error = nom::Err<nom::error::Error<&'a [u8]>>;
 
     fn try_from(value: RawRecord<'a>) -> Result<Self, Self::Error> {
-        let (_, record) = match value.tag {
-            0x01 => nom::combinator::map(Header::parse, Record::from)(value.content),
-            0x02 => nom::combinator::map(Footer::parse, Record::from)(value.content),
-            0x03 => nom::combinator::map(Schema::parse, Record::from)(value.content),
-            0x04 => nom::combinator::map(Channel::parse, Record::from)(value.content),
-            0x05 => nom::combinator::map(Message::parse, Record::from)(value.content),
-            0x06 => nom::combinator::map(Chunk::parse, Record::from)(value.content),
-            0x07 => nom::combinator::map(MessageIndex::parse, Record::from)(value.content),
-            0x08 => nom::combinator::map(ChunkIndex::parse, Record::from)(value.content),
-            0x09 => nom::combinator::map(Attachment::parse, Record::from)(value.content),
-            0x0C => nom::combinator::map(Metadata::parse, Record::from)(value.content),
-            0x0F => nom::combinator::map(DataEnd::parse, Record::from)(value.content),
-            0x0A => nom::combinator::map(AttachmentIndex::parse, Record::from)(value.content),
-            0x0D => nom::combinator::map(MetadataIndex::parse, Record::from)(value.content),
-            0x0B => nom::combinator::map(Statistics::parse, Record::from)(value.content),
-            0x0E => nom::combinator::map(SummaryOffset::parse, Record::from)(value.content),
-            _ => unreachable!(),
-        }?;
-
-        Ok(record)
+        Record::parse_content(value.tag, value.content)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Record<'a> {
     Magic(Magic),
     Header(Header<'a>),
@@ -114,9 +107,14 @@ pub enum Record<'a> {
     MetadataIndex(MetadataIndex<'a>),
     Statistics(Statistics),
     SummaryOffset(SummaryOffset),
+    /// A record with a reserved or custom opcode.
+    Unknown {
+        op: u8,
+        content: Cow<'a, [u8]>,
+    },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub enum RecordType {
     Magic,
     Header,
@@ -134,6 +132,7 @@ pub enum RecordType {
     MetadataIndex,
     Statistics,
     SummaryOffset,
+    Unknown,
 }
 
 impl<'a> From<&Record<'a>> for RecordType {
@@ -155,6 +154,7 @@ impl<'a> From<&Record<'a>> for RecordType {
             Record::MetadataIndex(_) => RecordType::MetadataIndex,
             Record::Statistics(_) => RecordType::Statistics,
             Record::SummaryOffset(_) => RecordType::SummaryOffset,
+            Record::Unknown { .. } => RecordType::Unknown,
         }
     }
 }
@@ -185,7 +185,72 @@ impl TryFrom<u8> for RecordType {
 }
 
 impl<'a> Record<'a> {
-    pub fn get_op(self) -> u8 {
+    /// Parses the content of a record with opcode `op`, which has to be consumed entirely.
+    /// Reserved and custom opcodes are returned as [`Record::Unknown`].
+    pub fn parse_content(
+        op: u8,
+        content: &'a [u8],
+    ) -> Result<Record<'a>, nom::Err<nom::error::Error<&'a [u8]>>> {
+        fn parse_all<'a, T: Parse<'a, T> + Into<Record<'a>>>(
+            content: &'a [u8],
+        ) -> IResult<&'a [u8], Record<'a>> {
+            nom::combinator::all_consuming(nom::combinator::map(T::parse, T::into))(content)
+        }
+
+        let (_, record) = match op {
+            0x01 => parse_all::<Header>(content),
+            0x02 => parse_all::<Footer>(content),
+            0x03 => parse_all::<Schema>(content),
+            0x04 => parse_all::<Channel>(content),
+            0x05 => parse_all::<Message>(content),
+            0x06 => parse_all::<Chunk>(content),
+            0x07 => parse_all::<MessageIndex>(content),
+            0x08 => parse_all::<ChunkIndex>(content),
+            0x09 => parse_all::<Attachment>(content),
+            0x0A => parse_all::<AttachmentIndex>(content),
+            0x0B => parse_all::<Statistics>(content),
+            0x0C => parse_all::<Metadata>(content),
+            0x0D => parse_all::<MetadataIndex>(content),
+            0x0E => parse_all::<SummaryOffset>(content),
+            0x0F => parse_all::<DataEnd>(content),
+            _ => {
+                return Ok(Record::Unknown {
+                    op,
+                    content: Cow::Borrowed(content),
+                })
+            }
+        }?;
+
+        Ok(record)
+    }
+
+    /// Copies any borrowed data, so the record can outlive the input it was parsed from.
+    pub fn into_owned(self) -> Record<'static> {
+        match self {
+            Record::Magic(record) => Record::Magic(record),
+            Record::Header(record) => Record::Header(record.into_owned()),
+            Record::Footer(record) => Record::Footer(record),
+            Record::Schema(record) => Record::Schema(record.into_owned()),
+            Record::Channel(record) => Record::Channel(record.into_owned()),
+            Record::Message(record) => Record::Message(record.into_owned()),
+            Record::Chunk(record) => Record::Chunk(record.into_owned()),
+            Record::MessageIndex(record) => Record::MessageIndex(record),
+            Record::ChunkIndex(record) => Record::ChunkIndex(record.into_owned()),
+            Record::Attachment(record) => Record::Attachment(record.into_owned()),
+            Record::Metadata(record) => Record::Metadata(record.into_owned()),
+            Record::DataEnd(record) => Record::DataEnd(record),
+            Record::AttachmentIndex(record) => Record::AttachmentIndex(record.into_owned()),
+            Record::MetadataIndex(record) => Record::MetadataIndex(record.into_owned()),
+            Record::Statistics(record) => Record::Statistics(record),
+            Record::SummaryOffset(record) => Record::SummaryOffset(record),
+            Record::Unknown { op, content } => Record::Unknown {
+                op,
+                content: Cow::Owned(content.into_owned()),
+            },
+        }
+    }
+
+    pub fn get_op(&self) -> u8 {
         match self {
             Record::Magic(_) => 0x89,
             Record::Header(_) => 0x01,
@@ -203,35 +268,51 @@ impl<'a> Record<'a> {
             Record::MetadataIndex(_) => 0x0D,
             Record::Statistics(_) => 0x0B,
             Record::SummaryOffset(_) => 0x0E,
+            Record::Unknown { op, .. } => *op,
         }
     }
-}
-
-macro_rules! create_empty_record {
-    ($sname:ident) => {
-        #[derive(Default, Debug, Clone, Copy)]
-        pub struct $sname {}
 
-        impl<'a> From<$sname> for Record<'a> {
-            fn from(value: $sname) -> Self {
-                Record::$sname(value)
+    /// Serializes the record with its opcode and length prefix, or just the magic bytes.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let content: &dyn Encode = match self {
+            Record::Magic(record) => return record.encode(buf),
+            Record::Header(record) => record,
+            Record::Footer(record) => record,
+            Record::Schema(record) => record,
+            Record::Channel(record) => record,
+            Record::Message(record) => record,
+            Record::Chunk(record) => record,
+            Record::MessageIndex(record) => record,
+            Record::ChunkIndex(record) => record,
+            Record::Attachment(record) => record,
+            Record::Metadata(record) => record,
+            Record::DataEnd(record) => record,
+            Record::AttachmentIndex(record) => record,
+            Record::MetadataIndex(record) => record,
+            Record::Statistics(record) => record,
+            Record::SummaryOffset(record) => record,
+            Record::Unknown { content, .. } => {
+                buf.push(self.get_op());
+                return put_bytes64(buf, content);
             }
-        }
+        };
 
-        impl<'a> Parse<'a, $sname> for $sname {
-            fn parse(input: &'a [u8]) -> IResult<&'a [u8], $sname> {
-                Ok((input, Default::default()))
-            }
-        }
-    };
+        buf.push(self.get_op());
+        let start = buf.len();
+        buf.extend(0u64.to_le_bytes());
+        content.encode(buf);
+
+        let len = (buf.len() - start - 8) as u64;
+        buf[start..start + 8].copy_from_slice(&len.to_le_bytes());
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
 pub struct Magic {}
 
 impl<'a> Parse<'a, Magic> for Magic {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], Magic> {
-        let (input, _) = nom::bytes::complete::tag(b"\x89MCAP\x30\r\n")(input)?;
+        let (input, _) = nom::bytes::complete::tag(MAGIC)(input)?;
 
         Ok((input, Default::default()))
     }
@@ -243,14 +324,37 @@ impl<'a> From<Magic> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Encode for Magic {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(MAGIC);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Header<'a> {
-    profile: &'a str,
-    library: &'a str,
+    profile: Cow<'a, str>,
+    library: Cow<'a, str>,
+}
+
+impl<'a> Header<'a> {
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn library(&self) -> &str {
+        &self.library
+    }
+
+    pub fn into_owned(self) -> Header<'static> {
+        Header {
+            profile: Cow::Owned(self.profile.into_owned()),
+            library: Cow::Owned(self.library.into_owned()),
+        }
+    }
 }
 
 impl<'a> Parse<'a, Header<'a>> for Header<'a> {
-    fn parse(input: &'a [u8]) -> IResult<&'a [u8], Header> {
+    fn parse(input: &'a [u8]) -> IResult<&'a [u8], Header<'a>> {
         let (input, profile) = parse_utils::parse_str(input)?;
         let (input, library) = parse_utils::parse_str(input)?;
 
@@ -264,13 +368,34 @@ impl<'a> From<Header<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl<'a> Encode for Header<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_str(buf, &self.profile);
+        put_str(buf, &self.library);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct Footer {
     summary_start: u64,
     summary_offset_start: u64,
     summary_crc: u32,
 }
 
+impl Footer {
+    pub fn summary_start(&self) -> u64 {
+        self.summary_start
+    }
+
+    pub fn summary_offset_start(&self) -> u64 {
+        self.summary_offset_start
+    }
+
+    pub fn summary_crc(&self) -> u32 {
+        self.summary_crc
+    }
+}
+
 impl<'a> Parse<'a, Footer> for Footer {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], Footer> {
         let (input, summary_start) = le_u64(input)?;
@@ -294,12 +419,47 @@ impl<'a> From<Footer> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Encode for Footer {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.summary_start.to_le_bytes());
+        buf.extend(self.summary_offset_start.to_le_bytes());
+        buf.extend(self.summary_crc.to_le_bytes());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Schema<'a> {
     id: u16,
-    name: &'a str,
-    encoding: &'a str,
-    data: &'a str,
+    name: Cow<'a, str>,
+    encoding: Cow<'a, str>,
+    data: Cow<'a, [u8]>,
+}
+
+impl<'a> Schema<'a> {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_owned(self) -> Schema<'static> {
+        Schema {
+            id: self.id,
+            name: Cow::Owned(self.name.into_owned()),
+            encoding: Cow::Owned(self.encoding.into_owned()),
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
 }
 
 impl<'a> Parse<'a, Schema<'a>> for Schema<'a> {
@@ -307,7 +467,7 @@ impl<'a> Parse<'a, Schema<'a>> for Schema<'a> {
         let (input, id) = le_u16(input)?;
         let (input, name) = parse_str(input)?;
         let (input, encoding) = parse_str(input)?;
-        let (input, data) = parse_str(input)?;
+        let (input, data) = map(length_data(le_u32), Cow::Borrowed)(input)?;
 
         Ok((
             input,
@@ -327,13 +487,54 @@ impl<'a> From<Schema<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for Schema<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.id.to_le_bytes());
+        put_str(buf, &self.name);
+        put_str(buf, &self.encoding);
+        put_bytes32(buf, &self.data);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Channel<'a> {
     id: u16,
     schema_id: u16,
-    topic: &'a str,
-    message_encoding: &'a str,
-    metadata: HashMap<&'a str, &'a str>,
+    topic: Cow<'a, str>,
+    message_encoding: Cow<'a, str>,
+    metadata: HashMap<Cow<'a, str>, Cow<'a, str>>,
+}
+
+impl<'a> Channel<'a> {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn schema_id(&self) -> u16 {
+        self.schema_id
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    pub fn message_encoding(&self) -> &str {
+        &self.message_encoding
+    }
+
+    pub fn metadata(&self) -> &HashMap<Cow<'a, str>, Cow<'a, str>> {
+        &self.metadata
+    }
+
+    pub fn into_owned(self) -> Channel<'static> {
+        Channel {
+            id: self.id,
+            schema_id: self.schema_id,
+            topic: Cow::Owned(self.topic.into_owned()),
+            message_encoding: Cow::Owned(self.message_encoding.into_owned()),
+            metadata: owned_map(self.metadata),
+        }
+    }
 }
 
 impl<'a> Parse<'a, Channel<'a>> for Channel<'a> {
@@ -364,26 +565,146 @@ impl<'a> From<Channel<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl<'a> Encode for Channel<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.id.to_le_bytes());
+        buf.extend(self.schema_id.to_le_bytes());
+        put_str(buf, &self.topic);
+        put_str(buf, &self.message_encoding);
+        put_map(
+            buf,
+            &self.metadata,
+            |buf, key| put_str(buf, key),
+            |buf, value| put_str(buf, value),
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Chunk<'a> {
     message_start_time: u64,
     message_end_time: u64,
     uncompressed_size: u64,
     uncompressed_crc: u32,
-    compression: &'a str,
-    records: &'a [u8],
+    compression: Cow<'a, str>,
+    records: Cow<'a, [u8]>,
 }
 
-impl <'a> Chunk<'a> {
-    pub fn parse_inner(&self) -> IResult<&'a [u8], Vec<RawRecord<'a>>> {
-        match self.compression {
-            "" => nom::multi::many0(RawRecord::parse)(self.records),
-            _ => Err(nom::Err::Failure(nom::error::Error::new(self.compression.as_bytes(), ErrorKind::NoneOf)))
+impl<'a> Chunk<'a> {
+    pub fn message_start_time(&self) -> u64 {
+        self.message_start_time
+    }
+
+    pub fn message_end_time(&self) -> u64 {
+        self.message_end_time
+    }
+
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    pub fn uncompressed_crc(&self) -> u32 {
+        self.uncompressed_crc
+    }
+
+    pub fn compression(&self) -> &str {
+        &self.compression
+    }
+
+    pub fn records(&self) -> &[u8] {
+        &self.records
+    }
+
+    pub fn into_owned(self) -> Chunk<'static> {
+        Chunk {
+            message_start_time: self.message_start_time,
+            message_end_time: self.message_end_time,
+            uncompressed_size: self.uncompressed_size,
+            uncompressed_crc: self.uncompressed_crc,
+            compression: Cow::Owned(self.compression.into_owned()),
+            records: Cow::Owned(self.records.into_owned()),
+        }
+    }
+
+    /// Parses the records of an uncompressed chunk. Compressed chunks fail, their records have to
+    /// be parsed from the buffer returned by [`Chunk::decompress`] with [`Chunk::parse_records`].
+    pub fn parse_inner(&self) -> IResult<&[u8], Vec<RawRecord<'_>>> {
+        match &*self.compression {
+            "" => Chunk::parse_records(&self.records),
+            _ => Err(nom::Err::Failure(nom::error::Error::new(
+                self.compression.as_bytes(),
+                ErrorKind::NoneOf,
+            ))),
+        }
+    }
+
+    /// Returns the records section, decompressed if needed. Uncompressed chunks are borrowed from
+    /// the input. Fails if the compression isn't supported or the data doesn't have
+    /// `uncompressed_size` bytes.
+    pub fn decompress(&self) -> io::Result<Cow<'_, [u8]>> {
+        let data = match &*self.compression {
+            "" => Cow::Borrowed(&*self.records),
+            "zstd" => Cow::Owned(read_limited(
+                zstd::stream::read::Decoder::new(&*self.records)?,
+                self.uncompressed_size,
+            )?),
+            "lz4" => Cow::Owned(read_limited(
+                lz4_flex::frame::FrameDecoder::new(&*self.records),
+                self.uncompressed_size,
+            )?),
+            compression => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported chunk compression {:?}", compression),
+                ))
+            }
+        };
+
+        if data.len() as u64 != self.uncompressed_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "chunk records are {} bytes, expected {}",
+                    data.len(),
+                    self.uncompressed_size
+                ),
+            ));
+        }
+
+        Ok(data)
+    }
+
+    /// Like [`Chunk::decompress`], but keeps uncompressed records borrowed from the input.
+    pub fn into_decompressed(self) -> io::Result<Cow<'a, [u8]>> {
+        let data = self.decompress()?;
+        if let (Cow::Borrowed(_), Cow::Borrowed(records)) = (&data, &self.records) {
+            return Ok(Cow::Borrowed(records));
         }
-        
+
+        Ok(Cow::Owned(data.into_owned()))
+    }
+
+    pub fn parse_records(data: &[u8]) -> IResult<&[u8], Vec<RawRecord<'_>>> {
+        nom::multi::many0(RawRecord::parse)(data)
     }
 }
 
+fn owned_map(
+    map: HashMap<Cow<'_, str>, Cow<'_, str>>,
+) -> HashMap<Cow<'static, str>, Cow<'static, str>> {
+    map.into_iter()
+        .map(|(key, value)| (Cow::Owned(key.into_owned()), Cow::Owned(value.into_owned())))
+        .collect()
+}
+
+/// Reads at most one byte more than `size`, so oversized data is detected without reading it all.
+fn read_limited(reader: impl io::Read, size: u64) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(size.saturating_add(1)).read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
 impl<'a> Parse<'a, Chunk<'a>> for Chunk<'a> {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], Chunk<'a>> {
         let (input, message_start_time) = le_u64(input)?;
@@ -391,7 +712,7 @@ impl<'a> Parse<'a, Chunk<'a>> for Chunk<'a> {
         let (input, uncompressed_size) = le_u64(input)?;
         let (input, uncompressed_crc) = le_u32(input)?;
         let (input, compression) = parse_str(input)?;
-        let (input, records) = length_data(le_u64)(input)?;
+        let (input, records) = map(length_data(le_u64), Cow::Borrowed)(input)?;
 
         Ok((
             input,
@@ -413,12 +734,33 @@ impl<'a> From<Chunk<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for Chunk<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.message_start_time.to_le_bytes());
+        buf.extend(self.message_end_time.to_le_bytes());
+        buf.extend(self.uncompressed_size.to_le_bytes());
+        buf.extend(self.uncompressed_crc.to_le_bytes());
+        put_str(buf, &self.compression);
+        put_bytes64(buf, &self.records);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MessageIndex {
     channel_id: u16,
     records: Vec<(u64, u64)>,
 }
 
+impl MessageIndex {
+    pub fn channel_id(&self) -> u16 {
+        self.channel_id
+    }
+
+    pub fn records(&self) -> &[(u64, u64)] {
+        &self.records
+    }
+}
+
 impl<'a> Parse<'a, MessageIndex> for MessageIndex {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], MessageIndex> {
         let (input, channel_id) = le_u16(input)?;
@@ -440,7 +782,18 @@ impl<'a> From<MessageIndex> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Encode for MessageIndex {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.channel_id.to_le_bytes());
+        buf.extend((self.records.len() as u32 * 16).to_le_bytes());
+        for (log_time, offset) in &self.records {
+            buf.extend(log_time.to_le_bytes());
+            buf.extend(offset.to_le_bytes());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Statistics {
     message_count: u64,
     schema_count: u16,
@@ -453,6 +806,44 @@ pub struct Statistics {
     channel_message_counts: HashMap<u16, u64>,
 }
 
+impl Statistics {
+    pub fn message_count(&self) -> u64 {
+        self.message_count
+    }
+
+    pub fn schema_count(&self) -> u16 {
+        self.schema_count
+    }
+
+    pub fn channel_count(&self) -> u32 {
+        self.channel_count
+    }
+
+    pub fn attachment_count(&self) -> u32 {
+        self.attachment_count
+    }
+
+    pub fn metadata_count(&self) -> u32 {
+        self.metadata_count
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        self.chunk_count
+    }
+
+    pub fn message_start_time(&self) -> u64 {
+        self.message_start_time
+    }
+
+    pub fn message_end_time(&self) -> u64 {
+        self.message_end_time
+    }
+
+    pub fn channel_message_counts(&self) -> &HashMap<u16, u64> {
+        &self.channel_message_counts
+    }
+}
+
 impl<'a> Parse<'a, Statistics> for Statistics {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], Statistics> {
         let (input, message_count) = le_u64(input)?;
@@ -489,11 +880,36 @@ impl<'a> From<Statistics> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Encode for Statistics {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.message_count.to_le_bytes());
+        buf.extend(self.schema_count.to_le_bytes());
+        buf.extend(self.channel_count.to_le_bytes());
+        buf.extend(self.attachment_count.to_le_bytes());
+        buf.extend(self.metadata_count.to_le_bytes());
+        buf.extend(self.chunk_count.to_le_bytes());
+        buf.extend(self.message_start_time.to_le_bytes());
+        buf.extend(self.message_end_time.to_le_bytes());
+        put_map(
+            buf,
+            &self.channel_message_counts,
+            |buf, key| buf.extend(key.to_le_bytes()),
+            |buf, value| buf.extend(value.to_le_bytes()),
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct DataEnd {
     data_section_crc: u32,
 }
 
+impl DataEnd {
+    pub fn data_section_crc(&self) -> u32 {
+        self.data_section_crc
+    }
+}
+
 impl<'a> Parse<'a, DataEnd> for DataEnd {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], DataEnd> {
         let (input, data_section_crc) = le_u32(input)?;
@@ -508,7 +924,13 @@ impl<'a> From<DataEnd> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Encode for DataEnd {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.data_section_crc.to_le_bytes());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ChunkIndex<'a> {
     message_start_time: u64,
     message_end_time: u64,
@@ -516,11 +938,63 @@ pub struct ChunkIndex<'a> {
     chunk_length: u64,
     message_index_offsets: HashMap<u16, u64>,
     message_index_length: u64,
-    compression: &'a str,
+    compression: Cow<'a, str>,
     compressed_size: u64,
     uncompressed_size: u64,
 }
 
+impl<'a> ChunkIndex<'a> {
+    pub fn message_start_time(&self) -> u64 {
+        self.message_start_time
+    }
+
+    pub fn message_end_time(&self) -> u64 {
+        self.message_end_time
+    }
+
+    pub fn chunk_start_offset(&self) -> u64 {
+        self.chunk_start_offset
+    }
+
+    pub fn chunk_length(&self) -> u64 {
+        self.chunk_length
+    }
+
+    pub fn message_index_offsets(&self) -> &HashMap<u16, u64> {
+        &self.message_index_offsets
+    }
+
+    pub fn message_index_length(&self) -> u64 {
+        self.message_index_length
+    }
+
+    pub fn compression(&self) -> &str {
+        &self.compression
+    }
+
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    pub fn into_owned(self) -> ChunkIndex<'static> {
+        ChunkIndex {
+            message_start_time: self.message_start_time,
+            message_end_time: self.message_end_time,
+            chunk_start_offset: self.chunk_start_offset,
+            chunk_length: self.chunk_length,
+            message_index_offsets: self.message_index_offsets,
+            message_index_length: self.message_index_length,
+            compression: Cow::Owned(self.compression.into_owned()),
+            compressed_size: self.compressed_size,
+            uncompressed_size: self.uncompressed_size,
+        }
+    }
+}
+
 impl<'a> Parse<'a, ChunkIndex<'a>> for ChunkIndex<'a> {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], ChunkIndex<'a>> {
         let (input, message_start_time) = le_u64(input)?;
@@ -557,13 +1031,46 @@ impl<'a> From<ChunkIndex<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for ChunkIndex<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.message_start_time.to_le_bytes());
+        buf.extend(self.message_end_time.to_le_bytes());
+        buf.extend(self.chunk_start_offset.to_le_bytes());
+        buf.extend(self.chunk_length.to_le_bytes());
+        put_map(
+            buf,
+            &self.message_index_offsets,
+            |buf, key| buf.extend(key.to_le_bytes()),
+            |buf, value| buf.extend(value.to_le_bytes()),
+        );
+        buf.extend(self.message_index_length.to_le_bytes());
+        put_str(buf, &self.compression);
+        buf.extend(self.compressed_size.to_le_bytes());
+        buf.extend(self.uncompressed_size.to_le_bytes());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct SummaryOffset {
     group_opcode: u8,
     group_start: u64,
     group_length: u64,
 }
 
+impl SummaryOffset {
+    pub fn group_opcode(&self) -> u8 {
+        self.group_opcode
+    }
+
+    pub fn group_start(&self) -> u64 {
+        self.group_start
+    }
+
+    pub fn group_length(&self) -> u64 {
+        self.group_length
+    }
+}
+
 impl<'a> Parse<'a, SummaryOffset> for SummaryOffset {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], SummaryOffset> {
         let (input, group_opcode) = le_u8(input)?;
@@ -587,23 +1094,81 @@ impl<'a> From<SummaryOffset> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Encode for SummaryOffset {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.group_opcode);
+        buf.extend(self.group_start.to_le_bytes());
+        buf.extend(self.group_length.to_le_bytes());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Attachment<'a> {
     log_time: u64,
     create_time: u64,
-    name: &'a str,
-    media_type: &'a str,
-    data: &'a [u8],
+    name: Cow<'a, str>,
+    media_type: Cow<'a, str>,
+    data: Cow<'a, [u8]>,
     crc: u32,
 }
 
+impl<'a> Attachment<'a> {
+    pub fn log_time(&self) -> u64 {
+        self.log_time
+    }
+
+    pub fn create_time(&self) -> u64 {
+        self.create_time
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Computes the CRC of the fields before `crc`, as stored when the attachment was written.
+    pub fn computed_crc(&self) -> u32 {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+
+        crc32fast::hash(&buf[..buf.len() - 4])
+    }
+
+    /// Whether the stored CRC matches the attachment. A CRC of zero isn't set and always matches.
+    pub fn verify_crc(&self) -> bool {
+        self.crc == 0 || self.crc == self.computed_crc()
+    }
+
+    pub fn into_owned(self) -> Attachment<'static> {
+        Attachment {
+            log_time: self.log_time,
+            create_time: self.create_time,
+            name: Cow::Owned(self.name.into_owned()),
+            media_type: Cow::Owned(self.media_type.into_owned()),
+            data: Cow::Owned(self.data.into_owned()),
+            crc: self.crc,
+        }
+    }
+}
+
 impl<'a> Parse<'a, Attachment<'a>> for Attachment<'a> {
     fn parse(input: &'a [u8]) -> IResult<&'a [u8], Attachment<'a>> {
         let (input, log_time) = le_u64(input)?;
         let (input, create_time) = le_u64(input)?;
         let (input, name) = parse_str(input)?;
         let (input, media_type) = parse_str(input)?;
-        let (input, data) = length_data(le_u64)(input)?;
+        let (input, data) = map(length_data(le_u64), Cow::Borrowed)(input)?;
         let (input, crc) = le_u32(input)?;
 
         Ok((
@@ -626,15 +1191,68 @@ impl<'a> From<Attachment<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for Attachment<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.log_time.to_le_bytes());
+        buf.extend(self.create_time.to_le_bytes());
+        put_str(buf, &self.name);
+        put_str(buf, &self.media_type);
+        put_bytes64(buf, &self.data);
+        buf.extend(self.crc.to_le_bytes());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct AttachmentIndex<'a> {
     offset: u64,
     length: u64,
     log_time: u64,
     create_time: u64,
     data_size: u64,
-    name: &'a str,
-    media_type: &'a str,
+    name: Cow<'a, str>,
+    media_type: Cow<'a, str>,
+}
+
+impl<'a> AttachmentIndex<'a> {
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn log_time(&self) -> u64 {
+        self.log_time
+    }
+
+    pub fn create_time(&self) -> u64 {
+        self.create_time
+    }
+
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    pub fn into_owned(self) -> AttachmentIndex<'static> {
+        AttachmentIndex {
+            offset: self.offset,
+            length: self.length,
+            log_time: self.log_time,
+            create_time: self.create_time,
+            data_size: self.data_size,
+            name: Cow::Owned(self.name.into_owned()),
+            media_type: Cow::Owned(self.media_type.into_owned()),
+        }
+    }
 }
 
 impl<'a> Parse<'a, AttachmentIndex<'a>> for AttachmentIndex<'a> {
@@ -668,13 +1286,57 @@ impl<'a> From<AttachmentIndex<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for AttachmentIndex<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.offset.to_le_bytes());
+        buf.extend(self.length.to_le_bytes());
+        buf.extend(self.log_time.to_le_bytes());
+        buf.extend(self.create_time.to_le_bytes());
+        buf.extend(self.data_size.to_le_bytes());
+        put_str(buf, &self.name);
+        put_str(buf, &self.media_type);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Message<'a> {
     channel_id: u16,
     sequence: u32,
     log_time: u64,
     publish_time: u64,
-    data: &'a [u8],
+    data: Cow<'a, [u8]>,
+}
+
+impl<'a> Message<'a> {
+    pub fn channel_id(&self) -> u16 {
+        self.channel_id
+    }
+
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    pub fn log_time(&self) -> u64 {
+        self.log_time
+    }
+
+    pub fn publish_time(&self) -> u64 {
+        self.publish_time
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_owned(self) -> Message<'static> {
+        Message {
+            channel_id: self.channel_id,
+            sequence: self.sequence,
+            log_time: self.log_time,
+            publish_time: self.publish_time,
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
 }
 
 impl<'a> Parse<'a, Message<'a>> for Message<'a> {
@@ -683,7 +1345,8 @@ impl<'a> Parse<'a, Message<'a>> for Message<'a> {
         let (input, sequence) = le_u32(input)?;
         let (input, log_time) = le_u64(input)?;
         let (input, publish_time) = le_u64(input)?;
-        let data = input;
+        let (input, data) = map(nom::combinator::rest, Cow::Borrowed)(input)?;
+
         Ok((
             input,
             Message {
@@ -703,10 +1366,37 @@ impl<'a> From<Message<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for Message<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.channel_id.to_le_bytes());
+        buf.extend(self.sequence.to_le_bytes());
+        buf.extend(self.log_time.to_le_bytes());
+        buf.extend(self.publish_time.to_le_bytes());
+        buf.extend_from_slice(&self.data);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Metadata<'a> {
-    name: &'a str,
-    metadata: HashMap<&'a str, &'a str>,
+    name: Cow<'a, str>,
+    metadata: HashMap<Cow<'a, str>, Cow<'a, str>>,
+}
+
+impl<'a> Metadata<'a> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn metadata(&self) -> &HashMap<Cow<'a, str>, Cow<'a, str>> {
+        &self.metadata
+    }
+
+    pub fn into_owned(self) -> Metadata<'static> {
+        Metadata {
+            name: Cow::Owned(self.name.into_owned()),
+            metadata: owned_map(self.metadata),
+        }
+    }
 }
 
 impl<'a> Parse<'a, Metadata<'a>> for Metadata<'a> {
@@ -723,11 +1413,45 @@ impl<'a> From<Metadata<'a>> for Record<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+impl<'a> Encode for Metadata<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_str(buf, &self.name);
+        put_map(
+            buf,
+            &self.metadata,
+            |buf, key| put_str(buf, key),
+            |buf, value| put_str(buf, value),
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetadataIndex<'a> {
     offset: u64,
     length: u64,
-    name: &'a str,
+    name: Cow<'a, str>,
+}
+
+impl<'a> MetadataIndex<'a> {
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn into_owned(self) -> MetadataIndex<'static> {
+        MetadataIndex {
+            offset: self.offset,
+            length: self.length,
+            name: Cow::Owned(self.name.into_owned()),
+        }
+    }
 }
 
 impl<'a> Parse<'a, MetadataIndex<'a>> for MetadataIndex<'a> {
@@ -752,13 +1476,29 @@ impl<'a> From<MetadataIndex<'a>> for Record<'a> {
     }
 }
 
-mod parse_utils {
-    use std::{collections::HashMap, hash::Hash};
+impl<'a> Encode for MetadataIndex<'a> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.offset.to_le_bytes());
+        buf.extend(self.length.to_le_bytes());
+        put_str(buf, &self.name);
+    }
+}
 
-    use nom::{error::ParseError, multi::length_data, number::complete::le_u32, IResult, Parser};
+mod parse_utils {
+    use std::{borrow::Cow, collections::HashMap, hash::Hash};
+
+    use nom::{
+        combinator::all_consuming,
+        error::ParseError,
+        multi::{length_data, many0},
+        number::complete::le_u32,
+        IResult, Parser,
+    };
 
-    pub fn parse_str(input: &[u8]) -> IResult<&[u8], &str> {
-        nom::combinator::map_res(length_data(le_u32), |slice| std::str::from_utf8(slice))(input)
+    pub fn parse_str(input: &[u8]) -> IResult<&[u8], Cow<'_, str>> {
+        nom::combinator::map_res(length_data(le_u32), |slice| {
+            std::str::from_utf8(slice).map(Cow::Borrowed)
+        })(input)
     }
 
     pub struct MapParser<'a, 'b, K, V, E>
@@ -829,6 +1569,149 @@ mod parse_utils {
     where
         F: Parser<&'a [u8], O, E>,
     {
-        length_data(le_u32).and_then(nom::multi::many0(parser))
+        length_data(le_u32).and_then(all_consuming(many0(parser)))
+    }
+}
+
+mod serialize_utils {
+    use std::collections::HashMap;
+
+    pub fn put_str(buf: &mut Vec<u8>, value: &str) {
+        put_bytes32(buf, value.as_bytes());
+    }
+
+    pub fn put_bytes32(buf: &mut Vec<u8>, value: &[u8]) {
+        buf.extend((value.len() as u32).to_le_bytes());
+        buf.extend_from_slice(value);
+    }
+
+    pub fn put_bytes64(buf: &mut Vec<u8>, value: &[u8]) {
+        buf.extend((value.len() as u64).to_le_bytes());
+        buf.extend_from_slice(value);
+    }
+
+    /// Writes a length prefixed map, sorted by key so the output is deterministic.
+    pub fn put_map<K: Ord, V>(
+        buf: &mut Vec<u8>,
+        map: &HashMap<K, V>,
+        mut put_key: impl FnMut(&mut Vec<u8>, &K),
+        mut put_value: impl FnMut(&mut Vec<u8>, &V),
+    ) {
+        let mut entries = map.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let start = buf.len();
+        buf.extend(0u32.to_le_bytes());
+        for (key, value) in entries {
+            put_key(buf, key);
+            put_value(buf, value);
+        }
+
+        let len = (buf.len() - start - 4) as u32;
+        buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn compressed_chunks_decompress() {
+        let mut records = Vec::new();
+        Record::from(Message {
+            channel_id: 1,
+            sequence: 2,
+            log_time: 3,
+            publish_time: 4,
+            data: b"payload".into(),
+        })
+        .encode(&mut records);
+
+        let mut lz4 = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        lz4.write_all(&records).unwrap();
+        let compressed = [
+            ("", records.clone()),
+            ("zstd", zstd::bulk::compress(&records, 0).unwrap()),
+            ("lz4", lz4.finish().unwrap()),
+        ];
+
+        for (compression, data) in &compressed {
+            let chunk = Chunk {
+                message_start_time: 3,
+                message_end_time: 3,
+                uncompressed_size: records.len() as u64,
+                uncompressed_crc: crc32fast::hash(&records),
+                compression: (*compression).into(),
+                records: data.into(),
+            };
+
+            let decompressed = chunk.decompress().unwrap();
+            assert_eq!(*decompressed, records[..]);
+            let (_, parsed) = Chunk::parse_records(&decompressed).unwrap();
+            assert!(matches!(
+                Record::try_from(parsed[0]),
+                Ok(Record::Message(Message { sequence: 2, .. }))
+            ));
+
+            let wrong_size = Chunk {
+                uncompressed_size: records.len() as u64 - 1,
+                ..chunk
+            };
+            assert!(wrong_size.decompress().is_err());
+        }
+
+        let unsupported = Chunk {
+            message_start_time: 3,
+            message_end_time: 3,
+            uncompressed_size: records.len() as u64,
+            uncompressed_crc: 0,
+            compression: "bz2".into(),
+            records: records.as_slice().into(),
+        };
+        assert!(unsupported.decompress().is_err());
+    }
+
+    #[test]
+    fn trailing_record_content_is_rejected() {
+        let mut buf = Vec::new();
+        Record::from(DataEnd {
+            data_section_crc: 1,
+        })
+        .encode(&mut buf);
+        buf.push(0);
+        buf[1] += 1;
+
+        assert!(Record::parse(&buf).is_err());
+        assert!(matches!(
+            Record::parse_content(0x10, &[1, 2]),
+            Ok(Record::Unknown { op: 0x10, .. })
+        ));
+    }
+
+    #[test]
+    fn records_serialize() {
+        let channel = Record::Channel(Channel {
+            id: 1,
+            schema_id: 2,
+            topic: "/t".into(),
+            message_encoding: "json".into(),
+            metadata: HashMap::from([("unit".into(), "m".into())]),
+        });
+
+        assert_eq!(
+            serde_json::to_value(&channel).unwrap(),
+            serde_json::json!({
+                "Channel": {
+                    "id": 1,
+                    "schema_id": 2,
+                    "topic": "/t",
+                    "message_encoding": "json",
+                    "metadata": { "unit": "m" },
+                }
+            })
+        );
     }
 }
@@ -0,0 +1,309 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+    sync::Arc,
+};
+
+use super::{
+    messages::{Definitions, MessageError, MessageEvent},
+    Attachment, Channel, Chunk, Metadata, Record, Schema, MAGIC,
+};
+
+/// Length of a record's opcode and length prefix.
+const RECORD_PREFIX_LEN: usize = 1 + 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum McapEvent {
+    /// A schema that wasn't seen before, or that changed.
+    Schema(Arc<Schema<'static>>),
+    /// A channel that wasn't seen before, or that changed.
+    Channel(Arc<Channel<'static>>),
+    Message(MessageEvent<'static>),
+    Attachment(Attachment<'static>),
+    Metadata(Metadata<'static>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Magic,
+    Records,
+    /// The footer was read, only the closing magic is left.
+    ClosingMagic,
+    Done,
+}
+
+/// Reads an MCAP file record by record from any [`Read`], decompressing chunks as they're
+/// reached.
+///
+/// Only the current record and the events of the current chunk are held in memory. Reaching the
+/// end of the input before the end of the file isn't an error: [`StreamingMcapReader::next_event`]
+/// returns `Ok(None)` and keeps any partial record, so reading can resume once a still-growing
+/// file has been written to.
+pub struct StreamingMcapReader<R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    state: State,
+    definitions: Definitions<'static>,
+    pending: VecDeque<McapEvent>,
+}
+
+impl<R: Read> StreamingMcapReader<R> {
+    pub fn new(reader: R) -> Self {
+        StreamingMcapReader {
+            reader,
+            buf: Vec::new(),
+            state: State::Magic,
+            definitions: Definitions::default(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Whether the closing magic has been read.
+    pub fn is_finished(&self) -> bool {
+        self.state == State::Done && self.pending.is_empty()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads up to the next event. Returns `Ok(None)` at the end of the file, or when the input
+    /// doesn't hold a complete record yet.
+    pub fn next_event(&mut self) -> Result<Option<McapEvent>, MessageError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            match self.state {
+                State::Magic | State::ClosingMagic => {
+                    if !self.fill(MAGIC.len())? {
+                        return Ok(None);
+                    }
+                    if self.buf != MAGIC {
+                        return Err(MessageError::Parse(nom::error::ErrorKind::Tag));
+                    }
+
+                    self.buf.clear();
+                    self.state = match self.state {
+                        State::Magic => State::Records,
+                        _ => State::Done,
+                    };
+                }
+                State::Records => {
+                    if !self.fill(RECORD_PREFIX_LEN)? {
+                        return Ok(None);
+                    }
+                    let len =
+                        u64::from_le_bytes(self.buf[1..RECORD_PREFIX_LEN].try_into().unwrap());
+                    let len = usize::try_from(len)
+                        .ok()
+                        .and_then(|len| len.checked_add(RECORD_PREFIX_LEN))
+                        .ok_or(MessageError::Parse(nom::error::ErrorKind::TooLarge))?;
+                    if !self.fill(len)? {
+                        return Ok(None);
+                    }
+
+                    let buf = std::mem::take(&mut self.buf);
+                    let record = Record::parse_content(buf[0], &buf[RECORD_PREFIX_LEN..])?;
+                    self.add(record)?;
+
+                    // keep the allocation for the next record
+                    self.buf = buf;
+                    self.buf.clear();
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Reads until the buffer holds `len` bytes, or the input runs out.
+    fn fill(&mut self, len: usize) -> io::Result<bool> {
+        if self.buf.len() < len {
+            let missing = (len - self.buf.len()) as u64;
+            (&mut self.reader)
+                .take(missing)
+                .read_to_end(&mut self.buf)?;
+        }
+
+        Ok(self.buf.len() >= len)
+    }
+
+    fn add(&mut self, record: Record) -> Result<(), MessageError> {
+        match record {
+            Record::Schema(schema) => {
+                if let Some(schema) = self.definitions.add_schema(schema.into_owned()) {
+                    self.pending.push_back(McapEvent::Schema(schema));
+                }
+            }
+            Record::Channel(channel) => {
+                if let Some(channel) = self.definitions.add_channel(channel.into_owned())? {
+                    self.pending.push_back(McapEvent::Channel(channel));
+                }
+            }
+            Record::Message(message) => {
+                let message = self.definitions.resolve(message.into_owned())?;
+                self.pending.push_back(McapEvent::Message(message));
+            }
+            Record::Attachment(attachment) => {
+                self.pending
+                    .push_back(McapEvent::Attachment(attachment.into_owned()));
+            }
+            Record::Metadata(metadata) => {
+                self.pending
+                    .push_back(McapEvent::Metadata(metadata.into_owned()));
+            }
+            Record::Chunk(chunk) => {
+                let records = chunk.decompress()?;
+                for raw in Chunk::parse_records(&records)?.1 {
+                    self.add(Record::try_from(raw)?)?;
+                }
+            }
+            Record::Footer(_) => self.state = State::ClosingMagic,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for StreamingMcapReader<R> {
+    type Item = Result<McapEvent, MessageError>;
+
+    /// Like [`StreamingMcapReader::next_event`]. After an error the iterator ends, as the
+    /// position in the input is lost.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Ok(event) => event.map(Ok),
+            Err(err) => {
+                self.state = State::Done;
+                self.pending.clear();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::mcap::{
+        writer::{McapWriteOptions, McapWriter},
+        Encode, MCap, Magic, Parse,
+    };
+
+    fn write_sample() -> Vec<u8> {
+        let mut mcap = McapWriter::with_options(
+            Vec::new(),
+            McapWriteOptions {
+                chunk_size: Some(100),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let schema = mcap.add_schema("Value", "jsonschema", b"{}").unwrap();
+        let channel = mcap
+            .add_channel(schema, "/a", "json", &HashMap::new())
+            .unwrap();
+        for i in 0..10 {
+            mcap.write_message(channel, i, i as u64, i as u64, b"{}")
+                .unwrap();
+        }
+        mcap.write_attachment(1, 2, "a.txt", "text/plain", b"attachment")
+            .unwrap();
+        mcap.write_metadata("run", &HashMap::from([("team", "1234")]))
+            .unwrap();
+
+        mcap.finish().unwrap()
+    }
+
+    /// Rewrites every chunk compressed with zstd. The summary offsets are left stale.
+    fn compress_chunks(data: &[u8]) -> Vec<u8> {
+        let (_, mcap) = MCap::parse(data).unwrap();
+
+        let mut compressed = Vec::new();
+        Magic {}.encode(&mut compressed);
+        for raw in mcap.records {
+            match Record::try_from(raw).unwrap() {
+                Record::Chunk(chunk) => Record::from(Chunk {
+                    compression: "zstd".into(),
+                    records: zstd::bulk::compress(&chunk.records, 0).unwrap().into(),
+                    ..chunk
+                })
+                .encode(&mut compressed),
+                record => record.encode(&mut compressed),
+            }
+        }
+        Magic {}.encode(&mut compressed);
+
+        compressed
+    }
+
+    fn summarize(events: impl Iterator<Item = Result<McapEvent, MessageError>>) -> Vec<String> {
+        events
+            .map(|event| match event.unwrap() {
+                McapEvent::Schema(schema) => format!("schema {}", schema.name()),
+                McapEvent::Channel(channel) => format!("channel {}", channel.topic()),
+                McapEvent::Message(message) => format!("message {}", message.message.log_time()),
+                McapEvent::Attachment(attachment) => format!("attachment {}", attachment.name()),
+                McapEvent::Metadata(metadata) => format!("metadata {}", metadata.name()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_are_read_in_order() {
+        let data = write_sample();
+        for data in [data.clone(), compress_chunks(&data)] {
+            let mut reader = StreamingMcapReader::new(&data[..]);
+
+            let mut expected = vec!["schema Value".to_string(), "channel /a".to_string()];
+            expected.extend((0..10).map(|i| format!("message {}", i)));
+            expected.push("attachment a.txt".to_string());
+            expected.push("metadata run".to_string());
+
+            let mut events = summarize(reader.by_ref());
+            // the attachment and metadata are written before the last open chunk
+            events.sort_by_key(|event| {
+                event.starts_with("attachment") || event.starts_with("metadata")
+            });
+            assert_eq!(events, expected);
+            assert!(reader.is_finished());
+        }
+    }
+
+    #[test]
+    fn growing_input_is_resumed() {
+        let data = compress_chunks(&write_sample());
+        let complete = summarize(StreamingMcapReader::new(&data[..]));
+
+        let mut reader = StreamingMcapReader::new(io::Cursor::new(Vec::new()));
+        let mut events = Vec::new();
+        for piece in data.chunks(7) {
+            reader.get_mut().get_mut().extend_from_slice(piece);
+            while let Some(event) = reader.next_event().unwrap() {
+                events.push(event);
+            }
+        }
+
+        assert_eq!(summarize(events.into_iter().map(Ok)), complete);
+        assert!(reader.is_finished());
+    }
+
+    #[test]
+    fn bad_magic_is_an_error() {
+        let mut reader = StreamingMcapReader::new(&b"\x89MCAP\x31\r\n"[..]);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(MessageError::Parse(nom::error::ErrorKind::Tag)))
+        ));
+        assert!(reader.next().is_none());
+    }
+}
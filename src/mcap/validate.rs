@@ -0,0 +1,220 @@
+use std::{fmt, io};
+
+use super::{MCap, Parse, Record, MAGIC};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrcKind {
+    /// `uncompressed_crc` of a chunk, over its decompressed records.
+    Chunk,
+    /// `data_section_crc` of the data end record, over every byte before it.
+    DataSection,
+    /// `crc` of an attachment, over the fields before it.
+    Attachment,
+    /// `summary_crc` of the footer, over the summary section and the footer up to the crc.
+    Summary,
+}
+
+impl fmt::Display for CrcKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CrcKind::Chunk => "chunk",
+            CrcKind::DataSection => "data section",
+            CrcKind::Attachment => "attachment",
+            CrcKind::Summary => "summary",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A stored CRC that doesn't match the data, in the record at `offset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrcMismatch {
+    pub kind: CrcKind,
+    pub offset: u64,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+impl fmt::Display for CrcMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} crc mismatch in record at offset {}: expected {:#010x}, computed {:#010x}",
+            self.kind, self.offset, self.expected, self.actual
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum ValidateError {
+    Parse(nom::error::ErrorKind),
+    /// A chunk at `offset` whose records couldn't be decompressed.
+    Chunk {
+        offset: u64,
+        error: io::Error,
+    },
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateError::Parse(kind) => write!(f, "invalid MCAP file: {:?}", kind),
+            ValidateError::Chunk { offset, error } => {
+                write!(f, "chunk at offset {}: {}", offset, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidateError {}
+
+impl From<nom::Err<nom::error::Error<&[u8]>>> for ValidateError {
+    fn from(value: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        match value {
+            nom::Err::Incomplete(_) => ValidateError::Parse(nom::error::ErrorKind::Eof),
+            nom::Err::Error(err) | nom::Err::Failure(err) => ValidateError::Parse(err.code),
+        }
+    }
+}
+
+/// Checks every CRC stored in an MCAP file and returns the ones that don't match, in file order.
+/// CRCs of zero are not present and skipped.
+pub fn check_crcs(input: &[u8]) -> Result<Vec<CrcMismatch>, ValidateError> {
+    let (_, mcap) = MCap::parse(input)?;
+
+    let mut mismatches = Vec::new();
+    let mut check = |kind, offset, expected, data: &[u8]| {
+        if expected != 0 {
+            let actual = crc32fast::hash(data);
+            if actual != expected {
+                mismatches.push(CrcMismatch {
+                    kind,
+                    offset,
+                    expected,
+                    actual,
+                });
+            }
+        }
+    };
+
+    let mut offset = MAGIC.len() as u64;
+    for raw in mcap.records {
+        let length = 1 + 8 + raw.content.len() as u64;
+
+        match Record::try_from(raw)? {
+            Record::Chunk(chunk) => {
+                let records = chunk
+                    .decompress()
+                    .map_err(|error| ValidateError::Chunk { offset, error })?;
+                check(CrcKind::Chunk, offset, chunk.uncompressed_crc, &records);
+            }
+            Record::DataEnd(data_end) => check(
+                CrcKind::DataSection,
+                offset,
+                data_end.data_section_crc,
+                &input[..offset as usize],
+            ),
+            Record::Attachment(attachment) => check(
+                CrcKind::Attachment,
+                offset,
+                attachment.crc,
+                &raw.content[..raw.content.len() - 4],
+            ),
+            Record::Footer(footer) => {
+                let start = match footer.summary_start {
+                    0 => offset,
+                    start => start,
+                };
+                // the footer's crc is the last field of the record
+                let end = offset + length - 4;
+
+                match input.get(start as usize..end as usize) {
+                    Some(summary) => check(CrcKind::Summary, offset, footer.summary_crc, summary),
+                    None => check(CrcKind::Summary, offset, footer.summary_crc, &[]),
+                }
+            }
+            _ => {}
+        }
+
+        offset += length;
+    }
+
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::mcap::writer::McapWriter;
+
+    fn write_sample() -> Vec<u8> {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+
+        let channel = mcap.add_channel(0, "/a", "", &HashMap::new()).unwrap();
+        mcap.write_message(channel, 0, 1, 1, b"message").unwrap();
+        mcap.write_attachment(2, 3, "a.txt", "text/plain", b"attachment")
+            .unwrap();
+
+        mcap.finish().unwrap()
+    }
+
+    fn find(data: &[u8], needle: &[u8]) -> usize {
+        data.windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap()
+    }
+
+    #[test]
+    fn valid_file_has_no_mismatches() {
+        assert_eq!(check_crcs(&write_sample()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn corrupted_data_is_reported() {
+        let mut data = write_sample();
+        let message = find(&data, b"message");
+        data[message] = b'M';
+        let attachment = find(&data, b"attachment");
+        data[attachment] = b'A';
+
+        // the open chunk is only written after the attachment
+        let kinds = check_crcs(&data)
+            .unwrap()
+            .iter()
+            .map(|mismatch| mismatch.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![CrcKind::Attachment, CrcKind::Chunk, CrcKind::DataSection]
+        );
+    }
+
+    #[test]
+    fn zero_crcs_are_skipped() {
+        let mut data = write_sample();
+        let message = find(&data, b"message");
+        data[message] = b'M';
+
+        let (_, mcap) = MCap::parse(&data).unwrap();
+        let mut crcs = Vec::new();
+        let mut offset = MAGIC.len();
+        for raw in &mcap.records {
+            match Record::try_from(*raw).unwrap() {
+                // uncompressed_crc follows the start time, end time and size
+                Record::Chunk(_) => crcs.push(offset + 9 + 24),
+                Record::DataEnd(_) => crcs.push(offset + 9),
+                _ => {}
+            }
+            offset += 9 + raw.content.len();
+        }
+
+        for crc in crcs {
+            data[crc..crc + 4].fill(0);
+        }
+
+        assert_eq!(check_crcs(&data).unwrap(), vec![]);
+    }
+}
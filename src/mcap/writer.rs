@@ -0,0 +1,867 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+};
+
+use crc32fast::Hasher;
+
+use super::{
+    Attachment, AttachmentIndex, Channel, Chunk, ChunkIndex, DataEnd, Encode, Footer, Header,
+    Magic, Message, MessageIndex, Metadata, MetadataIndex, Record, Schema, Statistics,
+    SummaryOffset,
+};
+
+#[derive(Debug, Clone)]
+pub struct McapWriteOptions {
+    pub profile: String,
+    pub library: String,
+    /// Chunks are closed once their records take up this many bytes. Without a chunk size
+    /// records are written straight to the data section and no message indexes are written.
+    pub chunk_size: Option<usize>,
+}
+
+impl Default for McapWriteOptions {
+    fn default() -> Self {
+        Self {
+            profile: String::new(),
+            library: "wpilog-reader".to_string(),
+            chunk_size: Some(1024 * 1024),
+        }
+    }
+}
+
+/// Writes an indexed MCAP file.
+///
+/// Schemas, channels and messages are grouped into uncompressed chunks, each followed by the
+/// message indexes of its channels. Attachments and metadata go between chunks. `finish` writes
+/// the data end record, the summary section with the schemas, channels, statistics and the chunk,
+/// attachment and metadata indexes, the summary offsets and the footer.
+pub struct McapWriter<W: Write> {
+    writer: W,
+    options: McapWriteOptions,
+    position: u64,
+    data_crc: Hasher,
+
+    schemas: Vec<Vec<u8>>,
+    channels: Vec<Vec<u8>>,
+    chunk_indexes: Vec<Vec<u8>>,
+    attachment_indexes: Vec<Vec<u8>>,
+    metadata_indexes: Vec<Vec<u8>>,
+
+    chunk: Vec<u8>,
+    chunk_start_time: u64,
+    chunk_end_time: u64,
+    // channel id -> (log time, offset in the chunk) of each message in the chunk
+    message_indexes: BTreeMap<u16, Vec<(u64, u64)>>,
+
+    message_count: u64,
+    message_start_time: u64,
+    message_end_time: u64,
+    channel_message_counts: HashMap<u16, u64>,
+}
+
+impl<W: Write> McapWriter<W> {
+    pub fn new(writer: W) -> io::Result<Self> {
+        Self::with_options(writer, McapWriteOptions::default())
+    }
+
+    pub fn with_options(writer: W, options: McapWriteOptions) -> io::Result<Self> {
+        let mut mcap = McapWriter {
+            writer,
+            options,
+            position: 0,
+            data_crc: Hasher::new(),
+            schemas: Vec::new(),
+            channels: Vec::new(),
+            chunk_indexes: Vec::new(),
+            attachment_indexes: Vec::new(),
+            metadata_indexes: Vec::new(),
+            chunk: Vec::new(),
+            chunk_start_time: u64::MAX,
+            chunk_end_time: 0,
+            message_indexes: BTreeMap::new(),
+            message_count: 0,
+            message_start_time: u64::MAX,
+            message_end_time: 0,
+            channel_message_counts: HashMap::new(),
+        };
+
+        let mut header = Vec::new();
+        Magic {}.encode(&mut header);
+        Record::Header(Header {
+            profile: mcap.options.profile.as_str().into(),
+            library: mcap.options.library.as_str().into(),
+        })
+        .encode(&mut header);
+        mcap.write_data(&header)?;
+
+        Ok(mcap)
+    }
+
+    /// Adds a schema and returns its id, which starts at 1 as 0 means no schema.
+    pub fn add_schema(&mut self, name: &str, encoding: &str, data: &[u8]) -> io::Result<u16> {
+        let id = next_id(self.schemas.len() + 1, "schema")?;
+
+        let mut schema = Vec::new();
+        Record::Schema(Schema {
+            id,
+            name: name.into(),
+            encoding: encoding.into(),
+            data: data.into(),
+        })
+        .encode(&mut schema);
+
+        self.write_chunked(&schema)?;
+        self.schemas.push(schema);
+
+        Ok(id)
+    }
+
+    /// Adds a channel and returns its id.
+    pub fn add_channel(
+        &mut self,
+        schema_id: u16,
+        topic: &str,
+        message_encoding: &str,
+        metadata: &HashMap<&str, &str>,
+    ) -> io::Result<u16> {
+        let id = next_id(self.channels.len(), "channel")?;
+
+        let mut channel = Vec::new();
+        Record::Channel(Channel {
+            id,
+            schema_id,
+            topic: topic.into(),
+            message_encoding: message_encoding.into(),
+            metadata: borrowed_map(metadata),
+        })
+        .encode(&mut channel);
+
+        self.write_chunked(&channel)?;
+        self.channels.push(channel);
+
+        Ok(id)
+    }
+
+    pub fn write_message(
+        &mut self,
+        channel_id: u16,
+        sequence: u32,
+        log_time: u64,
+        publish_time: u64,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut message = Vec::with_capacity(31 + data.len());
+        Record::Message(Message {
+            channel_id,
+            sequence,
+            log_time,
+            publish_time,
+            data: data.into(),
+        })
+        .encode(&mut message);
+
+        if self.options.chunk_size.is_some() {
+            self.message_indexes
+                .entry(channel_id)
+                .or_default()
+                .push((log_time, self.chunk.len() as u64));
+
+            self.chunk_start_time = self.chunk_start_time.min(log_time);
+            self.chunk_end_time = self.chunk_end_time.max(log_time);
+        }
+
+        self.message_count += 1;
+        self.message_start_time = self.message_start_time.min(log_time);
+        self.message_end_time = self.message_end_time.max(log_time);
+        *self.channel_message_counts.entry(channel_id).or_default() += 1;
+
+        self.write_chunked(&message)
+    }
+
+    pub fn write_attachment(
+        &mut self,
+        log_time: u64,
+        create_time: u64,
+        name: &str,
+        media_type: &str,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let mut attachment = Attachment {
+            log_time,
+            create_time,
+            name: name.into(),
+            media_type: media_type.into(),
+            data: data.into(),
+            crc: 0,
+        };
+
+        // the crc covers every field before it
+        let mut content = Vec::new();
+        attachment.encode(&mut content);
+        attachment.crc = crc32fast::hash(&content[..content.len() - 4]);
+
+        let mut record = Vec::new();
+        Record::Attachment(attachment).encode(&mut record);
+
+        let offset = self.position;
+        self.write_data(&record)?;
+
+        let mut index = Vec::new();
+        Record::AttachmentIndex(AttachmentIndex {
+            offset,
+            length: record.len() as u64,
+            log_time,
+            create_time,
+            data_size: data.len() as u64,
+            name: name.into(),
+            media_type: media_type.into(),
+        })
+        .encode(&mut index);
+        self.attachment_indexes.push(index);
+
+        Ok(())
+    }
+
+    pub fn write_metadata(&mut self, name: &str, metadata: &HashMap<&str, &str>) -> io::Result<()> {
+        let mut record = Vec::new();
+        Record::Metadata(Metadata {
+            name: name.into(),
+            metadata: borrowed_map(metadata),
+        })
+        .encode(&mut record);
+
+        let offset = self.position;
+        self.write_data(&record)?;
+
+        let mut index = Vec::new();
+        Record::MetadataIndex(MetadataIndex {
+            offset,
+            length: record.len() as u64,
+            name: name.into(),
+        })
+        .encode(&mut index);
+        self.metadata_indexes.push(index);
+
+        Ok(())
+    }
+
+    /// Closes the last chunk, writes the summary section and footer, and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_chunk()?;
+
+        let mut data_end = Vec::new();
+        Record::DataEnd(DataEnd {
+            data_section_crc: self.data_crc.clone().finalize(),
+        })
+        .encode(&mut data_end);
+        self.write_data(&data_end)?;
+
+        let (message_start_time, message_end_time) = if self.message_count > 0 {
+            (self.message_start_time, self.message_end_time)
+        } else {
+            (0, 0)
+        };
+
+        let mut statistics = Vec::new();
+        Record::Statistics(Statistics {
+            message_count: self.message_count,
+            schema_count: self.schemas.len() as u16,
+            channel_count: self.channels.len() as u32,
+            attachment_count: self.attachment_indexes.len() as u32,
+            metadata_count: self.metadata_indexes.len() as u32,
+            chunk_count: self.chunk_indexes.len() as u32,
+            message_start_time,
+            message_end_time,
+            channel_message_counts: std::mem::take(&mut self.channel_message_counts),
+        })
+        .encode(&mut statistics);
+
+        let summary_start = self.position;
+        let mut summary = Vec::new();
+        let mut offsets = Vec::new();
+
+        let groups = [
+            (0x03, self.schemas),
+            (0x04, self.channels),
+            (0x0B, vec![statistics]),
+            (0x08, self.chunk_indexes),
+            (0x0A, self.attachment_indexes),
+            (0x0D, self.metadata_indexes),
+        ];
+
+        for (group_opcode, records) in groups {
+            if records.is_empty() {
+                continue;
+            }
+
+            let group_start = summary_start + summary.len() as u64;
+            for record in &records {
+                summary.extend(record);
+            }
+
+            Record::SummaryOffset(SummaryOffset {
+                group_opcode,
+                group_start,
+                group_length: summary_start + summary.len() as u64 - group_start,
+            })
+            .encode(&mut offsets);
+        }
+
+        let summary_offset_start = summary_start + summary.len() as u64;
+        summary.extend(offsets);
+
+        let mut footer = Footer {
+            summary_start,
+            summary_offset_start,
+            summary_crc: 0,
+        };
+
+        // the summary crc covers the summary section and the footer up to the crc itself
+        Record::Footer(footer).encode(&mut summary);
+        footer.summary_crc = crc32fast::hash(&summary[..summary.len() - 4]);
+        summary.truncate(summary.len() - 4);
+        summary.extend(footer.summary_crc.to_le_bytes());
+        Magic {}.encode(&mut summary);
+
+        self.writer.write_all(&summary)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    /// Adds a record to the current chunk, or to the data section when not chunking.
+    fn write_chunked(&mut self, record: &[u8]) -> io::Result<()> {
+        match self.options.chunk_size {
+            Some(chunk_size) => {
+                self.chunk.extend(record);
+                if self.chunk.len() >= chunk_size {
+                    self.flush_chunk()?;
+                }
+
+                Ok(())
+            }
+            None => self.write_data(record),
+        }
+    }
+
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+
+        let (message_start_time, message_end_time) = if self.message_indexes.is_empty() {
+            (0, 0)
+        } else {
+            (self.chunk_start_time, self.chunk_end_time)
+        };
+
+        let mut chunk = Vec::with_capacity(49 + self.chunk.len());
+        Record::Chunk(Chunk {
+            message_start_time,
+            message_end_time,
+            uncompressed_size: self.chunk.len() as u64,
+            uncompressed_crc: crc32fast::hash(&self.chunk),
+            compression: "".into(),
+            records: self.chunk.as_slice().into(),
+        })
+        .encode(&mut chunk);
+
+        let chunk_start_offset = self.position;
+        self.write_data(&chunk)?;
+
+        let mut message_index_offsets = HashMap::new();
+        let message_index_start = self.position;
+        for (channel_id, records) in std::mem::take(&mut self.message_indexes) {
+            message_index_offsets.insert(channel_id, self.position);
+
+            let mut index = Vec::new();
+            Record::MessageIndex(MessageIndex {
+                channel_id,
+                records,
+            })
+            .encode(&mut index);
+            self.write_data(&index)?;
+        }
+
+        let mut chunk_index = Vec::new();
+        Record::ChunkIndex(ChunkIndex {
+            message_start_time,
+            message_end_time,
+            chunk_start_offset,
+            chunk_length: chunk.len() as u64,
+            message_index_offsets,
+            message_index_length: self.position - message_index_start,
+            compression: "".into(),
+            compressed_size: self.chunk.len() as u64,
+            uncompressed_size: self.chunk.len() as u64,
+        })
+        .encode(&mut chunk_index);
+        self.chunk_indexes.push(chunk_index);
+
+        self.chunk.clear();
+        self.chunk_start_time = u64::MAX;
+        self.chunk_end_time = 0;
+
+        Ok(())
+    }
+
+    /// Writes to the data section, keeping track of the position and data section crc.
+    fn write_data(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.data_crc.update(bytes);
+        self.position += bytes.len() as u64;
+
+        Ok(())
+    }
+}
+
+/// Converts a record count into the id of the next record, which has to fit in 16 bits.
+fn next_id(count: usize, kind: &str) -> io::Result<u16> {
+    u16::try_from(count).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("MCAP {} ids have to fit in 16 bits", kind),
+        )
+    })
+}
+
+fn borrowed_map<'a>(map: &HashMap<&'a str, &'a str>) -> HashMap<Cow<'a, str>, Cow<'a, str>> {
+    map.iter()
+        .map(|(key, value)| (Cow::Borrowed(*key), Cow::Borrowed(*value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcap::{MCap, Parse, RawRecord};
+
+    fn parse_records(data: &[u8]) -> Vec<(u64, Record<'_>)> {
+        let (rest, mcap) = MCap::parse(data).unwrap();
+        assert!(rest.is_empty());
+
+        let mut offset = 8;
+        mcap.records
+            .into_iter()
+            .map(|raw| {
+                let record_offset = offset;
+                offset += 9 + raw.content.len() as u64;
+                (record_offset, Record::try_from(raw).unwrap())
+            })
+            .collect()
+    }
+
+    fn write_sample(options: McapWriteOptions) -> Vec<u8> {
+        let mut mcap = McapWriter::with_options(Vec::new(), options).unwrap();
+
+        let pose = mcap.add_schema("Pose", "jsonschema", b"{}").unwrap();
+        let raw = mcap.add_schema("Raw", "", &[0, 159, 146, 150]).unwrap();
+        let a = mcap
+            .add_channel(pose, "/a", "json", &HashMap::from([("unit", "m")]))
+            .unwrap();
+        let b = mcap.add_channel(raw, "/b", "", &HashMap::new()).unwrap();
+        let c = mcap.add_channel(0, "/c", "", &HashMap::new()).unwrap();
+
+        for i in 0..30u32 {
+            let channel = [a, b, c][i as usize % 3];
+            let data = format!("{{\"i\":{}}}", i);
+            mcap.write_message(channel, i / 3, 1000 + i as u64 * 10, 0, data.as_bytes())
+                .unwrap();
+
+            if i == 10 {
+                mcap.write_attachment(5, 6, "calibration.json", "application/json", b"[1,2]")
+                    .unwrap();
+                mcap.write_metadata("run", &HashMap::from([("robot", "comp"), ("", "")]))
+                    .unwrap();
+            }
+        }
+
+        mcap.finish().unwrap()
+    }
+
+    #[test]
+    fn encoded_records_parse_back() {
+        let records: Vec<Record> = vec![
+            Header {
+                profile: "".into(),
+                library: "lib".into(),
+            }
+            .into(),
+            Footer {
+                summary_start: 1,
+                summary_offset_start: 2,
+                summary_crc: 3,
+            }
+            .into(),
+            Schema {
+                id: 1,
+                name: "s".into(),
+                encoding: "protobuf".into(),
+                data: [0, 255][..].into(),
+            }
+            .into(),
+            Channel {
+                id: 2,
+                schema_id: 1,
+                topic: "/t".into(),
+                message_encoding: "".into(),
+                metadata: HashMap::from([("a".into(), "b".into()), ("c".into(), "".into())]),
+            }
+            .into(),
+            Message {
+                channel_id: 2,
+                sequence: 3,
+                log_time: 4,
+                publish_time: 5,
+                data: b"payload".into(),
+            }
+            .into(),
+            Chunk {
+                message_start_time: 1,
+                message_end_time: 2,
+                uncompressed_size: 3,
+                uncompressed_crc: 4,
+                compression: "".into(),
+                records: [1, 2, 3][..].into(),
+            }
+            .into(),
+            MessageIndex {
+                channel_id: 1,
+                records: vec![(1, 2), (3, 4)],
+            }
+            .into(),
+            ChunkIndex {
+                message_start_time: 1,
+                message_end_time: 2,
+                chunk_start_offset: 3,
+                chunk_length: 4,
+                message_index_offsets: HashMap::from([(1, 5), (2, 6)]),
+                message_index_length: 7,
+                compression: "zstd".into(),
+                compressed_size: 8,
+                uncompressed_size: 9,
+            }
+            .into(),
+            Attachment {
+                log_time: 1,
+                create_time: 2,
+                name: "n".into(),
+                media_type: "text/plain".into(),
+                data: b"data".into(),
+                crc: 3,
+            }
+            .into(),
+            Metadata {
+                name: "m".into(),
+                metadata: HashMap::new(),
+            }
+            .into(),
+            DataEnd {
+                data_section_crc: 1,
+            }
+            .into(),
+            AttachmentIndex {
+                offset: 1,
+                length: 2,
+                log_time: 3,
+                create_time: 4,
+                data_size: 5,
+                name: "n".into(),
+                media_type: "".into(),
+            }
+            .into(),
+            MetadataIndex {
+                offset: 1,
+                length: 2,
+                name: "m".into(),
+            }
+            .into(),
+            Statistics {
+                message_count: 1,
+                schema_count: 2,
+                channel_count: 3,
+                attachment_count: 4,
+                metadata_count: 5,
+                chunk_count: 6,
+                message_start_time: 7,
+                message_end_time: 8,
+                channel_message_counts: HashMap::from([(1, 1)]),
+            }
+            .into(),
+            SummaryOffset {
+                group_opcode: 0x03,
+                group_start: 1,
+                group_length: 2,
+            }
+            .into(),
+            Record::Unknown {
+                op: 0x80,
+                content: b"custom".into(),
+            },
+        ];
+
+        for record in records {
+            let mut buf = Vec::new();
+            record.encode(&mut buf);
+
+            let (rest, raw) = RawRecord::parse(&buf).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(raw.tag, record.get_op());
+            assert_eq!(Record::try_from(raw).unwrap(), record);
+
+            let owned = {
+                let buf = buf.clone();
+                Record::try_from(RawRecord::parse(&buf).unwrap().1)
+                    .unwrap()
+                    .into_owned()
+            };
+            assert_eq!(owned, record);
+        }
+    }
+
+    #[test]
+    fn ids_have_to_fit_in_16_bits() {
+        let mut mcap = McapWriter::with_options(
+            Vec::new(),
+            McapWriteOptions {
+                chunk_size: None,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        for _ in 0..u16::MAX {
+            mcap.add_schema("", "", b"").unwrap();
+        }
+        let err = mcap.add_schema("", "", b"").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        for _ in 0..=u16::MAX {
+            mcap.add_channel(0, "", "", &HashMap::new()).unwrap();
+        }
+        let err = mcap.add_channel(0, "", "", &HashMap::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn attachment_crcs_are_verified() {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+        mcap.write_attachment(1, 2, "a.txt", "text/plain", b"attachment")
+            .unwrap();
+        let data = mcap.finish().unwrap();
+
+        let (_, mcap) = MCap::parse(&data).unwrap();
+        let attachment = mcap
+            .records
+            .iter()
+            .find_map(|raw| match Record::try_from(*raw) {
+                Ok(Record::Attachment(attachment)) => Some(attachment),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(attachment.computed_crc(), attachment.crc());
+        assert!(attachment.verify_crc());
+
+        let corrupted = Attachment {
+            data: b"Attachment".into(),
+            ..attachment.clone()
+        };
+        assert!(!corrupted.verify_crc());
+        assert!(Attachment {
+            crc: 0,
+            ..corrupted
+        }
+        .verify_crc());
+    }
+
+    #[test]
+    fn chunked_file_round_trips() {
+        let data = write_sample(McapWriteOptions {
+            chunk_size: Some(256),
+            ..Default::default()
+        });
+        let records = parse_records(&data);
+
+        assert!(matches!(
+            &records[0].1,
+            Record::Header(header) if header.profile() == "" && header.library() == "wpilog-reader"
+        ));
+
+        let mut messages = Vec::new();
+        let mut chunks = HashMap::new();
+        let mut data_end = None;
+        for (offset, record) in &records {
+            match record {
+                Record::Chunk(chunk) => {
+                    assert_eq!(chunk.uncompressed_size, chunk.records.len() as u64);
+                    assert_eq!(chunk.uncompressed_crc, crc32fast::hash(&chunk.records));
+                    chunks.insert(*offset, chunk.clone());
+
+                    let (_, inner) = chunk.parse_inner().unwrap();
+                    for raw in inner {
+                        if let Record::Message(message) = Record::try_from(raw).unwrap() {
+                            assert!(chunk.message_start_time <= message.log_time);
+                            assert!(message.log_time <= chunk.message_end_time);
+                            messages.push(message);
+                        }
+                    }
+                }
+                Record::Message(_) => panic!("message outside of a chunk"),
+                Record::DataEnd(end) => data_end = Some((*offset, end.data_section_crc)),
+                _ => {}
+            }
+        }
+
+        assert!(chunks.len() > 1);
+        assert_eq!(messages.len(), 30);
+        for (i, message) in messages.iter().enumerate() {
+            assert_eq!(message.sequence, i as u32 / 3);
+            assert_eq!(message.log_time, 1000 + i as u64 * 10);
+            assert_eq!(message.data, format!("{{\"i\":{}}}", i).as_bytes());
+        }
+
+        let (data_end_offset, data_section_crc) = data_end.unwrap();
+        assert_eq!(
+            data_section_crc,
+            crc32fast::hash(&data[..data_end_offset as usize])
+        );
+
+        let Some((_, Record::Footer(footer))) = records.last() else {
+            panic!("no footer");
+        };
+        let footer_offset = data.len() as u64 - 8 - 29;
+        assert_eq!(
+            footer.summary_crc,
+            crc32fast::hash(&data[footer.summary_start as usize..data.len() - 8 - 4])
+        );
+
+        let summary = records
+            .iter()
+            .filter(|(offset, _)| *offset >= footer.summary_start && *offset < footer_offset)
+            .collect::<Vec<_>>();
+
+        let mut chunk_indexes = 0;
+        let mut summary_offsets = 0;
+        for (_, record) in &summary {
+            match record {
+                Record::Statistics(statistics) => {
+                    assert_eq!(statistics.message_count, 30);
+                    assert_eq!(statistics.schema_count, 2);
+                    assert_eq!(statistics.channel_count, 3);
+                    assert_eq!(statistics.attachment_count, 1);
+                    assert_eq!(statistics.metadata_count, 1);
+                    assert_eq!(statistics.chunk_count as usize, chunks.len());
+                    assert_eq!(statistics.message_start_time, 1000);
+                    assert_eq!(statistics.message_end_time, 1290);
+                    assert_eq!(
+                        statistics.channel_message_counts,
+                        HashMap::from([(0, 10), (1, 10), (2, 10)])
+                    );
+                }
+                Record::ChunkIndex(index) => {
+                    chunk_indexes += 1;
+
+                    let chunk = &chunks[&index.chunk_start_offset];
+                    assert_eq!(index.message_start_time, chunk.message_start_time);
+                    assert_eq!(index.message_end_time, chunk.message_end_time);
+                    assert_eq!(index.chunk_length, 9 + 40 + chunk.records.len() as u64);
+
+                    for (channel_id, offset) in &index.message_index_offsets {
+                        let (_, raw) = RawRecord::parse(&data[*offset as usize..]).unwrap();
+                        let Record::MessageIndex(message_index) = Record::try_from(raw).unwrap()
+                        else {
+                            panic!("chunk index doesn't point at a message index");
+                        };
+                        assert_eq!(message_index.channel_id, *channel_id);
+
+                        for (log_time, offset) in message_index.records {
+                            let (_, raw) =
+                                RawRecord::parse(&chunk.records[offset as usize..]).unwrap();
+                            let Record::Message(message) = Record::try_from(raw).unwrap() else {
+                                panic!("message index doesn't point at a message");
+                            };
+                            assert_eq!(message.channel_id, *channel_id);
+                            assert_eq!(message.log_time, log_time);
+                        }
+                    }
+                }
+                Record::AttachmentIndex(index) => {
+                    let (_, raw) = RawRecord::parse(&data[index.offset as usize..]).unwrap();
+                    assert_eq!(9 + raw.content.len() as u64, index.length);
+                    let Record::Attachment(attachment) = Record::try_from(raw).unwrap() else {
+                        panic!("attachment index doesn't point at an attachment");
+                    };
+                    assert_eq!(attachment.name, index.name);
+                    assert_eq!(attachment.data(), b"[1,2]");
+                    assert_eq!(
+                        attachment.crc,
+                        crc32fast::hash(&raw.content[..raw.content.len() - 4])
+                    );
+                }
+                Record::MetadataIndex(index) => {
+                    let (_, raw) = RawRecord::parse(&data[index.offset as usize..]).unwrap();
+                    let Record::Metadata(metadata) = Record::try_from(raw).unwrap() else {
+                        panic!("metadata index doesn't point at metadata");
+                    };
+                    assert_eq!(metadata.name, "run");
+                    assert_eq!(
+                        metadata.metadata,
+                        HashMap::from([("robot".into(), "comp".into()), ("".into(), "".into())])
+                    );
+                }
+                Record::SummaryOffset(offset) => {
+                    summary_offsets += 1;
+                    assert!(offset.group_start >= footer.summary_start);
+                    assert!(
+                        offset.group_start + offset.group_length <= footer.summary_offset_start
+                    );
+
+                    let group = &data[offset.group_start as usize
+                        ..(offset.group_start + offset.group_length) as usize];
+                    let (rest, raws) = nom::multi::many1(RawRecord::parse)(group).unwrap();
+                    assert!(rest.is_empty());
+                    assert!(raws.iter().all(|raw| raw.tag == offset.group_opcode));
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(chunk_indexes, chunks.len());
+        assert_eq!(summary_offsets, 6);
+    }
+
+    #[test]
+    fn unchunked_file_round_trips() {
+        let data = write_sample(McapWriteOptions {
+            chunk_size: None,
+            ..Default::default()
+        });
+        let records = parse_records(&data);
+
+        let messages = records
+            .iter()
+            .filter_map(|(_, record)| match record {
+                Record::Message(message) => Some(message),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 30);
+        assert!(records
+            .iter()
+            .all(|(_, record)| !matches!(record, Record::Chunk(_) | Record::ChunkIndex(_))));
+    }
+
+    #[test]
+    fn empty_file_round_trips() {
+        let data = McapWriter::new(Vec::new()).unwrap().finish().unwrap();
+        let records = parse_records(&data);
+
+        assert!(matches!(records[0].1, Record::Header(_)));
+        assert!(matches!(records[1].1, Record::DataEnd(_)));
+        assert!(records
+            .iter()
+            .any(|(_, record)| matches!(record, Record::Statistics(_))));
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use serde::Serialize;
+
+use crate::{
+    filter::EntryFilter,
+    mcap::{
+        convert::channel_metadata,
+        decode::{DecodeError, DecoderRegistry},
+        messages::MessageError,
+        MCap,
+    },
+    wpilog::{
+        parser::parse_data,
+        structs::{StructRegistry, StructValue},
+        types::{DataType, MetadataEntry, Record, WpiLog},
+    },
+};
+
+#[derive(Debug)]
+pub enum SourceError {
+    Mcap(MessageError),
+    /// A message on `topic` that doesn't match its schema.
+    Decode {
+        topic: String,
+        error: DecodeError,
+    },
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Mcap(err) => write!(f, "{}", err),
+            SourceError::Decode { topic, error } => write!(f, "{}: {}", topic, error),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+impl From<MessageError> for SourceError {
+    fn from(value: MessageError) -> Self {
+        SourceError::Mcap(value)
+    }
+}
+
+/// An entry of a wpilog, or a channel of an MCAP file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub id: u32,
+    pub name: String,
+    pub typ: String,
+    pub metadata: String,
+}
+
+impl From<&MetadataEntry<'_>> for Entry {
+    fn from(entry: &MetadataEntry) -> Self {
+        Entry {
+            id: entry.entry_id,
+            name: entry.name.to_string(),
+            typ: entry.typ.to_string(),
+            metadata: entry.metadata.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Value<'a> {
+    Data(DataType<'a>),
+    Struct(StructValue),
+    /// The bytes of a message without a decoder for its encodings.
+    Raw(Vec<u8>),
+}
+
+/// A decoded value of an entry.
+#[derive(Debug, Clone)]
+pub struct Sample<'a> {
+    /// In nanoseconds, so MCAP log times keep their precision.
+    pub timestamp_ns: u64,
+    pub entry_id: u32,
+    pub value: Value<'a>,
+    /// The data as a wpilog stores it for the entry's type, if the source has it. Lets the
+    /// sample be copied to a wpilog without encoding its value again.
+    pub raw: Option<&'a [u8]>,
+}
+
+impl Sample<'_> {
+    pub fn timestamp_us(&self) -> u64 {
+        self.timestamp_ns / 1000
+    }
+}
+
+pub type Samples<'a> = Box<dyn Iterator<Item = Result<Sample<'a>, SourceError>> + 'a>;
+
+/// A log whose entries and decoded samples can be read the same way whatever its format, so
+/// tools only have to be written once.
+///
+/// The statistics, [event windows](crate::window), JSONL, wide and tidy CSV exports and
+/// [slicing](crate::wpilog::edit::slice_source) are written against it. Merging and the other
+/// exporters work on [`WpiLog`]s, which MCAP files can be converted to with
+/// [`convert_to_wpilog`](crate::mcap::convert::convert_to_wpilog).
+pub trait LogSource {
+    /// Every entry of the log, ordered by id.
+    fn entries(&self) -> Result<Vec<Entry>, SourceError>;
+
+    /// The samples of `entries` in timestamp order.
+    fn samples<'a>(&'a self, entries: &[Entry]) -> Samples<'a>;
+
+    /// The entries whose name passes `filter`.
+    fn filter_entries(&self, filter: &EntryFilter) -> Result<Vec<Entry>, SourceError> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|entry| filter.matches(&entry.name))
+            .collect())
+    }
+}
+
+/// Entries are the started entries of the log. Samples are decoded like the JSONL export does:
+/// `struct:` entries to structs when their schema is in the log, and data that doesn't match
+/// the entry type to [`DataType::Raw`].
+impl LogSource for WpiLog<'_> {
+    fn entries(&self) -> Result<Vec<Entry>, SourceError> {
+        let mut entries = self
+            .get_entry_metadata()
+            .iter()
+            .map(Entry::from)
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.id);
+
+        Ok(entries)
+    }
+
+    fn samples<'a>(&'a self, entries: &[Entry]) -> Samples<'a> {
+        let structs = StructRegistry::from_log(self);
+        let types = entries
+            .iter()
+            .map(|entry| (entry.id, entry.typ.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut records = self.records.iter().collect::<Vec<_>>();
+        records.sort_by_key(|record| record.timestamp_us);
+
+        Box::new(records.into_iter().filter_map(move |record| {
+            let Record::Data(data) = record.data else {
+                return None;
+            };
+            let typ = types.get(&record.entry_id)?;
+
+            let value = match structs.decode(typ, data) {
+                Some(value) => Value::Struct(value),
+                None => Value::Data(
+                    parse_data(typ, data)
+                        .map(|(_, value)| value)
+                        .unwrap_or(DataType::Raw(data)),
+                ),
+            };
+
+            Some(Ok(Sample {
+                timestamp_ns: record.timestamp_us * 1000,
+                entry_id: record.entry_id,
+                value,
+                raw: Some(data),
+            }))
+        }))
+    }
+}
+
+/// Entries are the channels, named after their topic, typed by their schema name (`raw` without
+/// one), with the same metadata as MCAP to wpilog conversion gives them. Samples are messages
+/// decoded by the default [`DecoderRegistry`], in log time order. Like in the conversion, `json`
+/// messages that are just `{"value": ...}` are that value. Messages without a decoder for their
+/// encodings are [`Value::Raw`].
+impl LogSource for MCap<'_> {
+    fn entries(&self) -> Result<Vec<Entry>, SourceError> {
+        Ok(self
+            .channels()?
+            .iter()
+            .map(|(channel, schema)| Entry {
+                id: channel.id() as u32,
+                name: channel.topic().to_string(),
+                typ: schema
+                    .as_ref()
+                    .map(|schema| schema.name())
+                    .unwrap_or("raw")
+                    .to_string(),
+                metadata: channel_metadata(channel, schema.as_ref()),
+            })
+            .collect())
+    }
+
+    fn samples<'a>(&'a self, entries: &[Entry]) -> Samples<'a> {
+        let mut decoders = DecoderRegistry::default();
+        let ids = entries.iter().map(|entry| entry.id).collect::<HashSet<_>>();
+
+        Box::new(self.messages().ordered().filter_map(move |message| {
+            let message = match message {
+                Ok(message) => message,
+                Err(err) => return Some(Err(err.into())),
+            };
+            let entry_id = message.message.channel_id() as u32;
+            if !ids.contains(&entry_id) {
+                return None;
+            }
+
+            let value = match decoders.decode(&message) {
+                Ok(StructValue::Struct(mut members))
+                    if message.channel.message_encoding() == "json"
+                        && members.len() == 1
+                        && members[0].0 == "value" =>
+                {
+                    Value::Struct(members.remove(0).1)
+                }
+                Ok(value) => Value::Struct(value),
+                Err(DecodeError::UnsupportedEncoding { .. }) => {
+                    Value::Raw(message.message.data().to_vec())
+                }
+                Err(error) => {
+                    return Some(Err(SourceError::Decode {
+                        topic: message.channel.topic().to_string(),
+                        error,
+                    }))
+                }
+            };
+
+            Some(Ok(Sample {
+                timestamp_ns: message.message.log_time(),
+                entry_id,
+                value,
+                raw: None,
+            }))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::mcap::export_mcap,
+        mcap::{writer::McapWriter, Parse},
+        wpilog::{parser::parse_wpilog, writer::WpiLogWriter},
+    };
+
+    fn write_sample() -> Vec<u8> {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let speed = log
+            .start("/speed", "double", "{\"unit\":\"m/s\"}", 1)
+            .unwrap();
+        let flag = log.start("/flag", "boolean", "", 1).unwrap();
+        log.append(speed, 3, &2.5f64.to_le_bytes()).unwrap();
+        log.append(flag, 2, &[1]).unwrap();
+        log.append(speed, 1, &1.5f64.to_le_bytes()).unwrap();
+        log.into_inner().unwrap()
+    }
+
+    #[test]
+    fn wpilog_samples_are_time_ordered() {
+        let data = write_sample();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let entries = log.entries().unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    id: 1,
+                    name: "/speed".to_string(),
+                    typ: "double".to_string(),
+                    metadata: "{\"unit\":\"m/s\"}".to_string(),
+                },
+                Entry {
+                    id: 2,
+                    name: "/flag".to_string(),
+                    typ: "boolean".to_string(),
+                    metadata: "".to_string(),
+                },
+            ]
+        );
+
+        let samples = log
+            .samples(&entries)
+            .map(|sample| {
+                let sample = sample.unwrap();
+                (
+                    sample.timestamp_us(),
+                    serde_json::to_string(&sample.value).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            samples,
+            vec![
+                (1, "1.5".to_string()),
+                (2, "true".to_string()),
+                (3, "2.5".to_string())
+            ]
+        );
+
+        let filter = EntryFilter::new().include("/flag").unwrap();
+        let flag = log.filter_entries(&filter).unwrap();
+        assert_eq!(log.samples(&flag).count(), 1);
+    }
+
+    #[test]
+    fn both_formats_give_the_same_samples() {
+        let data = write_sample();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &log.get_entry_metadata()).unwrap();
+        let (_, mcap) = MCap::parse(&converted).unwrap();
+
+        let samples = |source: &dyn LogSource| {
+            let entries = source.entries().unwrap();
+            let names = entries
+                .iter()
+                .map(|entry| (entry.id, entry.name.clone()))
+                .collect::<HashMap<_, _>>();
+
+            source
+                .samples(&entries)
+                .map(|sample| {
+                    let sample = sample.unwrap();
+                    (
+                        sample.timestamp_ns,
+                        names[&sample.entry_id].clone(),
+                        serde_json::to_string(&sample.value).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the {"value": ...} messages of the converted log are their value
+        assert_eq!(samples(&log), samples(&mcap));
+
+        let types = |source: &dyn LogSource| {
+            let mut types = source
+                .entries()
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.name, entry.typ))
+                .collect::<Vec<_>>();
+            types.sort();
+            types
+        };
+        assert_eq!(types(&log), types(&mcap));
+    }
+
+    #[test]
+    fn undecodable_messages_are_errors() {
+        let mut mcap = McapWriter::new(Vec::new()).unwrap();
+        let json = mcap
+            .add_channel(0, "/json", "json", &HashMap::new())
+            .unwrap();
+        let raw = mcap
+            .add_channel(0, "/raw", "cbor", &HashMap::new())
+            .unwrap();
+        mcap.write_message(raw, 0, 1000, 0, &[7]).unwrap();
+        mcap.write_message(json, 0, 2000, 0, b"{").unwrap();
+        let data = mcap.finish().unwrap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+
+        let mut samples = mcap.samples(&mcap.entries().unwrap());
+        let raw = samples.next().unwrap().unwrap();
+        assert_eq!(serde_json::to_string(&raw.value).unwrap(), "[7]");
+        assert!(matches!(
+            samples.next(),
+            Some(Err(SourceError::Decode { topic, .. })) if topic == "/json"
+        ));
+    }
+}
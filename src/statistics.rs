@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    source::{Entry, LogSource, SourceError, Value},
+    wpilog::{structs::StructValue, types::DataType},
+};
+
+/// Sample count and time range of an entry, with the range and mean of its numeric values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryStatistics {
+    pub entry_id: u32,
+    pub samples: u64,
+    pub start_ns: Option<u64>,
+    pub end_ns: Option<u64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Data(DataType::Int64(val)) => Some(*val as f64),
+        Value::Data(DataType::Float(val)) => Some(*val as f64),
+        Value::Data(DataType::Double(val)) => Some(*val),
+        Value::Struct(StructValue::Int(val)) => Some(*val as f64),
+        Value::Struct(StructValue::UInt(val)) => Some(*val as f64),
+        Value::Struct(StructValue::Float(val)) => Some(*val as f64),
+        Value::Struct(StructValue::Double(val)) => Some(*val),
+        _ => None,
+    }
+}
+
+/// The statistics of each of `entries`, in the same order. Only integer and floating point
+/// samples count towards `min`, `max` and `mean`.
+pub fn statistics<S: LogSource + ?Sized>(
+    source: &S,
+    entries: &[Entry],
+) -> Result<Vec<EntryStatistics>, SourceError> {
+    let mut statistics = entries
+        .iter()
+        .map(|entry| EntryStatistics {
+            entry_id: entry.id,
+            samples: 0,
+            start_ns: None,
+            end_ns: None,
+            min: None,
+            max: None,
+            mean: None,
+        })
+        .collect::<Vec<_>>();
+    let indices = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.id, index))
+        .collect::<HashMap<_, _>>();
+    // sum and count of the numeric samples of each entry
+    let mut sums = vec![(0.0, 0u64); entries.len()];
+
+    for sample in source.samples(entries) {
+        let sample = sample?;
+        let index = indices[&sample.entry_id];
+        let entry = &mut statistics[index];
+
+        entry.samples += 1;
+        entry.start_ns.get_or_insert(sample.timestamp_ns);
+        entry.end_ns = Some(sample.timestamp_ns);
+
+        if let Some(value) = as_number(&sample.value) {
+            entry.min = Some(entry.min.map_or(value, |min| min.min(value)));
+            entry.max = Some(entry.max.map_or(value, |max| max.max(value)));
+            sums[index].0 += value;
+            sums[index].1 += 1;
+        }
+    }
+
+    for (entry, (sum, count)) in statistics.iter_mut().zip(sums) {
+        if count > 0 {
+            entry.mean = Some(sum / count as f64);
+        }
+    }
+
+    Ok(statistics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::mcap::export_mcap,
+        mcap::{MCap, Parse},
+        wpilog::{parser::parse_wpilog, writer::WpiLogWriter},
+    };
+
+    #[test]
+    fn both_formats_give_the_same_statistics() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let speed = log.start("/speed", "double", "", 0).unwrap();
+        let name = log.start("/name", "string", "", 0).unwrap();
+        for (time, value) in [(1, 2.0), (2, -1.0), (3, 5.0)] {
+            log.append_value(speed, time, &DataType::Double(value))
+                .unwrap();
+        }
+        log.append_value(name, 2, &DataType::String("a")).unwrap();
+        let data = log.into_inner().unwrap();
+
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &log.get_entry_metadata()).unwrap();
+        let (_, mcap) = MCap::parse(&converted).unwrap();
+
+        for source in [&log as &dyn LogSource, &mcap] {
+            let entries = source.entries().unwrap();
+            let by_name = entries
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .zip(statistics(source, &entries).unwrap())
+                .collect::<HashMap<_, _>>();
+
+            let speed = &by_name["/speed"];
+            assert_eq!(
+                (speed.samples, speed.start_ns, speed.end_ns),
+                (3, Some(1000), Some(3000))
+            );
+            assert_eq!(
+                (speed.min, speed.max, speed.mean),
+                (Some(-1.0), Some(5.0), Some(2.0))
+            );
+
+            let name = &by_name["/name"];
+            assert_eq!((name.samples, name.mean), (1, None));
+        }
+    }
+}
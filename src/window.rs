@@ -0,0 +1,202 @@
+use crate::{
+    source::{Entry, LogSource, Sample, Samples, SourceError, Value},
+    wpilog::{structs::StructValue, types::DataType},
+};
+
+/// The samples of a [`LogSource`] from `start_us` to `end_us` inclusive, itself a [`LogSource`] so
+/// every tool can be run on part of a log.
+pub struct Window<'s, S: ?Sized> {
+    source: &'s S,
+    start_us: u64,
+    end_us: u64,
+    with_state: bool,
+}
+
+impl<'s, S: LogSource + ?Sized> Window<'s, S> {
+    pub fn new(source: &'s S, start_us: u64, end_us: u64) -> Self {
+        Window {
+            source,
+            start_us,
+            end_us,
+            with_state: false,
+        }
+    }
+
+    /// Also starts with the last sample of each entry before `start_us`, all at the time of the
+    /// latest of them, so the window begins with the state of the log.
+    pub fn with_state(mut self) -> Self {
+        self.with_state = true;
+        self
+    }
+
+    fn state<'a>(&'a self, entries: &[Entry]) -> Result<Vec<Sample<'a>>, SourceError> {
+        let mut state: Vec<Sample> = Vec::new();
+        for sample in self.source.samples(entries) {
+            let sample = sample?;
+            if sample.timestamp_us() >= self.start_us {
+                break;
+            }
+            match state
+                .iter_mut()
+                .find(|last| last.entry_id == sample.entry_id)
+            {
+                Some(last) => *last = sample,
+                None => state.push(sample),
+            }
+        }
+
+        let timestamp_ns = state.iter().map(|sample| sample.timestamp_ns).max();
+        for sample in &mut state {
+            sample.timestamp_ns = timestamp_ns.unwrap_or_default();
+        }
+        Ok(state)
+    }
+}
+
+impl<S: LogSource + ?Sized> LogSource for Window<'_, S> {
+    fn entries(&self) -> Result<Vec<Entry>, SourceError> {
+        self.source.entries()
+    }
+
+    fn samples<'a>(&'a self, entries: &[Entry]) -> Samples<'a> {
+        let (start_us, end_us) = (self.start_us, self.end_us);
+        let state = match self.with_state {
+            true => match self.state(entries) {
+                Ok(state) => state.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            },
+            false => Vec::new(),
+        };
+
+        Box::new(
+            state.into_iter().chain(
+                self.source
+                    .samples(entries)
+                    .skip_while(move |sample| {
+                        matches!(sample, Ok(sample) if sample.timestamp_us() < start_us)
+                    })
+                    .take_while(move |sample| {
+                        !matches!(sample, Ok(sample) if sample.timestamp_us() > end_us)
+                    }),
+            ),
+        )
+    }
+}
+
+fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Data(DataType::Boolean(val)) | Value::Struct(StructValue::Bool(val)) => Some(*val),
+        _ => None,
+    }
+}
+
+/// The `(start_us, end_us)` windows in which the boolean entry `name`, like `DS:enabled`, is
+/// true. A window still open at the end of the log ends with its last sample. Logs without the
+/// entry have no windows.
+pub fn enabled_windows<S: LogSource + ?Sized>(
+    source: &S,
+    name: &str,
+) -> Result<Vec<(u64, u64)>, SourceError> {
+    let entries = source.entries()?;
+    let Some(entry) = entries.iter().find(|entry| entry.name == name) else {
+        return Ok(Vec::new());
+    };
+
+    let mut windows = Vec::new();
+    let mut enabled_since = None;
+    for sample in source.samples(std::slice::from_ref(entry)) {
+        let sample = sample?;
+        match (as_bool(&sample.value), enabled_since) {
+            (Some(true), None) => enabled_since = Some(sample.timestamp_us()),
+            (Some(false), Some(start_us)) => {
+                windows.push((start_us, sample.timestamp_us()));
+                enabled_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(start_us) = enabled_since {
+        let mut end_us = start_us;
+        for sample in source.samples(&entries) {
+            end_us = end_us.max(sample?.timestamp_us());
+        }
+        windows.push((start_us, end_us));
+    }
+
+    Ok(windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::mcap::export_mcap,
+        mcap::{MCap, Parse},
+        wpilog::{parser::parse_wpilog, writer::WpiLogWriter},
+    };
+
+    fn write_sample() -> Vec<u8> {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let enabled = log.start("DS:enabled", "boolean", "", 0).unwrap();
+        let speed = log.start("/speed", "double", "", 0).unwrap();
+        for (time, value) in [(10, true), (20, true), (30, false), (40, false), (50, true)] {
+            log.append_value(enabled, time, &DataType::Boolean(value))
+                .unwrap();
+        }
+        for time in [5, 25, 45, 60] {
+            log.append_value(speed, time, &DataType::Double(time as f64))
+                .unwrap();
+        }
+        log.into_inner().unwrap()
+    }
+
+    #[test]
+    fn windows_are_found_in_either_format() {
+        let data = write_sample();
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &log.get_entry_metadata()).unwrap();
+        let (_, mcap) = MCap::parse(&converted).unwrap();
+
+        for source in [&log as &dyn LogSource, &mcap] {
+            assert_eq!(
+                enabled_windows(source, "DS:enabled").unwrap(),
+                vec![(10, 30), (50, 60)]
+            );
+            assert!(enabled_windows(source, "/missing").unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn windows_limit_the_samples() {
+        let data = write_sample();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let window = Window::new(&log, 10, 30);
+        let entries = window.entries().unwrap();
+        let times = window
+            .samples(&entries)
+            .map(|sample| sample.unwrap().timestamp_us())
+            .collect::<Vec<_>>();
+        assert_eq!(times, vec![10, 20, 25, 30]);
+    }
+
+    #[test]
+    fn windows_can_start_with_the_state() {
+        let data = write_sample();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let window = Window::new(&log, 40, 50).with_state();
+        let entries = window.entries().unwrap();
+        let samples = window
+            .samples(&entries)
+            .map(|sample| {
+                let sample = sample.unwrap();
+                (sample.timestamp_us(), sample.entry_id)
+            })
+            .collect::<Vec<_>>();
+        // the state is the speed at 25 and the enabled sample at 30
+        assert_eq!(samples, vec![(30, 2), (30, 1), (40, 1), (45, 2), (50, 1)]);
+    }
+}
@@ -0,0 +1,555 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+};
+
+use crate::{
+    filter::EntryFilter,
+    source::{Entry, LogSource, Sample, SourceError, Value},
+    window::Window,
+};
+
+use super::{
+    structs::StructValue,
+    types::{ControlRecord, DataType, MetadataEntry, Record, WpiLog, WpiRecord},
+    writer::WpiLogWriter,
+};
+
+#[derive(Debug)]
+pub enum SliceError {
+    Io(io::Error),
+    Source(SourceError),
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceError::Io(err) => write!(f, "{}", err),
+            SliceError::Source(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SliceError {}
+
+impl From<io::Error> for SliceError {
+    fn from(value: io::Error) -> Self {
+        SliceError::Io(value)
+    }
+}
+
+impl From<SourceError> for SliceError {
+    fn from(value: SourceError) -> Self {
+        SliceError::Source(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum MergeError {
+    Io(io::Error),
+    /// Two logs have an entry with the same name and different types.
+    TypeConflict {
+        name: String,
+        first: String,
+        second: String,
+    },
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::Io(err) => write!(f, "{}", err),
+            MergeError::TypeConflict {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "entry {} has type {} in one log and {} in another",
+                name, first, second
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+impl From<io::Error> for MergeError {
+    fn from(value: io::Error) -> Self {
+        MergeError::Io(value)
+    }
+}
+
+/// The records of a log in timestamp order, keeping the file order of records at the same time.
+fn ordered<'a, 'b>(log: &'b WpiLog<'a>) -> Vec<&'b WpiRecord<'a>> {
+    let mut records = log.records.iter().collect::<Vec<_>>();
+    records.sort_by_key(|record| record.timestamp_us);
+    records
+}
+
+/// Writes the records of the entries in `metadata` from `start_us` to `end_us` inclusive to a new
+/// log, keeping their timestamps.
+///
+/// Entries started before `start_us` and not finished by then are started at `start_us`, with
+/// the metadata they had at that time. Entry ids are renumbered in the order entries start.
+pub fn slice<W: io::Write>(
+    writer: W,
+    log: &WpiLog,
+    metadata: &[MetadataEntry],
+    start_us: u64,
+    end_us: u64,
+) -> io::Result<W> {
+    let selected = metadata
+        .iter()
+        .map(|entry| entry.entry_id)
+        .collect::<HashSet<_>>();
+
+    let mut out = WpiLogWriter::new(writer, log.extra_header)?;
+    // old id -> (name, type, metadata) of entries started before the slice
+    let mut active = HashMap::new();
+    // old id -> new id of entries started in the slice
+    let mut ids = HashMap::new();
+    let mut started = false;
+
+    for record in ordered(log) {
+        if record.timestamp_us > end_us {
+            break;
+        }
+
+        if record.timestamp_us >= start_us && !started {
+            started = true;
+
+            let mut active = active.drain().collect::<Vec<_>>();
+            active.sort_by_key(|(id, _)| *id);
+            for (id, (name, typ, metadata)) in active {
+                ids.insert(id, out.start(name, typ, metadata, start_us)?);
+            }
+        }
+
+        let entry_id = match &record.data {
+            Record::Data(_) => record.entry_id,
+            Record::Control(ControlRecord::Start(start)) => start.entry_id,
+            Record::Control(ControlRecord::Finish(finish)) => finish.entry_id,
+            Record::Control(ControlRecord::SetMetadata(set_metadata)) => set_metadata.entry_id,
+        };
+        if !selected.contains(&entry_id) {
+            continue;
+        }
+
+        if !started {
+            match &record.data {
+                Record::Control(ControlRecord::Start(start)) => {
+                    active.insert(entry_id, (start.name, start.typ, start.metadata));
+                }
+                Record::Control(ControlRecord::SetMetadata(set_metadata)) => {
+                    if let Some(entry) = active.get_mut(&entry_id) {
+                        entry.2 = set_metadata.metadata;
+                    }
+                }
+                Record::Control(ControlRecord::Finish(_)) => {
+                    active.remove(&entry_id);
+                }
+                Record::Data(_) => {}
+            }
+            continue;
+        }
+
+        match &record.data {
+            Record::Control(ControlRecord::Start(start)) => {
+                let id = out.start(start.name, start.typ, start.metadata, record.timestamp_us)?;
+                ids.insert(entry_id, id);
+            }
+            Record::Control(ControlRecord::SetMetadata(set_metadata)) => {
+                if let Some(&id) = ids.get(&entry_id) {
+                    out.set_metadata(id, set_metadata.metadata, record.timestamp_us)?;
+                }
+            }
+            Record::Control(ControlRecord::Finish(_)) => {
+                if let Some(id) = ids.remove(&entry_id) {
+                    out.finish(id, record.timestamp_us)?;
+                }
+            }
+            Record::Data(data) => {
+                if let Some(&id) = ids.get(&entry_id) {
+                    out.append(id, record.timestamp_us, data)?;
+                }
+            }
+        }
+    }
+
+    out.into_inner()
+}
+
+/// A decoded value as a wpilog value of type `typ`, if it fits the type.
+fn to_data<'v>(typ: &str, value: &'v StructValue) -> Option<DataType<'v>> {
+    fn number(value: &StructValue) -> Option<f64> {
+        match value {
+            StructValue::Int(val) => Some(*val as f64),
+            StructValue::UInt(val) => Some(*val as f64),
+            StructValue::Float(val) => Some(*val as f64),
+            StructValue::Double(val) => Some(*val),
+            _ => None,
+        }
+    }
+    fn integer(value: &StructValue) -> Option<i64> {
+        match value {
+            StructValue::Int(val) => Some(*val),
+            StructValue::UInt(val) => i64::try_from(*val).ok(),
+            _ => None,
+        }
+    }
+    fn boolean(value: &StructValue) -> Option<bool> {
+        match value {
+            StructValue::Bool(val) => Some(*val),
+            _ => None,
+        }
+    }
+    fn string(value: &StructValue) -> Option<&str> {
+        match value {
+            StructValue::String(val) => Some(val),
+            _ => None,
+        }
+    }
+    fn array<'v, T>(
+        value: &'v StructValue,
+        element: impl Fn(&'v StructValue) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        match value {
+            StructValue::Array(elements) => elements.iter().map(element).collect(),
+            _ => None,
+        }
+    }
+
+    Some(match typ {
+        "boolean" => DataType::Boolean(boolean(value)?),
+        "int64" => DataType::Int64(integer(value)?),
+        "float" => DataType::Float(number(value)? as f32),
+        "double" => DataType::Double(number(value)?),
+        "string" => DataType::String(string(value)?),
+        "boolean[]" => DataType::BooleanArray(array(value, boolean)?),
+        "int64[]" => DataType::Int64Array(array(value, integer)?),
+        "float[]" => DataType::FloatArray(array(value, |value| Some(number(value)? as f32))?),
+        "double[]" => DataType::DoubleArray(array(value, number)?),
+        "string[]" => DataType::StringArray(array(value, string)?),
+        _ => return None,
+    })
+}
+
+/// The wpilog type a sample of `entry` can be written as without losing data.
+fn sample_type<'e>(entry: &'e Entry, sample: &Sample) -> &'e str {
+    match &sample.value {
+        _ if sample.raw.is_some() => &entry.typ,
+        Value::Data(_) => &entry.typ,
+        Value::Struct(value) if to_data(&entry.typ, value).is_some() => &entry.typ,
+        Value::Struct(_) => "json",
+        Value::Raw(_) => "raw",
+    }
+}
+
+/// Writes the samples of `entries` from `start_us` to `end_us` inclusive to a new log, for logs
+/// of any format. Entry ids are renumbered in the order of `entries`, and every entry is started
+/// at `start_us` with its metadata.
+///
+/// Samples the source stores as wpilog data are copied. Decoded values are written as the type of
+/// their entry when every sample fits it, and as `json` otherwise. Samples without a decoder are
+/// written as `raw` data.
+pub fn slice_source<W: io::Write, S: LogSource + ?Sized>(
+    writer: W,
+    source: &S,
+    entries: &[Entry],
+    start_us: u64,
+    end_us: u64,
+) -> Result<W, SliceError> {
+    let window = Window::new(source, start_us, end_us);
+    let indices = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.id, index))
+        .collect::<HashMap<_, _>>();
+
+    // the type of each entry's samples, if they all have the same
+    let mut types: Vec<Option<&str>> = vec![None; entries.len()];
+    for sample in window.samples(entries) {
+        let sample = sample?;
+        let index = indices[&sample.entry_id];
+        let typ = sample_type(&entries[index], &sample);
+        types[index] = match types[index] {
+            Some(existing) if existing != typ => Some("json"),
+            _ => Some(typ),
+        };
+    }
+
+    let mut out = WpiLogWriter::new(writer, "")?;
+    let mut ids = Vec::new();
+    for (entry, typ) in entries.iter().zip(&types) {
+        let typ = typ.unwrap_or(&entry.typ);
+        ids.push(out.start(&entry.name, typ, &entry.metadata, start_us)?);
+    }
+
+    for sample in window.samples(entries) {
+        let sample = sample?;
+        let index = indices[&sample.entry_id];
+        let entry = &entries[index];
+        let (id, typ) = (ids[index], types[index].unwrap_or(&entry.typ));
+        let timestamp_us = sample.timestamp_us();
+
+        match (&sample.value, sample.raw) {
+            _ if typ == "json" => {
+                let json = serde_json::to_string(&sample.value)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                out.append(id, timestamp_us, json.as_bytes())?;
+            }
+            (_, Some(raw)) => out.append(id, timestamp_us, raw)?,
+            (Value::Data(value), None) => out.append_value(id, timestamp_us, value)?,
+            (Value::Struct(value), None) => {
+                // every sample fits the type, or it would be json
+                let value = to_data(typ, value).unwrap();
+                out.append_value(id, timestamp_us, &value)?;
+            }
+            (Value::Raw(data), None) => out.append(id, timestamp_us, data)?,
+        }
+    }
+
+    Ok(out.into_inner()?)
+}
+
+/// Merges logs into one, in timestamp order, with the entries whose name passes `filter`.
+///
+/// Entries with the same name are merged into one and have to have the same type. A merged
+/// entry is started with the metadata of the first log to start it, and is only finished once it
+/// is finished in every log. The extra header is taken from the first log.
+pub fn merge<W: io::Write>(
+    writer: W,
+    logs: &[&WpiLog],
+    filter: &EntryFilter,
+) -> Result<W, MergeError> {
+    let extra_header = logs.first().map(|log| log.extra_header).unwrap_or("");
+    let mut out = WpiLogWriter::new(writer, extra_header)?;
+
+    let mut records = logs
+        .iter()
+        .enumerate()
+        .flat_map(|(index, log)| ordered(log).into_iter().map(move |record| (index, record)))
+        .collect::<Vec<_>>();
+    records.sort_by_key(|(_, record)| record.timestamp_us);
+
+    // name -> (new id, type, number of logs it is started in)
+    let mut entries: HashMap<&str, (u32, &str, usize)> = HashMap::new();
+    // (log index, old id) -> name of the entry
+    let mut names = HashMap::new();
+
+    for (index, record) in records {
+        let timestamp_us = record.timestamp_us;
+
+        match &record.data {
+            Record::Control(ControlRecord::Start(start)) => {
+                if !filter.matches(start.name) {
+                    continue;
+                }
+
+                match entries.get_mut(start.name) {
+                    Some((_, typ, count)) if *typ == start.typ => *count += 1,
+                    Some((_, typ, _)) => {
+                        return Err(MergeError::TypeConflict {
+                            name: start.name.to_string(),
+                            first: typ.to_string(),
+                            second: start.typ.to_string(),
+                        })
+                    }
+                    None => {
+                        let id = out.start(start.name, start.typ, start.metadata, timestamp_us)?;
+                        entries.insert(start.name, (id, start.typ, 1));
+                    }
+                }
+                names.insert((index, start.entry_id), start.name);
+            }
+            Record::Control(ControlRecord::SetMetadata(set_metadata)) => {
+                if let Some(name) = names.get(&(index, set_metadata.entry_id)) {
+                    let (id, _, _) = entries[name];
+                    out.set_metadata(id, set_metadata.metadata, timestamp_us)?;
+                }
+            }
+            Record::Control(ControlRecord::Finish(finish)) => {
+                if let Some(name) = names.remove(&(index, finish.entry_id)) {
+                    let (id, _, count) = entries.get_mut(name).unwrap();
+                    *count -= 1;
+                    if *count == 0 {
+                        out.finish(*id, timestamp_us)?;
+                        entries.remove(name);
+                    }
+                }
+            }
+            Record::Data(data) => {
+                if let Some(name) = names.get(&(index, record.entry_id)) {
+                    let (id, _, _) = entries[name];
+                    out.append(id, timestamp_us, data)?;
+                }
+            }
+        }
+    }
+
+    Ok(out.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::{mcap::export_mcap, tests::sample_mcap},
+        mcap::{MCap, Parse},
+        wpilog::parser::parse_wpilog,
+    };
+
+    fn write_log(name: &str, typ: &str, times: &[u64]) -> Vec<u8> {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let other = log.start("/other", "int64", "", 0).unwrap();
+        let entry = log.start(name, typ, "first", 0).unwrap();
+        log.set_metadata(entry, "second", 1).unwrap();
+        for &time in times {
+            log.append(entry, time, &(time as i64).to_le_bytes())
+                .unwrap();
+            log.append(other, time, &0i64.to_le_bytes()).unwrap();
+        }
+        log.finish(entry, 100).unwrap();
+        log.into_inner().unwrap()
+    }
+
+    /// `(timestamp, entry name, metadata or data)` of every record.
+    fn summarize(data: &[u8]) -> Vec<(u64, String, String)> {
+        let (_, log) = parse_wpilog(data).unwrap();
+        let mut names = HashMap::new();
+
+        log.records
+            .iter()
+            .map(|record| {
+                let (id, description) = match &record.data {
+                    Record::Control(ControlRecord::Start(start)) => {
+                        names.insert(start.entry_id, start.name);
+                        (start.entry_id, format!("start {}", start.metadata))
+                    }
+                    Record::Control(ControlRecord::SetMetadata(set_metadata)) => (
+                        set_metadata.entry_id,
+                        format!("metadata {}", set_metadata.metadata),
+                    ),
+                    Record::Control(ControlRecord::Finish(finish)) => {
+                        (finish.entry_id, "finish".to_string())
+                    }
+                    Record::Data(data) => (record.entry_id, format!("{:?}", &data[..1])),
+                };
+                (record.timestamp_us, names[&id].to_string(), description)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slices_start_active_entries() {
+        let data = write_log("/a", "int64", &[5, 10, 15, 20]);
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let metadata = EntryFilter::new()
+            .include("/a")
+            .unwrap()
+            .filter_entries(&log.get_entry_metadata());
+
+        let sliced = slice(Vec::new(), &log, &metadata, 10, 15).unwrap();
+        assert_eq!(
+            summarize(&sliced),
+            vec![
+                (10, "/a".to_string(), "start second".to_string()),
+                (10, "/a".to_string(), "[10]".to_string()),
+                (15, "/a".to_string(), "[15]".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn sources_are_sliced_the_same_in_either_format() {
+        let data = write_log("/a", "int64", &[5, 10, 15, 20]);
+        let (_, log) = parse_wpilog(&data).unwrap();
+        let mut converted = Vec::new();
+        export_mcap(&mut converted, &log, &log.get_entry_metadata()).unwrap();
+        let (_, mcap) = MCap::parse(&converted).unwrap();
+
+        for source in [&log as &dyn LogSource, &mcap] {
+            let entries = source
+                .filter_entries(&EntryFilter::new().include("/a").unwrap())
+                .unwrap();
+            let sliced = slice_source(Vec::new(), source, &entries, 10, 15).unwrap();
+            let records = summarize(&sliced);
+
+            // MCAP channels have their metadata wrapped with their encodings
+            assert_eq!((records[0].0, records[0].1.as_str()), (10, "/a"));
+            assert!(records[0].2.starts_with("start ") && records[0].2.contains("second"));
+            assert_eq!(
+                records[1..],
+                [
+                    (10, "/a".to_string(), "[10]".to_string()),
+                    (15, "/a".to_string(), "[15]".to_string()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn decoded_values_are_sliced_as_their_type_or_json() {
+        let data = sample_mcap();
+        let (_, mcap) = MCap::parse(&data).unwrap();
+        let entries = mcap.entries().unwrap();
+
+        let sliced = slice_source(Vec::new(), &mcap, &entries, 0, u64::MAX).unwrap();
+        let (_, log) = parse_wpilog(&sliced).unwrap();
+        let mut types = log
+            .get_entry_metadata()
+            .iter()
+            .map(|entry| (entry.name.to_string(), entry.typ.to_string()))
+            .collect::<Vec<_>>();
+        types.sort();
+        assert_eq!(
+            types,
+            vec![
+                ("/pose".to_string(), "json".to_string()),
+                ("/quiet".to_string(), "raw".to_string()),
+                ("/raw".to_string(), "raw".to_string()),
+            ]
+        );
+
+        let json = log
+            .records
+            .iter()
+            .filter_map(|record| match record.data {
+                Record::Data(data) if record.timestamp_us == 3_000_000 => Some(data),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(json, vec![br#"{"x":2.5}"#.as_slice()]);
+    }
+
+    #[test]
+    fn merges_entries_by_name() {
+        let first = write_log("/a", "int64", &[10, 30]);
+        let second = write_log("/a", "int64", &[20]);
+        let (_, first) = parse_wpilog(&first).unwrap();
+        let (_, second) = parse_wpilog(&second).unwrap();
+
+        let filter = EntryFilter::new().exclude("/other").unwrap();
+        let merged = merge(Vec::new(), &[&first, &second], &filter).unwrap();
+        assert_eq!(
+            summarize(&merged),
+            vec![
+                (0, "/a".to_string(), "start first".to_string()),
+                (1, "/a".to_string(), "metadata second".to_string()),
+                (1, "/a".to_string(), "metadata second".to_string()),
+                (10, "/a".to_string(), "[10]".to_string()),
+                (20, "/a".to_string(), "[20]".to_string()),
+                (30, "/a".to_string(), "[30]".to_string()),
+                (100, "/a".to_string(), "finish".to_string()),
+            ]
+        );
+
+        let conflicting = write_log("/a", "double", &[]);
+        let (_, conflicting) = parse_wpilog(&conflicting).unwrap();
+        assert!(matches!(
+            merge(Vec::new(), &[&first, &conflicting], &filter),
+            Err(MergeError::TypeConflict { .. })
+        ));
+    }
+}
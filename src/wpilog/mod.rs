@@ -1,2 +1,5 @@
+pub mod edit;
 pub mod parser;
+pub mod structs;
 pub mod types;
+pub mod writer;
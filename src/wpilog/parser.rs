@@ -1,8 +1,8 @@
-use nom::IResult;
+use nom::{combinator::map, IResult};
 
 use super::types::*;
 
-pub fn parse_wpilog(input: &[u8]) -> IResult<&[u8], WpiLog> {
+pub fn parse_wpilog(input: &[u8]) -> IResult<&[u8], WpiLog<'_>> {
     let (input, _) = nom::bytes::complete::tag("WPILOG")(input)?;
     let (input, minor_version) = nom::number::complete::le_u8(input)?;
     let (input, major_version) = nom::number::complete::le_u8(input)?;
@@ -56,7 +56,7 @@ fn parse_u64(input: &[u8], len: u8) -> IResult<&[u8], u64> {
     Ok((input, agg))
 }
 
-fn parse_wpilog_record(input: &[u8]) -> IResult<&[u8], WpiRecord> {
+fn parse_wpilog_record(input: &[u8]) -> IResult<&[u8], WpiRecord<'_>> {
     let (input, header_len) = nom::number::complete::le_u8(input)?;
     let entry_id_len = header_len & 0x3;
     let payload_size_len = (header_len >> 2) & 0x3;
@@ -86,7 +86,7 @@ fn parse_wpilog_record(input: &[u8]) -> IResult<&[u8], WpiRecord> {
     ))
 }
 
-fn parse_control_record(input: &[u8]) -> IResult<&[u8], ControlRecord> {
+fn parse_control_record(input: &[u8]) -> IResult<&[u8], ControlRecord<'_>> {
     let (rest, control_record_type) = nom::number::complete::le_u8(input)?;
 
     match control_record_type {
@@ -109,7 +109,7 @@ fn parse_control_record(input: &[u8]) -> IResult<&[u8], ControlRecord> {
     }
 }
 
-fn parse_start_record(input: &[u8]) -> IResult<&[u8], ControlRecord> {
+fn parse_start_record(input: &[u8]) -> IResult<&[u8], ControlRecord<'_>> {
     let (input, entry_id) = nom::number::complete::le_u32(input)?;
     let (input, name) = parse_string_with_len(input)?;
     let (input, typ) = parse_string_with_len(input)?;
@@ -126,13 +126,13 @@ fn parse_start_record(input: &[u8]) -> IResult<&[u8], ControlRecord> {
     ))
 }
 
-fn parse_finish_record(input: &[u8]) -> IResult<&[u8], ControlRecord> {
+fn parse_finish_record(input: &[u8]) -> IResult<&[u8], ControlRecord<'_>> {
     let (input, entry_id) = nom::number::complete::le_u32(input)?;
 
     Ok((input, ControlRecord::Finish(FinishRecord { entry_id })))
 }
 
-fn parse_set_metadata_record(input: &[u8]) -> IResult<&[u8], ControlRecord> {
+fn parse_set_metadata_record(input: &[u8]) -> IResult<&[u8], ControlRecord<'_>> {
     let (input, entry_id) = nom::number::complete::le_u32(input)?;
     let (input, metadata) = parse_string_with_len(input)?;
 
@@ -181,6 +181,26 @@ pub fn parse_double(input: &[u8]) -> IResult<&[u8], f64> {
     Ok((input, number))
 }
 
+/// Decodes a data record according to the entry type, anything unknown is returned as raw.
+pub fn parse_data<'a>(typ: &str, input: &'a [u8]) -> IResult<&'a [u8], DataType<'a>> {
+    match typ {
+        "boolean" => map(parse_boolean, DataType::Boolean)(input),
+        "int64" => map(parse_int64, DataType::Int64)(input),
+        "float" => map(parse_float, DataType::Float)(input),
+        "double" => map(parse_double, DataType::Double)(input),
+        "string" | "json" | "structschema" => map(parse_string_full, DataType::String)(input),
+        "boolean[]" => map(|i| parse_array(parse_boolean, i), DataType::BooleanArray)(input),
+        "int64[]" => map(|i| parse_array(parse_int64, i), DataType::Int64Array)(input),
+        "float[]" => map(|i| parse_array(parse_float, i), DataType::FloatArray)(input),
+        "double[]" => map(|i| parse_array(parse_double, i), DataType::DoubleArray)(input),
+        "string[]" => map(
+            |i| parse_array_ref_with_len(parse_string_with_len, i),
+            DataType::StringArray,
+        )(input),
+        _ => map(parse_raw, DataType::Raw)(input),
+    }
+}
+
 pub fn parse_array<T>(
     func: impl Fn(&[u8]) -> IResult<&[u8], T>,
     input: &[u8],
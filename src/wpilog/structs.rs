@@ -0,0 +1,646 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use super::types::{ControlRecord, Record, WpiLog};
+
+const SCHEMA_PREFIX: &str = "/.schema/struct:";
+const MAX_DEPTH: usize = 32;
+
+/// Decoded value of a WPILib struct, or of one of its members.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Array(Vec<StructValue>),
+    Struct(Vec<(String, StructValue)>),
+}
+
+impl Serialize for StructValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            StructValue::Bool(val) => serializer.serialize_bool(*val),
+            StructValue::Int(val) => serializer.serialize_i64(*val),
+            StructValue::UInt(val) => serializer.serialize_u64(*val),
+            StructValue::Float(val) => serializer.serialize_f32(*val),
+            StructValue::Double(val) => serializer.serialize_f64(*val),
+            StructValue::String(val) => serializer.serialize_str(val),
+            StructValue::Array(val) => val.serialize(serializer),
+            StructValue::Struct(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (name, val) in members {
+                    map.serialize_entry(name, val)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructFieldType {
+    Bool,
+    Char,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float,
+    Double,
+    Struct(String),
+}
+
+impl StructFieldType {
+    fn parse(typ: &str) -> Self {
+        match typ {
+            "bool" => StructFieldType::Bool,
+            "char" => StructFieldType::Char,
+            "int8" => StructFieldType::Int8,
+            "int16" => StructFieldType::Int16,
+            "int32" => StructFieldType::Int32,
+            "int64" => StructFieldType::Int64,
+            "uint8" => StructFieldType::UInt8,
+            "uint16" => StructFieldType::UInt16,
+            "uint32" => StructFieldType::UInt32,
+            "uint64" => StructFieldType::UInt64,
+            "float" | "float32" => StructFieldType::Float,
+            "double" | "float64" => StructFieldType::Double,
+            name => StructFieldType::Struct(name.to_string()),
+        }
+    }
+
+    /// Size in bytes of primitive types, nested structs are sized by the registry.
+    pub fn primitive_size(&self) -> Option<usize> {
+        match self {
+            StructFieldType::Bool
+            | StructFieldType::Char
+            | StructFieldType::Int8
+            | StructFieldType::UInt8 => Some(1),
+            StructFieldType::Int16 | StructFieldType::UInt16 => Some(2),
+            StructFieldType::Int32 | StructFieldType::UInt32 | StructFieldType::Float => Some(4),
+            StructFieldType::Int64 | StructFieldType::UInt64 | StructFieldType::Double => Some(8),
+            StructFieldType::Struct(_) => None,
+        }
+    }
+
+    fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            StructFieldType::Int8
+                | StructFieldType::Int16
+                | StructFieldType::Int32
+                | StructFieldType::Int64
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructField {
+    pub name: String,
+    pub typ: StructFieldType,
+    pub array_len: Option<usize>,
+    pub bit_width: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructSchema {
+    pub name: String,
+    pub fields: Vec<StructField>,
+}
+
+impl StructSchema {
+    /// Parses a schema such as `double x;double y;enum {a=1, b=2} int8 mode:4`.
+    pub fn parse(name: &str, schema: &str) -> Result<Self, StructError> {
+        let fields = schema
+            .split(';')
+            .map(str::trim)
+            .filter(|decl| !decl.is_empty())
+            .map(parse_declaration)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(StructSchema {
+            name: name.to_string(),
+            fields,
+        })
+    }
+}
+
+fn parse_declaration(decl: &str) -> Result<StructField, StructError> {
+    let invalid = || StructError::InvalidDeclaration(decl.to_string());
+
+    // enum values are informational only, the member is decoded as a plain integer
+    let decl = match decl.strip_prefix("enum") {
+        Some(rest) => rest[rest.find('}').ok_or_else(invalid)? + 1..].trim_start(),
+        None => decl,
+    };
+
+    let (typ, rest) = decl.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let typ = StructFieldType::parse(typ);
+
+    let (rest, bit_width) = match rest.split_once(':') {
+        Some((rest, bits)) => (rest, Some(bits.trim().parse().map_err(|_| invalid())?)),
+        None => (rest, None),
+    };
+
+    let (name, array_len) = match rest.split_once('[') {
+        Some((name, len)) => {
+            let len = len.trim().strip_suffix(']').ok_or_else(invalid)?;
+            (name, Some(len.trim().parse().map_err(|_| invalid())?))
+        }
+        None => (rest, None),
+    };
+
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    match bit_width {
+        Some(width)
+            if array_len.is_some()
+                || matches!(
+                    typ,
+                    StructFieldType::Float | StructFieldType::Double | StructFieldType::Struct(_)
+                )
+                || (typ == StructFieldType::Bool && width != 1)
+                || width == 0
+                || width as usize > typ.primitive_size().unwrap_or_default() * 8 =>
+        {
+            return Err(invalid())
+        }
+        _ => {}
+    }
+
+    Ok(StructField {
+        name: name.to_string(),
+        typ,
+        array_len,
+        bit_width,
+    })
+}
+
+/// Struct schemas published in a log under `/.schema/struct:<name>`.
+#[derive(Debug, Clone, Default)]
+pub struct StructRegistry {
+    schemas: HashMap<String, StructSchema>,
+}
+
+impl StructRegistry {
+    pub fn from_log(log: &WpiLog) -> Self {
+        let mut schema_entries = HashMap::new();
+        let mut registry = StructRegistry::default();
+
+        for record in &log.records {
+            match &record.data {
+                Record::Control(ControlRecord::Start(start)) if start.typ == "structschema" => {
+                    if let Some(name) = start.name.strip_prefix(SCHEMA_PREFIX) {
+                        schema_entries.insert(start.entry_id, name);
+                    }
+                }
+                Record::Data(data) => {
+                    if let Some(name) = schema_entries.get(&record.entry_id) {
+                        if let Ok(schema) = std::str::from_utf8(data) {
+                            // malformed schemas are left out, their entries then decode as raw
+                            if let Ok(schema) = StructSchema::parse(name, schema) {
+                                registry.insert(schema);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        registry
+    }
+
+    pub fn insert(&mut self, schema: StructSchema) {
+        self.schemas.insert(schema.name.clone(), schema);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StructSchema> {
+        self.schemas.get(name)
+    }
+
+    /// Resolves an entry type such as `struct:Pose2d` or `struct:Pose2d[]` to its schema,
+    /// and whether the entry holds an array of structs.
+    pub fn resolve_type(&self, typ: &str) -> Option<(&StructSchema, bool)> {
+        let name = typ.strip_prefix("struct:")?;
+
+        match name.strip_suffix("[]") {
+            Some(name) => self.get(name).map(|schema| (schema, true)),
+            None => self.get(name).map(|schema| (schema, false)),
+        }
+    }
+
+    /// Size of the named struct in bytes, `None` if it or a nested struct is unknown.
+    pub fn size_of(&self, name: &str) -> Option<usize> {
+        self.size_of_inner(name, 0)
+    }
+
+    fn size_of_inner(&self, name: &str, depth: usize) -> Option<usize> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let schema = self.get(name)?;
+        let mut size = 0;
+        // (storage size, bits used) of the bit-field storage unit currently being filled
+        let mut unit: Option<(usize, u32)> = None;
+
+        for field in &schema.fields {
+            let field_size = match &field.typ {
+                StructFieldType::Struct(name) => self.size_of_inner(name, depth + 1)?,
+                typ => typ.primitive_size()?,
+            };
+
+            match field.bit_width {
+                Some(width) => {
+                    let (unit_size, used) = next_bit_field(&mut unit, &field.typ, width);
+                    if used == 0 {
+                        size += unit_size;
+                    }
+                }
+                None => {
+                    unit = None;
+                    size += field_size * field.array_len.unwrap_or(1);
+                }
+            }
+        }
+
+        Some(size)
+    }
+
+    /// Decodes the value of an entry with a `struct:` type.
+    pub fn decode(&self, typ: &str, data: &[u8]) -> Option<StructValue> {
+        let (schema, is_array) = self.resolve_type(typ)?;
+
+        if is_array {
+            let size = self.size_of(&schema.name)?;
+            if size == 0 || !data.len().is_multiple_of(size) {
+                return None;
+            }
+
+            data.chunks_exact(size)
+                .map(|chunk| self.decode_struct(schema, chunk, 0))
+                .collect::<Option<Vec<_>>>()
+                .map(StructValue::Array)
+        } else {
+            self.decode_struct(schema, data, 0)
+        }
+    }
+
+    fn decode_struct(
+        &self,
+        schema: &StructSchema,
+        data: &[u8],
+        depth: usize,
+    ) -> Option<StructValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let mut offset = 0;
+        let mut unit: Option<(usize, u32)> = None;
+        let mut unit_start = 0;
+        let mut members = Vec::with_capacity(schema.fields.len());
+
+        for field in &schema.fields {
+            let value = match field.bit_width {
+                Some(width) => {
+                    let (unit_size, used) = next_bit_field(&mut unit, &field.typ, width);
+                    if used == 0 {
+                        unit_start = offset;
+                        offset += unit_size;
+                    }
+
+                    let raw = read_uint(data.get(unit_start..unit_start + unit_size)?);
+                    let mask = if width == 64 {
+                        u64::MAX
+                    } else {
+                        (1 << width) - 1
+                    };
+                    let bits = (raw >> used) & mask;
+
+                    match &field.typ {
+                        StructFieldType::Bool => StructValue::Bool(bits != 0),
+                        typ if typ.is_signed() => {
+                            let shift = 64 - width;
+                            StructValue::Int(((bits << shift) as i64) >> shift)
+                        }
+                        _ => StructValue::UInt(bits),
+                    }
+                }
+                None => {
+                    unit = None;
+
+                    let size = match &field.typ {
+                        StructFieldType::Struct(name) => self.size_of_inner(name, depth + 1)?,
+                        typ => typ.primitive_size()?,
+                    };
+
+                    match (field.array_len, &field.typ) {
+                        (Some(len), StructFieldType::Char) => {
+                            let chars = data.get(offset..offset + len)?;
+                            offset += len;
+
+                            // char arrays are zero padded strings
+                            let end = chars.iter().position(|c| *c == 0).unwrap_or(len);
+                            StructValue::String(String::from_utf8_lossy(&chars[..end]).into_owned())
+                        }
+                        (Some(len), typ) => {
+                            let mut values = Vec::with_capacity(len);
+                            for _ in 0..len {
+                                let bytes = data.get(offset..offset + size)?;
+                                values.push(self.decode_member(typ, bytes, depth)?);
+                                offset += size;
+                            }
+
+                            StructValue::Array(values)
+                        }
+                        (None, typ) => {
+                            let bytes = data.get(offset..offset + size)?;
+                            offset += size;
+
+                            self.decode_member(typ, bytes, depth)?
+                        }
+                    }
+                }
+            };
+
+            members.push((field.name.clone(), value));
+        }
+
+        Some(StructValue::Struct(members))
+    }
+
+    fn decode_member(
+        &self,
+        typ: &StructFieldType,
+        bytes: &[u8],
+        depth: usize,
+    ) -> Option<StructValue> {
+        let value = match typ {
+            StructFieldType::Bool => StructValue::Bool(bytes[0] != 0),
+            StructFieldType::Char => {
+                StructValue::String(String::from_utf8_lossy(bytes).into_owned())
+            }
+            StructFieldType::Int8 => StructValue::Int(bytes[0] as i8 as i64),
+            StructFieldType::Int16 => {
+                StructValue::Int(i16::from_le_bytes(bytes.try_into().ok()?) as i64)
+            }
+            StructFieldType::Int32 => {
+                StructValue::Int(i32::from_le_bytes(bytes.try_into().ok()?) as i64)
+            }
+            StructFieldType::Int64 => StructValue::Int(i64::from_le_bytes(bytes.try_into().ok()?)),
+            StructFieldType::UInt8
+            | StructFieldType::UInt16
+            | StructFieldType::UInt32
+            | StructFieldType::UInt64 => StructValue::UInt(read_uint(bytes)),
+            StructFieldType::Float => {
+                StructValue::Float(f32::from_le_bytes(bytes.try_into().ok()?))
+            }
+            StructFieldType::Double => {
+                StructValue::Double(f64::from_le_bytes(bytes.try_into().ok()?))
+            }
+            StructFieldType::Struct(name) => {
+                self.decode_struct(self.get(name)?, bytes, depth + 1)?
+            }
+        };
+
+        Some(value)
+    }
+}
+
+/// Places the next bit-field, returning the storage unit size and the bits of it already used.
+///
+/// Consecutive bit-fields share a storage unit while their type sizes match and they fit,
+/// bool bit-fields adopt the size of the unit being filled.
+fn next_bit_field(
+    unit: &mut Option<(usize, u32)>,
+    typ: &StructFieldType,
+    width: u32,
+) -> (usize, u32) {
+    let size = match (typ, *unit) {
+        (StructFieldType::Bool, Some((size, _))) => size,
+        (typ, _) => typ.primitive_size().unwrap_or(1),
+    };
+
+    match unit {
+        Some((unit_size, used)) if *unit_size == size && *used + width <= size as u32 * 8 => {
+            let start = *used;
+            *used += width;
+            (size, start)
+        }
+        _ => {
+            *unit = Some((size, width));
+            (size, 0)
+        }
+    }
+}
+
+fn read_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |agg, byte| (agg << 8) | *byte as u64)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructError {
+    InvalidDeclaration(String),
+}
+
+impl fmt::Display for StructError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructError::InvalidDeclaration(decl) => {
+                write!(f, "invalid struct member declaration: {}", decl)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StructError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wpilog::{parser::parse_wpilog, writer::WpiLogWriter};
+
+    fn registry(schemas: &[(&str, &str)]) -> StructRegistry {
+        let mut registry = StructRegistry::default();
+        for (name, schema) in schemas {
+            registry.insert(StructSchema::parse(name, schema).unwrap());
+        }
+        registry
+    }
+
+    fn doubles(values: &[f64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn members(members: &[(&str, StructValue)]) -> StructValue {
+        StructValue::Struct(
+            members
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn nested_structs_are_decoded() {
+        let registry = registry(&[
+            ("Translation2d", "double x;double y"),
+            ("Rotation2d", "double value"),
+            ("Pose2d", "Translation2d translation;Rotation2d rotation"),
+        ]);
+        assert_eq!(registry.size_of("Pose2d"), Some(24));
+
+        let translation = members(&[
+            ("x", StructValue::Double(1.0)),
+            ("y", StructValue::Double(2.0)),
+        ]);
+        let rotation = members(&[("value", StructValue::Double(0.5))]);
+        assert_eq!(
+            registry.decode("struct:Pose2d", &doubles(&[1.0, 2.0, 0.5])),
+            Some(members(&[
+                ("translation", translation.clone()),
+                ("rotation", rotation),
+            ]))
+        );
+
+        assert_eq!(
+            registry.decode("struct:Translation2d[]", &doubles(&[1.0, 2.0, 1.0, 2.0])),
+            Some(StructValue::Array(vec![translation.clone(), translation]))
+        );
+        assert_eq!(
+            registry.decode("struct:Translation2d[]", &[]),
+            Some(StructValue::Array(vec![]))
+        );
+    }
+
+    #[test]
+    fn bit_fields_share_storage_units() {
+        let registry = registry(&[(
+            "Flags",
+            "int8 a:3;int8 b:5;bool c:1;uint16 d:4;uint16 e:12;bool f:1",
+        )]);
+        // a and b fill a byte and c starts the next, d and e fill a uint16, so f starts another
+        // unit that is as large as the previous bit-field's
+        assert_eq!(registry.size_of("Flags"), Some(6));
+
+        let data = [0b0001_0111, 0b1, 0x35, 0x12, 0b1, 0];
+        assert_eq!(
+            registry.decode("struct:Flags", &data),
+            Some(members(&[
+                ("a", StructValue::Int(-1)),
+                ("b", StructValue::Int(2)),
+                ("c", StructValue::Bool(true)),
+                ("d", StructValue::UInt(5)),
+                ("e", StructValue::UInt(0x123)),
+                ("f", StructValue::Bool(true)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn enums_and_arrays_are_decoded() {
+        let registry = registry(&[(
+            "Mode",
+            "enum {off=0, on=2} int8 mode;char name[4];int16 values[2];uint32 count",
+        )]);
+        let schema = registry.get("Mode").unwrap();
+        assert_eq!(schema.fields[0].typ, StructFieldType::Int8);
+        assert_eq!(schema.fields[1].array_len, Some(4));
+
+        let data = [2, b'a', b'b', 0, 0, 1, 0, 0xFF, 0xFF, 7, 0, 0, 0];
+        assert_eq!(
+            registry.decode("struct:Mode", &data),
+            Some(members(&[
+                ("mode", StructValue::Int(2)),
+                ("name", StructValue::String("ab".to_string())),
+                (
+                    "values",
+                    StructValue::Array(vec![StructValue::Int(1), StructValue::Int(-1)])
+                ),
+                ("count", StructValue::UInt(7)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn short_data_is_not_decoded() {
+        let registry = registry(&[
+            ("Translation2d", "double x;double y"),
+            ("Flags", "uint16 a:4"),
+            ("Broken", "Missing inner"),
+        ]);
+
+        assert_eq!(
+            registry.decode("struct:Translation2d", &doubles(&[1.0])),
+            None
+        );
+        assert_eq!(registry.decode("struct:Translation2d[]", &[0; 24]), None);
+        assert_eq!(registry.decode("struct:Flags", &[1]), None);
+        assert_eq!(registry.decode("struct:Unknown", &[]), None);
+        assert_eq!(registry.size_of("Broken"), None);
+        assert_eq!(registry.decode("struct:Broken", &[0; 8]), None);
+    }
+
+    #[test]
+    fn invalid_declarations_are_rejected() {
+        for schema in [
+            "double",
+            "double x:3",
+            "int8 a[2]:3",
+            "bool b:2",
+            "int8 a:9",
+            "int8 a:0",
+            "int8 a[x]",
+            "enum {a=1 int8 b",
+            "int8 two words",
+        ] {
+            assert_eq!(
+                StructSchema::parse("Bad", schema),
+                Err(StructError::InvalidDeclaration(schema.to_string())),
+                "{}",
+                schema
+            );
+        }
+    }
+
+    #[test]
+    fn schemas_are_read_from_the_log() {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let schema = log
+            .start("/.schema/struct:Translation2d", "structschema", "", 0)
+            .unwrap();
+        let broken = log
+            .start("/.schema/struct:Broken", "structschema", "", 0)
+            .unwrap();
+        log.append(schema, 1, b"double x;double y").unwrap();
+        log.append(broken, 1, b"double").unwrap();
+        let data = log.into_inner().unwrap();
+        let (_, log) = parse_wpilog(&data).unwrap();
+
+        let registry = StructRegistry::from_log(&log);
+        let (schema, is_array) = registry.resolve_type("struct:Translation2d[]").unwrap();
+        assert_eq!(schema.fields.len(), 2);
+        assert!(is_array);
+        assert!(registry.resolve_type("struct:Broken").is_none());
+        assert!(registry.resolve_type("Translation2d").is_none());
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use serde::Serialize;
+
 #[derive(Default, Debug, Clone)]
 pub struct WpiLog<'a> {
     pub major_version: u8,
@@ -191,7 +193,8 @@ impl<'a> MetadataEntry<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum DataType<'a> {
     Raw(&'a [u8]),
     Boolean(bool),
@@ -199,4 +202,22 @@ pub enum DataType<'a> {
     Float(f32),
     Double(f64),
     String(&'a str),
+    BooleanArray(Vec<bool>),
+    Int64Array(Vec<i64>),
+    FloatArray(Vec<f32>),
+    DoubleArray(Vec<f64>),
+    StringArray(Vec<&'a str>),
+}
+
+impl<'a> DataType<'a> {
+    pub fn is_array(&self) -> bool {
+        matches!(
+            self,
+            DataType::BooleanArray(_)
+                | DataType::Int64Array(_)
+                | DataType::FloatArray(_)
+                | DataType::DoubleArray(_)
+                | DataType::StringArray(_)
+        )
+    }
 }
@@ -0,0 +1,137 @@
+use std::io::{self, Write};
+
+use super::types::DataType;
+
+/// Writes a version 1.0 wpilog, using the smallest field widths for each record header.
+pub struct WpiLogWriter<W: Write> {
+    writer: W,
+    next_entry_id: u32,
+}
+
+impl<W: Write> WpiLogWriter<W> {
+    pub fn new(mut writer: W, extra_header: &str) -> io::Result<Self> {
+        writer.write_all(b"WPILOG")?;
+        writer.write_all(&[0x00, 0x01])?;
+        writer.write_all(&(extra_header.len() as u32).to_le_bytes())?;
+        writer.write_all(extra_header.as_bytes())?;
+
+        Ok(WpiLogWriter {
+            writer,
+            next_entry_id: 1,
+        })
+    }
+
+    /// Writes a start record for a new entry and returns its id.
+    pub fn start(
+        &mut self,
+        name: &str,
+        typ: &str,
+        metadata: &str,
+        timestamp_us: u64,
+    ) -> io::Result<u32> {
+        let entry_id = self.next_entry_id;
+        self.next_entry_id += 1;
+
+        let mut payload = vec![0];
+        payload.extend(entry_id.to_le_bytes());
+        for field in [name, typ, metadata] {
+            payload.extend((field.len() as u32).to_le_bytes());
+            payload.extend(field.as_bytes());
+        }
+
+        self.write_record(0, timestamp_us, &payload)?;
+
+        Ok(entry_id)
+    }
+
+    pub fn finish(&mut self, entry_id: u32, timestamp_us: u64) -> io::Result<()> {
+        let mut payload = vec![1];
+        payload.extend(entry_id.to_le_bytes());
+
+        self.write_record(0, timestamp_us, &payload)
+    }
+
+    pub fn set_metadata(
+        &mut self,
+        entry_id: u32,
+        metadata: &str,
+        timestamp_us: u64,
+    ) -> io::Result<()> {
+        let mut payload = vec![2];
+        payload.extend(entry_id.to_le_bytes());
+        payload.extend((metadata.len() as u32).to_le_bytes());
+        payload.extend(metadata.as_bytes());
+
+        self.write_record(0, timestamp_us, &payload)
+    }
+
+    /// Writes a data record with an already encoded payload.
+    pub fn append(&mut self, entry_id: u32, timestamp_us: u64, data: &[u8]) -> io::Result<()> {
+        self.write_record(entry_id, timestamp_us, data)
+    }
+
+    pub fn append_value(
+        &mut self,
+        entry_id: u32,
+        timestamp_us: u64,
+        value: &DataType,
+    ) -> io::Result<()> {
+        self.write_record(entry_id, timestamp_us, &encode_data(value))
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn write_record(&mut self, entry_id: u32, timestamp_us: u64, payload: &[u8]) -> io::Result<()> {
+        let entry_id_len = byte_len(entry_id as u64, 4);
+        let payload_size_len = byte_len(payload.len() as u64, 4);
+        let timestamp_len = byte_len(timestamp_us, 8);
+
+        let mut header = Vec::with_capacity(17);
+        header.push(
+            (entry_id_len - 1) as u8
+                | ((payload_size_len - 1) as u8) << 2
+                | ((timestamp_len - 1) as u8) << 4,
+        );
+        header.extend(&entry_id.to_le_bytes()[..entry_id_len]);
+        header.extend(&(payload.len() as u32).to_le_bytes()[..payload_size_len]);
+        header.extend(&timestamp_us.to_le_bytes()[..timestamp_len]);
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(payload)
+    }
+}
+
+/// Encodes a value as the payload of a data record.
+pub fn encode_data(value: &DataType) -> Vec<u8> {
+    match value {
+        DataType::Raw(val) => val.to_vec(),
+        DataType::Boolean(val) => vec![*val as u8],
+        DataType::Int64(val) => val.to_le_bytes().to_vec(),
+        DataType::Float(val) => val.to_le_bytes().to_vec(),
+        DataType::Double(val) => val.to_le_bytes().to_vec(),
+        DataType::String(val) => val.as_bytes().to_vec(),
+        DataType::BooleanArray(val) => val.iter().map(|val| *val as u8).collect(),
+        DataType::Int64Array(val) => val.iter().flat_map(|val| val.to_le_bytes()).collect(),
+        DataType::FloatArray(val) => val.iter().flat_map(|val| val.to_le_bytes()).collect(),
+        DataType::DoubleArray(val) => val.iter().flat_map(|val| val.to_le_bytes()).collect(),
+        DataType::StringArray(val) => {
+            let mut data = (val.len() as u32).to_le_bytes().to_vec();
+            for val in val {
+                data.extend((val.len() as u32).to_le_bytes());
+                data.extend(val.as_bytes());
+            }
+            data
+        }
+    }
+}
+
+/// Number of little endian bytes needed for `value`, at least 1 and at most `max`.
+fn byte_len(value: u64, max: usize) -> usize {
+    let bytes = (64 - value.leading_zeros() as usize).div_ceil(8);
+    bytes.clamp(1, max)
+}
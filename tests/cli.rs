@@ -0,0 +1,360 @@
+//! Runs the `wpilog` binary on logs written to a temporary directory and checks its output and
+//! exit codes.
+#![cfg(feature = "cli")]
+
+use std::{collections::HashMap, fs, path::PathBuf, process::Command};
+
+use serde_json::Value;
+use wpilog_reader::{mcap::writer::McapWriter, wpilog::writer::WpiLogWriter};
+
+/// A directory of its own for each test, holding `a.wpilog` and `b.wpilog`.
+fn logs(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wpilog-cli-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for (name, offset) in [("a", 0), ("b", 1_000_000)] {
+        let mut log = WpiLogWriter::new(Vec::new(), "").unwrap();
+        let speed = log.start("/speed", "double", "", offset).unwrap();
+        for i in 0..3 {
+            log.append(speed, offset + i * 1000, &(i as f64).to_le_bytes())
+                .unwrap();
+        }
+        fs::write(
+            dir.join(format!("{}.wpilog", name)),
+            log.into_inner().unwrap(),
+        )
+        .unwrap();
+    }
+
+    dir
+}
+
+fn wpilog(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_wpilog"))
+        .args(args)
+        .output()
+        .unwrap();
+
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn info_expands_globs() {
+    let dir = logs("info");
+    let pattern = dir.join("*.wpilog");
+
+    let (code, stdout) = wpilog(&["info", "--json", pattern.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+
+    let infos: Value = serde_json::from_str(&stdout).unwrap();
+    let summary = infos
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|info| (info["samples"].as_u64(), info["start_us"].as_u64()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![(Some(3), Some(0)), (Some(3), Some(1_000_000))]
+    );
+
+    let missing = dir.join("*.mcap");
+    assert_eq!(wpilog(&["info", missing.to_str().unwrap()]).0, Some(2));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn merges_and_validates() {
+    let dir = logs("merge");
+    let merged = dir.join("merged.wpilog");
+
+    let (code, _) = wpilog(&[
+        "merge",
+        dir.join("a.wpilog").to_str().unwrap(),
+        dir.join("b.wpilog").to_str().unwrap(),
+        "-o",
+        merged.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(0));
+
+    let (code, stdout) = wpilog(&["entries", "--json", merged.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let files: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(files[0]["entries"][0]["samples"], 6);
+    assert_eq!(files[0]["entries"][0]["min"], 0.0);
+    assert_eq!(files[0]["entries"][0]["max"], 2.0);
+    assert_eq!(files[0]["entries"][0]["mean"], 1.0);
+
+    let broken = dir.join("broken.wpilog");
+    fs::write(&broken, b"WPILOG").unwrap();
+    assert_eq!(wpilog(&["validate", broken.to_str().unwrap()]).0, Some(1));
+    assert_eq!(wpilog(&["validate", merged.to_str().unwrap()]).0, Some(0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn exports_csv_and_jsonl() {
+    let dir = logs("export");
+    let input = dir.join("a.wpilog");
+    let out = dir.join("out");
+
+    let (code, stdout) = wpilog(&[
+        "export",
+        "csv",
+        "--json",
+        "--out-dir",
+        out.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(0));
+    let exported: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(exported[0]["outputs"].as_array().unwrap().len(), 3);
+
+    // one row per sample, the start of /speed gets none
+    assert_eq!(
+        fs::read_to_string(out.join("a-data.csv")).unwrap(),
+        "timestamp,/speed\n0,0\n0.001,1\n0.002,2\n"
+    );
+    assert_eq!(
+        fs::read_to_string(out.join("a-types.csv")).unwrap(),
+        "timestamp,/speed\n0,double\n"
+    );
+
+    let (code, _) = wpilog(&[
+        "export",
+        "jsonl",
+        "--control",
+        "--out-dir",
+        out.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(0));
+    let samples = fs::read_to_string(out.join("a.jsonl"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(samples.len(), 3);
+    assert_eq!(samples[2]["value"], 2.0);
+    assert_eq!(
+        fs::read_to_string(out.join("a-control.jsonl"))
+            .unwrap()
+            .lines()
+            .count(),
+        1
+    );
+
+    let broken = dir.join("broken.wpilog");
+    fs::write(&broken, b"WPILOG").unwrap();
+    let (code, stdout) = wpilog(&[
+        "export",
+        "csv",
+        "--json",
+        "--out-dir",
+        out.to_str().unwrap(),
+        broken.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(1));
+    let exported: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(exported.as_array().unwrap().len(), 1);
+
+    let (code, _) = wpilog(&[
+        "export",
+        "csv",
+        "--include",
+        "re:(",
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(2));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn exports_parquet() {
+    let dir = logs("parquet");
+    let out = dir.join("out");
+    let pattern = dir.join("*.wpilog");
+
+    let (code, stdout) = wpilog(&[
+        "export",
+        "parquet",
+        "--json",
+        "--out-dir",
+        out.to_str().unwrap(),
+        pattern.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(0));
+    let exported: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(exported.as_array().unwrap().len(), 2);
+
+    for name in ["a.parquet", "b.parquet"] {
+        let content = fs::read(out.join(name)).unwrap();
+        assert!(content.starts_with(b"PAR1") && content.ends_with(b"PAR1"));
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn slices() {
+    let dir = logs("slice");
+    let input = dir.join("b.wpilog");
+    let sliced = dir.join("sliced.wpilog");
+
+    let (code, stdout) = wpilog(&[
+        "slice",
+        "--json",
+        "--start",
+        "1.001",
+        "--end",
+        "1.002",
+        "-o",
+        sliced.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(0));
+    let written: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(written["output"], sliced.to_str().unwrap());
+
+    let (code, stdout) = wpilog(&["info", "--json", sliced.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let infos: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(infos[0]["samples"], 2);
+
+    let (code, _) = wpilog(&[
+        "slice",
+        "--start",
+        "2",
+        "--end",
+        "1",
+        "-o",
+        sliced.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(2));
+    assert_eq!(wpilog(&["slice", input.to_str().unwrap()]).0, Some(2));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn converts_and_inspects_mcap() {
+    let dir = logs("convert");
+    let input = dir.join("a.wpilog");
+    let mcap = dir.join("a.mcap");
+    let back = dir.join("back.wpilog");
+
+    let (code, stdout) = wpilog(&["convert", "--json", input.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let written: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(written["output"], mcap.to_str().unwrap());
+
+    let (code, stdout) = wpilog(&["mcap", "info", "--json", mcap.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let infos: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(infos[0]["messages"], 3);
+    assert_eq!(infos[0]["channels"][0]["topic"], "/speed");
+
+    let (code, stdout) = wpilog(&["mcap", "validate", "--json", mcap.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let reports: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(reports[0]["valid"], true);
+
+    let (code, _) = wpilog(&[
+        "convert",
+        mcap.to_str().unwrap(),
+        "-o",
+        back.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(0));
+    let (code, stdout) = wpilog(&["entries", "--json", back.to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let files: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(files[0]["entries"][0]["name"], "/speed");
+    assert_eq!(files[0]["entries"][0]["samples"], 3);
+
+    // a wpilog isn't an MCAP file, and converting it onto itself is refused
+    assert_eq!(
+        wpilog(&["mcap", "validate", input.to_str().unwrap()]).0,
+        Some(1)
+    );
+    assert_eq!(
+        wpilog(&["mcap", "info", input.to_str().unwrap()]).0,
+        Some(1)
+    );
+    assert_eq!(
+        wpilog(&[
+            "convert",
+            input.to_str().unwrap(),
+            "-o",
+            input.to_str().unwrap()
+        ])
+        .0,
+        Some(2)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mcap_attachments_and_metadata() {
+    let dir = logs("attachments");
+    let path = dir.join("a.mcap");
+    let mut mcap = McapWriter::new(Vec::new()).unwrap();
+    for name in ["a/cal.json", "b/cal.json", "b/cal.json"] {
+        mcap.write_attachment(1, 2, name, "application/json", b"{}")
+            .unwrap();
+    }
+    mcap.write_metadata("run", &HashMap::from([("team", "1234")]))
+        .unwrap();
+    fs::write(&path, mcap.finish().unwrap()).unwrap();
+    let path = path.to_str().unwrap();
+
+    let (code, stdout) = wpilog(&["mcap", "attachments", "--json", path]);
+    assert_eq!(code, Some(0));
+    let attachments: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(attachments[1]["name"], "b/cal.json");
+    assert_eq!(attachments[1]["size"], 2);
+
+    // the second b/cal.json would overwrite the first
+    let out = dir.join("out");
+    let (code, stdout) = wpilog(&[
+        "mcap",
+        "attachments",
+        "--json",
+        "--extract",
+        out.to_str().unwrap(),
+        path,
+    ]);
+    assert_eq!(code, Some(1));
+    let extracted: Value = serde_json::from_str(&stdout).unwrap();
+    assert!(extracted[1]["error"].is_null());
+    assert!(extracted[2]["path"].is_null());
+    assert!(out.join("a").join("cal.json").exists());
+    assert!(out.join("b").join("cal.json").exists());
+
+    let (code, stdout) = wpilog(&["mcap", "metadata", "--json", path]);
+    assert_eq!(code, Some(0));
+    let files: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(
+        files[0]["metadata"],
+        serde_json::json!([{ "name": "run", "metadata": { "team": "1234" } }])
+    );
+
+    let wpilog_path = dir.join("a.wpilog");
+    assert_eq!(
+        wpilog(&["mcap", "metadata", wpilog_path.to_str().unwrap()]).0,
+        Some(1)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
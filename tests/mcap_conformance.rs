@@ -0,0 +1,771 @@
+//! Parses MCAP records from byte fixtures written out by hand from the specification, and checks
+//! that encoding the parsed records reproduces the same bytes.
+
+use std::borrow::Cow;
+
+use wpilog_reader::mcap::{MCap, Message, Parse, RawRecord, Record, MAGIC};
+
+/// Frames record content with its opcode and u64 length prefix.
+fn record(op: u8, fields: &[&[u8]]) -> Vec<u8> {
+    let content = fields.concat();
+
+    let mut data = vec![op];
+    data.extend((content.len() as u64).to_le_bytes());
+    data.extend(content);
+    data
+}
+
+/// Parses a single framed record, requiring the whole fixture to be consumed, and checks that it
+/// encodes back to the fixture.
+fn parse(fixture: &[u8]) -> Record<'_> {
+    let (rest, parsed) = Record::parse(fixture).unwrap();
+    assert!(rest.is_empty(), "{} bytes left over", rest.len());
+
+    let mut encoded = Vec::new();
+    parsed.encode(&mut encoded);
+    assert_eq!(
+        encoded, fixture,
+        "{:?} doesn't encode to its fixture",
+        parsed
+    );
+
+    parsed
+}
+
+fn rejects(fixture: &[u8]) {
+    assert!(
+        Record::parse(fixture).is_err(),
+        "parsed {:?}",
+        Record::parse(fixture)
+    );
+}
+
+#[test]
+fn header() {
+    let fixture = record(0x01, &[b"\x04\0\0\0ros2", b"\x09\0\0\0mcap-test"]);
+    let Record::Header(header) = parse(&fixture) else {
+        panic!("not a header");
+    };
+    assert_eq!(header.profile(), "ros2");
+    assert_eq!(header.library(), "mcap-test");
+
+    let fixture = record(0x01, &[b"\0\0\0\0", b"\0\0\0\0"]);
+    let Record::Header(header) = parse(&fixture) else {
+        panic!("not a header");
+    };
+    assert_eq!(header.profile(), "");
+    assert_eq!(header.library(), "");
+}
+
+#[test]
+fn footer() {
+    let fixture = record(
+        0x02,
+        &[
+            &0x1234u64.to_le_bytes(),
+            &0x5678u64.to_le_bytes(),
+            &0xDEADBEEFu32.to_le_bytes(),
+        ],
+    );
+    let Record::Footer(footer) = parse(&fixture) else {
+        panic!("not a footer");
+    };
+    assert_eq!(footer.summary_start(), 0x1234);
+    assert_eq!(footer.summary_offset_start(), 0x5678);
+    assert_eq!(footer.summary_crc(), 0xDEADBEEF);
+}
+
+#[test]
+fn schema() {
+    let fixture = record(
+        0x03,
+        &[
+            b"\x01\0",
+            b"\x0a\0\0\0pose.Point",
+            b"\x0a\0\0\0jsonschema",
+            b"\x02\0\0\0{}",
+        ],
+    );
+    let Record::Schema(schema) = parse(&fixture) else {
+        panic!("not a schema");
+    };
+    assert_eq!(schema.id(), 1);
+    assert_eq!(schema.name(), "pose.Point");
+    assert_eq!(schema.encoding(), "jsonschema");
+    assert_eq!(schema.data(), b"{}");
+
+    // the data length is a u32, not a u64
+    let fixture = record(0x03, &[b"\x02\0", b"\0\0\0\0", b"\0\0\0\0", b"\0\0\0\0"]);
+    let Record::Schema(schema) = parse(&fixture) else {
+        panic!("not a schema");
+    };
+    assert_eq!(schema.name(), "");
+    assert_eq!(schema.data(), b"");
+}
+
+#[test]
+fn channel() {
+    let fixture = record(
+        0x04,
+        &[
+            b"\x03\0",
+            b"\x01\0",
+            b"\x04\0\0\0/imu",
+            b"\x04\0\0\0json",
+            // one entry: 4 + 3 + 4 + 5 bytes
+            b"\x10\0\0\0",
+            b"\x03\0\0\0key",
+            b"\x05\0\0\0value",
+        ],
+    );
+    let Record::Channel(channel) = parse(&fixture) else {
+        panic!("not a channel");
+    };
+    assert_eq!(channel.id(), 3);
+    assert_eq!(channel.schema_id(), 1);
+    assert_eq!(channel.topic(), "/imu");
+    assert_eq!(channel.message_encoding(), "json");
+    assert_eq!(channel.metadata().len(), 1);
+    assert_eq!(channel.metadata()[&Cow::Borrowed("key")], "value");
+
+    let fixture = record(
+        0x04,
+        &[b"\x04\0", b"\0\0", b"\0\0\0\0", b"\0\0\0\0", b"\0\0\0\0"],
+    );
+    let Record::Channel(channel) = parse(&fixture) else {
+        panic!("not a channel");
+    };
+    assert_eq!(channel.schema_id(), 0);
+    assert_eq!(channel.topic(), "");
+    assert!(channel.metadata().is_empty());
+
+    // an empty key and value still take up their length prefixes
+    let fixture = record(
+        0x04,
+        &[
+            b"\x05\0",
+            b"\0\0",
+            b"\x02\0\0\0/a",
+            b"\0\0\0\0",
+            b"\x08\0\0\0",
+            b"\0\0\0\0",
+            b"\0\0\0\0",
+        ],
+    );
+    let Record::Channel(channel) = parse(&fixture) else {
+        panic!("not a channel");
+    };
+    assert_eq!(channel.metadata()[&Cow::Borrowed("")], "");
+}
+
+#[test]
+fn channel_metadata_must_fit_its_length() {
+    rejects(&record(
+        0x04,
+        &[
+            b"\x03\0",
+            b"\0\0",
+            b"\0\0\0\0",
+            b"\0\0\0\0",
+            // claims 4 bytes but holds a whole entry
+            b"\x04\0\0\0",
+            b"\x01\0\0\0k",
+            b"\x01\0\0\0v",
+        ],
+    ));
+}
+
+#[test]
+fn message() {
+    let fixture = record(
+        0x05,
+        &[
+            b"\x03\0",
+            &7u32.to_le_bytes(),
+            &1_000u64.to_le_bytes(),
+            &2_000u64.to_le_bytes(),
+            b"{\"x\":1}",
+        ],
+    );
+    let Record::Message(message) = parse(&fixture) else {
+        panic!("not a message");
+    };
+    assert_eq!(message.channel_id(), 3);
+    assert_eq!(message.sequence(), 7);
+    assert_eq!(message.log_time(), 1_000);
+    assert_eq!(message.publish_time(), 2_000);
+    assert_eq!(message.data(), b"{\"x\":1}");
+
+    let fixture = record(0x05, &[b"\x03\0", &[0; 4], &[0; 8], &[0; 8]]);
+    let Record::Message(message) = parse(&fixture) else {
+        panic!("not a message");
+    };
+    assert_eq!(message.data(), b"");
+}
+
+#[test]
+fn message_payload_consumes_the_body() {
+    let content = [&b"\x01\0"[..], &[0; 4], &[0; 8], &[0; 8], b"payload"].concat();
+    let (rest, message) = Message::parse(&content).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(message.data(), b"payload");
+
+    // a following record is left for the next parser
+    let mut data = record(0x05, &[&content]);
+    data.extend(record(0x0F, &[&[0; 4]]));
+    let (rest, Record::Message(message)) = Record::parse(&data).unwrap() else {
+        panic!("not a message");
+    };
+    assert_eq!(message.data(), b"payload");
+    assert!(matches!(Record::parse(rest), Ok((b"", Record::DataEnd(_)))));
+
+    // shorter than the fixed fields
+    rejects(&record(0x05, &[b"\x01\0", &[0; 4], &[0; 8]]));
+}
+
+#[test]
+fn chunk() {
+    let inner = record(0x0F, &[&[0; 4]]);
+    let fixture = record(
+        0x06,
+        &[
+            &10u64.to_le_bytes(),
+            &20u64.to_le_bytes(),
+            &(inner.len() as u64).to_le_bytes(),
+            &crc32fast::hash(&inner).to_le_bytes(),
+            b"\0\0\0\0",
+            &(inner.len() as u64).to_le_bytes(),
+            &inner,
+        ],
+    );
+    let Record::Chunk(chunk) = parse(&fixture) else {
+        panic!("not a chunk");
+    };
+    assert_eq!(chunk.message_start_time(), 10);
+    assert_eq!(chunk.message_end_time(), 20);
+    assert_eq!(chunk.uncompressed_size(), inner.len() as u64);
+    assert_eq!(chunk.uncompressed_crc(), crc32fast::hash(&inner));
+    assert_eq!(chunk.compression(), "");
+    assert_eq!(chunk.records(), &inner[..]);
+
+    let (_, records) = chunk.parse_inner().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].tag, 0x0F);
+
+    // a chunk with no records
+    let fixture = record(
+        0x06,
+        &[&[0; 8], &[0; 8], &[0; 8], &[0; 4], b"\0\0\0\0", &[0; 8]],
+    );
+    let Record::Chunk(chunk) = parse(&fixture) else {
+        panic!("not a chunk");
+    };
+    assert_eq!(chunk.parse_inner().unwrap().1.len(), 0);
+    assert_eq!(chunk.decompress().unwrap(), &b""[..]);
+}
+
+#[test]
+fn chunk_records_must_fit_the_chunk() {
+    rejects(&record(
+        0x06,
+        &[
+            &[0; 8],
+            &[0; 8],
+            &[0; 8],
+            &[0; 4],
+            b"\0\0\0\0",
+            &16u64.to_le_bytes(),
+            &[0; 8],
+        ],
+    ));
+}
+
+#[test]
+fn message_index() {
+    let fixture = record(
+        0x07,
+        &[
+            b"\x02\0",
+            &32u32.to_le_bytes(),
+            &100u64.to_le_bytes(),
+            &0u64.to_le_bytes(),
+            &200u64.to_le_bytes(),
+            &31u64.to_le_bytes(),
+        ],
+    );
+    let Record::MessageIndex(index) = parse(&fixture) else {
+        panic!("not a message index");
+    };
+    assert_eq!(index.channel_id(), 2);
+    assert_eq!(index.records(), &[(100, 0), (200, 31)]);
+
+    let fixture = record(0x07, &[b"\x02\0", b"\0\0\0\0"]);
+    let Record::MessageIndex(index) = parse(&fixture) else {
+        panic!("not a message index");
+    };
+    assert!(index.records().is_empty());
+}
+
+#[test]
+fn message_index_records_must_fit_their_length() {
+    rejects(&record(
+        0x07,
+        &[
+            b"\x02\0",
+            // a whole (log time, offset) entry and one byte of the next
+            &17u32.to_le_bytes(),
+            &100u64.to_le_bytes(),
+            &0u64.to_le_bytes(),
+            b"\x01",
+        ],
+    ));
+}
+
+#[test]
+fn chunk_index() {
+    let fixture = record(
+        0x08,
+        &[
+            &10u64.to_le_bytes(),
+            &20u64.to_le_bytes(),
+            &8u64.to_le_bytes(),
+            &100u64.to_le_bytes(),
+            // one entry: a u16 channel id and a u64 offset
+            &10u32.to_le_bytes(),
+            b"\x01\0",
+            &108u64.to_le_bytes(),
+            &40u64.to_le_bytes(),
+            b"\x04\0\0\0zstd",
+            &60u64.to_le_bytes(),
+            &80u64.to_le_bytes(),
+        ],
+    );
+    let Record::ChunkIndex(index) = parse(&fixture) else {
+        panic!("not a chunk index");
+    };
+    assert_eq!(index.message_start_time(), 10);
+    assert_eq!(index.message_end_time(), 20);
+    assert_eq!(index.chunk_start_offset(), 8);
+    assert_eq!(index.chunk_length(), 100);
+    assert_eq!(index.message_index_offsets().len(), 1);
+    assert_eq!(index.message_index_offsets()[&1], 108);
+    assert_eq!(index.message_index_length(), 40);
+    assert_eq!(index.compression(), "zstd");
+    assert_eq!(index.compressed_size(), 60);
+    assert_eq!(index.uncompressed_size(), 80);
+
+    let fixture = record(
+        0x08,
+        &[
+            &[0; 8],
+            &[0; 8],
+            &[0; 8],
+            &[0; 8],
+            b"\0\0\0\0",
+            &[0; 8],
+            b"\0\0\0\0",
+            &[0; 8],
+            &[0; 8],
+        ],
+    );
+    let Record::ChunkIndex(index) = parse(&fixture) else {
+        panic!("not a chunk index");
+    };
+    assert!(index.message_index_offsets().is_empty());
+    assert_eq!(index.compression(), "");
+}
+
+#[test]
+fn attachment() {
+    let fixture = record(
+        0x09,
+        &[
+            &1u64.to_le_bytes(),
+            &2u64.to_le_bytes(),
+            b"\x07\0\0\0map.png",
+            b"\x09\0\0\0image/png",
+            &4u64.to_le_bytes(),
+            b"\x89PNG",
+            &0xCAFEF00Du32.to_le_bytes(),
+        ],
+    );
+    let Record::Attachment(attachment) = parse(&fixture) else {
+        panic!("not an attachment");
+    };
+    assert_eq!(attachment.log_time(), 1);
+    assert_eq!(attachment.create_time(), 2);
+    assert_eq!(attachment.name(), "map.png");
+    assert_eq!(attachment.media_type(), "image/png");
+    assert_eq!(attachment.data(), b"\x89PNG");
+    assert_eq!(attachment.crc(), 0xCAFEF00D);
+
+    let fixture = record(
+        0x09,
+        &[&[0; 8], &[0; 8], b"\0\0\0\0", b"\0\0\0\0", &[0; 8], &[0; 4]],
+    );
+    let Record::Attachment(attachment) = parse(&fixture) else {
+        panic!("not an attachment");
+    };
+    assert_eq!(attachment.name(), "");
+    assert_eq!(attachment.data(), b"");
+}
+
+#[test]
+fn attachment_index() {
+    let fixture = record(
+        0x0A,
+        &[
+            &500u64.to_le_bytes(),
+            &80u64.to_le_bytes(),
+            &1u64.to_le_bytes(),
+            &2u64.to_le_bytes(),
+            &4u64.to_le_bytes(),
+            b"\x07\0\0\0map.png",
+            b"\x09\0\0\0image/png",
+        ],
+    );
+    let Record::AttachmentIndex(index) = parse(&fixture) else {
+        panic!("not an attachment index");
+    };
+    assert_eq!(index.offset(), 500);
+    assert_eq!(index.length(), 80);
+    assert_eq!(index.log_time(), 1);
+    assert_eq!(index.create_time(), 2);
+    assert_eq!(index.data_size(), 4);
+    assert_eq!(index.name(), "map.png");
+    assert_eq!(index.media_type(), "image/png");
+}
+
+#[test]
+fn statistics() {
+    let fixture = record(
+        0x0B,
+        &[
+            &3u64.to_le_bytes(),
+            b"\x01\0",
+            &2u32.to_le_bytes(),
+            &1u32.to_le_bytes(),
+            &0u32.to_le_bytes(),
+            &1u32.to_le_bytes(),
+            &10u64.to_le_bytes(),
+            &30u64.to_le_bytes(),
+            // one entry: a u16 channel id and a u64 count
+            &10u32.to_le_bytes(),
+            b"\x01\0",
+            &3u64.to_le_bytes(),
+        ],
+    );
+    let Record::Statistics(statistics) = parse(&fixture) else {
+        panic!("not statistics");
+    };
+    assert_eq!(statistics.message_count(), 3);
+    assert_eq!(statistics.schema_count(), 1);
+    assert_eq!(statistics.channel_count(), 2);
+    assert_eq!(statistics.attachment_count(), 1);
+    assert_eq!(statistics.metadata_count(), 0);
+    assert_eq!(statistics.chunk_count(), 1);
+    assert_eq!(statistics.message_start_time(), 10);
+    assert_eq!(statistics.message_end_time(), 30);
+    assert_eq!(statistics.channel_message_counts()[&1], 3);
+
+    let fixture = record(
+        0x0B,
+        &[
+            &[0; 8],
+            &[0; 2],
+            &[0; 4],
+            &[0; 4],
+            &[0; 4],
+            &[0; 4],
+            &[0; 8],
+            &[0; 8],
+            b"\0\0\0\0",
+        ],
+    );
+    let Record::Statistics(statistics) = parse(&fixture) else {
+        panic!("not statistics");
+    };
+    assert!(statistics.channel_message_counts().is_empty());
+}
+
+#[test]
+fn metadata() {
+    let fixture = record(
+        0x0C,
+        &[
+            b"\x05\0\0\0robot",
+            b"\x11\0\0\0",
+            b"\x04\0\0\0team",
+            b"\x05\0\0\x0012345",
+        ],
+    );
+    let Record::Metadata(metadata) = parse(&fixture) else {
+        panic!("not metadata");
+    };
+    assert_eq!(metadata.name(), "robot");
+    assert_eq!(metadata.metadata()[&Cow::Borrowed("team")], "12345");
+
+    let fixture = record(0x0C, &[b"\0\0\0\0", b"\0\0\0\0"]);
+    let Record::Metadata(metadata) = parse(&fixture) else {
+        panic!("not metadata");
+    };
+    assert_eq!(metadata.name(), "");
+    assert!(metadata.metadata().is_empty());
+}
+
+#[test]
+fn metadata_index() {
+    let fixture = record(
+        0x0D,
+        &[
+            &700u64.to_le_bytes(),
+            &40u64.to_le_bytes(),
+            b"\x05\0\0\0robot",
+        ],
+    );
+    let Record::MetadataIndex(index) = parse(&fixture) else {
+        panic!("not a metadata index");
+    };
+    assert_eq!(index.offset(), 700);
+    assert_eq!(index.length(), 40);
+    assert_eq!(index.name(), "robot");
+}
+
+#[test]
+fn summary_offset() {
+    let fixture = record(
+        0x0E,
+        &[b"\x04", &900u64.to_le_bytes(), &120u64.to_le_bytes()],
+    );
+    let Record::SummaryOffset(offset) = parse(&fixture) else {
+        panic!("not a summary offset");
+    };
+    assert_eq!(offset.group_opcode(), 0x04);
+    assert_eq!(offset.group_start(), 900);
+    assert_eq!(offset.group_length(), 120);
+}
+
+#[test]
+fn data_end() {
+    let fixture = record(0x0F, &[&0x01020304u32.to_le_bytes()]);
+    let Record::DataEnd(data_end) = parse(&fixture) else {
+        panic!("not a data end");
+    };
+    assert_eq!(data_end.data_section_crc(), 0x01020304);
+}
+
+#[test]
+fn unknown_opcodes_are_kept() {
+    for op in [0x00, 0x10, 0x80, 0xFF] {
+        let fixture = record(op, &[b"custom"]);
+        let Record::Unknown {
+            op: parsed,
+            content,
+        } = parse(&fixture)
+        else {
+            panic!("{:#04x} isn't unknown", op);
+        };
+        assert_eq!(parsed, op);
+        assert_eq!(&*content, b"custom");
+    }
+}
+
+#[test]
+fn trailing_content_is_rejected() {
+    rejects(&record(0x0F, &[&[0; 4], b"x"]));
+    rejects(&record(0x01, &[b"\0\0\0\0", b"\0\0\0\0", b"x"]));
+    rejects(&record(0x0E, &[b"\x04", &[0; 8], &[0; 8], b"x"]));
+}
+
+#[test]
+fn truncated_records_are_rejected() {
+    // shorter than its length prefix
+    let mut fixture = record(0x0F, &[&[0; 4]]);
+    fixture.pop();
+    rejects(&fixture);
+
+    // a string running past the record
+    rejects(&record(0x01, &[b"\x10\0\0\0ros2", b"\0\0\0\0"]));
+}
+
+/// Builds a file with two uncompressed chunks on one channel, each followed by its message index,
+/// then a summary section with the channel and chunk indexes.
+fn multi_chunk_file() -> Vec<u8> {
+    let channel = record(
+        0x04,
+        &[
+            b"\x01\0",
+            b"\0\0",
+            b"\x02\0\0\0/a",
+            b"\0\0\0\0",
+            b"\0\0\0\0",
+        ],
+    );
+    let message = |log_time: u64, data: &[u8]| {
+        record(
+            0x05,
+            &[
+                b"\x01\0",
+                &0u32.to_le_bytes(),
+                &log_time.to_le_bytes(),
+                &log_time.to_le_bytes(),
+                data,
+            ],
+        )
+    };
+
+    let mut file = MAGIC.to_vec();
+    file.extend(record(0x01, &[b"\0\0\0\0", b"\0\0\0\0"]));
+
+    let mut chunk_indexes = Vec::new();
+    for (start, end, messages) in [
+        (10u64, 20u64, vec![message(10, b"a"), message(20, b"b")]),
+        (15, 30, vec![message(15, b"c"), message(30, b"d")]),
+    ] {
+        let mut records = channel.clone();
+        let mut offsets = Vec::new();
+        for message in &messages {
+            offsets.push(records.len() as u64);
+            records.extend(message);
+        }
+
+        let chunk_start = file.len() as u64;
+        file.extend(record(
+            0x06,
+            &[
+                &start.to_le_bytes(),
+                &end.to_le_bytes(),
+                &(records.len() as u64).to_le_bytes(),
+                &crc32fast::hash(&records).to_le_bytes(),
+                b"\0\0\0\0",
+                &(records.len() as u64).to_le_bytes(),
+                &records,
+            ],
+        ));
+        let chunk_length = file.len() as u64 - chunk_start;
+
+        let message_index_start = file.len() as u64;
+        file.extend(record(
+            0x07,
+            &[
+                b"\x01\0",
+                &32u32.to_le_bytes(),
+                &start.to_le_bytes(),
+                &offsets[0].to_le_bytes(),
+                &end.to_le_bytes(),
+                &offsets[1].to_le_bytes(),
+            ],
+        ));
+        let message_index_length = file.len() as u64 - message_index_start;
+
+        chunk_indexes.push(record(
+            0x08,
+            &[
+                &start.to_le_bytes(),
+                &end.to_le_bytes(),
+                &chunk_start.to_le_bytes(),
+                &chunk_length.to_le_bytes(),
+                &10u32.to_le_bytes(),
+                b"\x01\0",
+                &message_index_start.to_le_bytes(),
+                &message_index_length.to_le_bytes(),
+                b"\0\0\0\0",
+                &(records.len() as u64).to_le_bytes(),
+                &(records.len() as u64).to_le_bytes(),
+            ],
+        ));
+    }
+
+    file.extend(record(0x0F, &[&crc32fast::hash(&file).to_le_bytes()]));
+
+    let summary_start = file.len() as u64;
+    file.extend(&channel);
+    for chunk_index in chunk_indexes {
+        file.extend(chunk_index);
+    }
+
+    // the summary crc covers the footer up to the crc itself
+    file.push(0x02);
+    file.extend(20u64.to_le_bytes());
+    file.extend(summary_start.to_le_bytes());
+    file.extend(0u64.to_le_bytes());
+    let summary_crc = crc32fast::hash(&file[summary_start as usize..]);
+    file.extend(summary_crc.to_le_bytes());
+    file.extend(MAGIC);
+
+    file
+}
+
+#[test]
+fn multiple_chunks() {
+    let file = multi_chunk_file();
+    let (rest, mcap) = MCap::parse(&file).unwrap();
+    assert!(rest.is_empty());
+
+    let ops = mcap.records.iter().map(|raw| raw.tag).collect::<Vec<_>>();
+    assert_eq!(
+        ops,
+        vec![0x01, 0x06, 0x07, 0x06, 0x07, 0x0F, 0x04, 0x08, 0x08, 0x02]
+    );
+
+    let mut data = Vec::new();
+    for raw in &mcap.records {
+        if let Record::Chunk(chunk) = Record::try_from(*raw).unwrap() {
+            let (rest, records) = chunk.parse_inner().unwrap();
+            assert!(rest.is_empty());
+            for raw in records {
+                if let Record::Message(message) = Record::try_from(raw).unwrap() {
+                    data.push(message.data().to_vec());
+                }
+            }
+        }
+    }
+    assert_eq!(
+        data,
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+    );
+
+    let log_times = mcap
+        .messages()
+        .ordered()
+        .map(|event| event.unwrap().message.log_time())
+        .collect::<Vec<_>>();
+    assert_eq!(log_times, vec![10, 15, 20, 30]);
+
+    assert_eq!(
+        wpilog_reader::mcap::validate::check_crcs(&file).unwrap(),
+        vec![]
+    );
+}
+
+#[test]
+fn multiple_chunks_are_indexed() {
+    let file = multi_chunk_file();
+    let mut reader =
+        wpilog_reader::mcap::indexed::IndexedMcapReader::new(std::io::Cursor::new(file)).unwrap();
+
+    assert_eq!(reader.chunk_indexes().len(), 2);
+    let messages = reader
+        .read_messages(15..21, None)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        messages
+            .iter()
+            .map(|message| message.data())
+            .collect::<Vec<_>>(),
+        vec![&b"c"[..], &b"b"[..]]
+    );
+}
+
+#[test]
+fn raw_records_split_on_length() {
+    let mut data = record(0x10, &[b"one"]);
+    data.extend(record(0x11, &[]));
+
+    let (rest, first) = RawRecord::parse(&data).unwrap();
+    assert_eq!((first.tag, first.content), (0x10, &b"one"[..]));
+    let (rest, second) = RawRecord::parse(rest).unwrap();
+    assert_eq!((second.tag, second.content), (0x11, &b""[..]));
+    assert!(rest.is_empty());
+}